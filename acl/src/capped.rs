@@ -0,0 +1,96 @@
+//! ACLs that cap the value (and optionally the fee and gas price) of transactions to each allowed recipient.
+
+use alloc::{format, string::ToString, vec::Vec};
+
+use codec::{Decode, Encode};
+use entropy_programs_core::{
+    Architecture, Error as CoreError, GetFee, GetValue, SatisfiableForArchitecture,
+};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::single_receiver;
+
+/// An allow list mapping each recipient to the maximum value that can be sent to it. Recipients without a
+/// cap are not allowed, and if a recipient has several caps the first one applies.
+#[derive(Clone, Debug, Encode, Decode, PartialEq, Eq, TypeInfo, Serialize, Deserialize)]
+pub struct CappedAcl<Address, Value, Fee> {
+    pub caps: Vec<Cap<Address, Value, Fee>>,
+}
+
+/// The maximum value (and optionally fee and gas price) of transactions to a single recipient.
+#[derive(Clone, Debug, Encode, Decode, PartialEq, Eq, TypeInfo, Serialize, Deserialize)]
+pub struct Cap<Address, Value, Fee> {
+    /// The recipient, or `None` to cap transactions without one (eg. EVM contract creations).
+    pub address: Option<Address>,
+    /// Inclusive maximum value of a transaction to the recipient.
+    pub max_value: Value,
+    /// Inclusive maximum total fee of a transaction to the recipient (see [`GetFee::fee`]), eg. gas limit × max
+    /// fee per gas (plus the blob fee) for EVM.
+    #[serde(default = "Option::default")]
+    pub max_fee: Option<Fee>,
+    /// Inclusive maximum gas price of a transaction to the recipient (see [`GetFee::gas_price`]), eg. the max
+    /// fee per gas for EVM. Unlike `max_fee`, this limits the price however low the gas limit is.
+    #[serde(default = "Option::default")]
+    pub max_gas_price: Option<Fee>,
+}
+
+impl<Address, Value, Fee> Default for CappedAcl<Address, Value, Fee> {
+    /// Creates an empty ACL that always evaluates to false.
+    fn default() -> Self {
+        Self { caps: Vec::new() }
+    }
+}
+
+/// Evaluates the caps against the receiver, value and fee of a transaction of any architecture.
+///
+/// The architecture cannot be inferred from the transaction type, so needs to be given explicitly, eg.
+/// `SatisfiableForArchitecture::<Evm>::is_satisfied_by(acl, &tx)`.
+impl<A: Architecture> SatisfiableForArchitecture<A> for CappedAcl<A::AddressRaw, A::Value, A::Fee> {
+    fn is_satisfied_by(self, tx: &A::TransactionRequest) -> Result<(), CoreError> {
+        let receiver = single_receiver::<A>(tx)?;
+        let cap = self
+            .caps
+            .into_iter()
+            .find(|cap| cap.address == receiver)
+            .ok_or(CoreError::Evaluation(
+                "Transaction not allowed.".to_string(),
+            ))?;
+
+        let value = tx.value().ok_or(CoreError::Evaluation(
+            "Transaction has no value to check against the cap.".to_string(),
+        ))?;
+        if value > cap.max_value {
+            return Err(CoreError::Evaluation(format!(
+                "Transaction value {:?} exceeds the cap of {:?}.",
+                value, cap.max_value
+            )));
+        }
+
+        if let Some(max_fee) = cap.max_fee {
+            let fee = tx.fee().ok_or(CoreError::Evaluation(
+                "Transaction has no fee to check against the cap.".to_string(),
+            ))?;
+            if fee > max_fee {
+                return Err(CoreError::Evaluation(format!(
+                    "Transaction fee {:?} exceeds the cap of {:?}.",
+                    fee, max_fee
+                )));
+            }
+        }
+
+        if let Some(max_gas_price) = cap.max_gas_price {
+            let gas_price = tx.gas_price().ok_or(CoreError::Evaluation(
+                "Transaction has no gas price to check against the cap.".to_string(),
+            ))?;
+            if gas_price > max_gas_price {
+                return Err(CoreError::Evaluation(format!(
+                    "Transaction gas price {:?} exceeds the cap of {:?}.",
+                    gas_price, max_gas_price
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
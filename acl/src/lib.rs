@@ -5,7 +5,9 @@ use core::fmt::Debug;
 
 use codec::MaxEncodedLen;
 use codec::{Decode, Encode};
-pub use entropy_programs_core::{Architecture, Error as CoreError, SatisfiableForArchitecture};
+pub use entropy_programs_core::{
+    AclKind, Architecture, Error as CoreError, GetReceiver, GetSender, SatisfiableForArchitecture,
+};
 
 #[cfg(feature = "evm")]
 pub use entropy_programs_evm::{Evm, NameOrAddress, H160};
@@ -13,6 +15,16 @@ pub use entropy_programs_evm::{Evm, NameOrAddress, H160};
 use scale_info::TypeInfo;
 use serde::{Deserialize, Serialize};
 
+pub mod capped;
+pub mod merkle;
+pub mod private;
+#[cfg(test)]
+mod tests;
+
+pub use capped::{Cap, CappedAcl};
+pub use merkle::{MerkleAcl, MerkleProof, MerkleTree};
+pub use private::PrivateAcl;
+
 /// An access control list (Allow/Deny lists).
 #[derive(
     Clone,
@@ -32,9 +44,37 @@ pub struct Acl<Address> {
     pub allow_null_recipient: bool,
 }
 
-/// Represents either an allow or deny list.
+/// An [`Acl`] checked against the sender and/or receiver of a transaction, rather than only its receiver.
+///
+/// This is a separate type so that the SCALE encoding of existing [`Acl`] configs is unchanged. In JSON, the ACL's
+/// fields are flattened, with an optional `target` defaulting to the receiver.
+#[derive(
+    Clone,
+    Debug,
+    Encode,
+    Decode,
+    PartialEq,
+    Eq,
+    scale_info::TypeInfo,
+    MaxEncodedLen,
+    Serialize,
+    Deserialize,
+)]
+pub struct TargetedAcl<Address> {
+    #[serde(flatten)]
+    pub acl: Acl<Address>,
+    /// Which side(s) of the transaction the list is checked against.
+    #[serde(default)]
+    pub target: AclTarget,
+}
+
+/// Represents which address(es) of a transaction a [`TargetedAcl`] is checked against.
+///
+/// Transactions without a sender (eg. unsigned EVM transactions, where the sender is implied by the signature)
+/// never satisfy an ACL that targets the sender, regardless of its kind.
 #[derive(
     Debug,
+    Default,
     Clone,
     Copy,
     PartialEq,
@@ -46,9 +86,11 @@ pub struct Acl<Address> {
     Serialize,
     Deserialize,
 )]
-pub enum AclKind {
-    Allow,
-    Deny,
+pub enum AclTarget {
+    #[default]
+    Receiver,
+    Sender,
+    Both,
 }
 
 /// Creates an empty ACL that always evaluates to false.
@@ -63,16 +105,12 @@ impl<A: Default> Default for Acl<A> {
     }
 }
 
-// TODO This needs to be generic over any architecture (use GetRecipient and GetSender traits)
-// TODO Move to `entropy-programs-evm` crate?
-#[allow(clippy::needless_collect)]
-#[cfg(feature = "evm")]
-impl SatisfiableForArchitecture<Evm> for Acl<<Evm as Architecture>::AddressRaw> {
-    fn is_satisfied_by(
-        self,
-        tx: &<Evm as Architecture>::TransactionRequest,
-    ) -> Result<(), CoreError> {
-        if tx.to.is_none() {
+impl<Address: Eq> Acl<Address> {
+    fn check_receivers(&self, receivers: Option<Vec<Address>>) -> Result<(), CoreError> {
+        let receivers = receivers.ok_or(CoreError::Evaluation(
+            "Transaction receivers could not be determined.".to_string(),
+        ))?;
+        if receivers.is_empty() {
             return match self.allow_null_recipient {
                 true => Ok(()),
                 false => Err(CoreError::Evaluation(
@@ -81,21 +119,69 @@ impl SatisfiableForArchitecture<Evm> for Acl<<Evm as Architecture>::AddressRaw>
             };
         }
 
-        let converted_addresses: Vec<NameOrAddress> = self
-            .addresses
-            .into_iter()
-            .map(|a| NameOrAddress::Address(H160::from(a)))
-            .collect();
-
-        match (
-            converted_addresses.contains(&tx.to.clone().unwrap()),
-            self.kind,
-        ) {
-            (true, AclKind::Allow) => Ok(()),
-            (false, AclKind::Deny) => Ok(()),
-            _ => Err(CoreError::Evaluation(
+        match receivers
+            .iter()
+            .all(|receiver| self.kind.permits(self.addresses.contains(receiver)))
+        {
+            true => Ok(()),
+            false => Err(CoreError::Evaluation(
                 "Transaction not allowed.".to_string(),
             )),
         }
     }
+
+    fn check_sender(&self, sender: Option<Address>) -> Result<(), CoreError> {
+        let sender = sender.ok_or(CoreError::Evaluation(
+            "Transaction has no sender to check against the ACL.".to_string(),
+        ))?;
+
+        match self.kind.permits(self.addresses.contains(&sender)) {
+            true => Ok(()),
+            false => Err(CoreError::Evaluation("Sender not allowed.".to_string())),
+        }
+    }
+}
+
+/// Evaluates the ACL against the receivers of a transaction of any architecture. Every receiver of transactions
+/// with several (see [`GetReceiver::receivers`]) must be permitted.
+///
+/// Since the ACL is generic over the raw address type, the architecture usually needs to be given explicitly, eg.
+/// `SatisfiableForArchitecture::<Evm>::is_satisfied_by(acl, &tx)`.
+impl<A: Architecture> SatisfiableForArchitecture<A> for Acl<A::AddressRaw> {
+    fn is_satisfied_by(self, tx: &A::TransactionRequest) -> Result<(), CoreError> {
+        let receivers = tx
+            .receivers()
+            .map(|receivers| receivers.into_iter().map(A::AddressRaw::from).collect());
+        self.check_receivers(receivers)
+    }
+}
+
+/// Evaluates the ACL against the sender and/or receivers of a transaction of any architecture.
+impl<A: Architecture> SatisfiableForArchitecture<A> for TargetedAcl<A::AddressRaw> {
+    fn is_satisfied_by(self, tx: &A::TransactionRequest) -> Result<(), CoreError> {
+        if matches!(self.target, AclTarget::Sender | AclTarget::Both) {
+            self.acl
+                .check_sender(tx.sender().map(A::AddressRaw::from))?;
+        }
+        if matches!(self.target, AclTarget::Receiver | AclTarget::Both) {
+            SatisfiableForArchitecture::<A>::is_satisfied_by(self.acl, tx)?;
+        }
+        Ok(())
+    }
+}
+
+/// The single receiver of a transaction, or `None` if it has none. Fails for transactions with several receivers,
+/// or whose receivers can't be determined.
+pub(crate) fn single_receiver<A: Architecture>(
+    tx: &A::TransactionRequest,
+) -> Result<Option<A::AddressRaw>, CoreError> {
+    let mut receivers = tx.receivers().ok_or(CoreError::Evaluation(
+        "Transaction receivers could not be determined.".to_string(),
+    ))?;
+    match receivers.len() {
+        0 | 1 => Ok(receivers.pop().map(A::AddressRaw::from)),
+        _ => Err(CoreError::Evaluation(
+            "Transactions with several receivers are not supported.".to_string(),
+        )),
+    }
 }
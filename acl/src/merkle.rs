@@ -0,0 +1,278 @@
+//! Merkle-root ACLs, for allow and deny lists too large to store in a program config.
+//!
+//! The list is committed to by a single root: a Blake2s Merkle tree over the sorted, deduplicated addresses,
+//! hashed together with the number of addresses. Signature requests then carry a [`MerkleProof`] for the
+//! receiver in their auxiliary data: an inclusion proof for allow lists, or an exclusion proof (the two
+//! adjacent leaves surrounding the receiver in the sorted tree) for deny lists.
+//!
+//! Leaves and internal nodes are domain-separated, and a node without a sibling is promoted to the next level
+//! unchanged, so no address can be proven to be in the tree unless it was part of the list.
+
+use alloc::{string::ToString, vec::Vec};
+
+use blake2::{Blake2s256, Digest};
+use codec::{Decode, Encode};
+use entropy_programs_core::{Architecture, Error as CoreError};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::{single_receiver, AclKind};
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const ROOT_PREFIX: u8 = 2;
+
+/// A 32-byte Blake2s hash.
+pub type Hash = [u8; 32];
+
+/// An ACL whose addresses are only committed to by a Merkle root. Used with a [`MerkleProof`] for the receiver.
+#[derive(Clone, Debug, Encode, Decode, PartialEq, Eq, TypeInfo, Serialize, Deserialize)]
+pub struct MerkleAcl {
+    /// Root of the sorted Merkle tree of addresses, see [`MerkleTree::root`].
+    pub root: Hash,
+    pub kind: AclKind,
+    pub allow_null_recipient: bool,
+}
+
+/// Proves that a leaf is at a given position of the tree.
+#[derive(
+    Clone, Debug, Default, Encode, Decode, PartialEq, Eq, TypeInfo, Serialize, Deserialize,
+)]
+pub struct LeafProof {
+    /// Position of the leaf in the sorted list of addresses.
+    pub index: u32,
+    /// Total number of addresses in the tree.
+    pub leaf_count: u32,
+    /// Sibling hashes from the leaf up to the root, skipping levels where the node has no sibling.
+    pub siblings: Vec<Hash>,
+}
+
+/// Auxiliary data proving that an address is, or is not, part of a [`MerkleAcl`].
+#[derive(Clone, Debug, Encode, Decode, PartialEq, Eq, TypeInfo, Serialize, Deserialize)]
+pub enum MerkleProof<Address> {
+    /// The address is a leaf of the tree.
+    Inclusion(LeafProof),
+    /// The address falls strictly between two adjacent leaves of the sorted tree. `lower` is omitted if the
+    /// address sorts before the first leaf, and `upper` if it sorts after the last one.
+    Exclusion {
+        lower: Option<(Address, LeafProof)>,
+        upper: Option<(Address, LeafProof)>,
+    },
+}
+
+fn hash_leaf(address: &[u8]) -> Hash {
+    let mut hasher = Blake2s256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(address);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Blake2s256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Root of a tree without any addresses.
+fn empty_root() -> Hash {
+    hash_root(0, &[0u8; 32])
+}
+
+fn hash_root(leaf_count: u32, tree_root: &Hash) -> Hash {
+    let mut hasher = Blake2s256::new();
+    hasher.update([ROOT_PREFIX]);
+    hasher.update(leaf_count.to_le_bytes());
+    hasher.update(tree_root);
+    hasher.finalize().into()
+}
+
+impl LeafProof {
+    /// Computes the root committed to by this proof if `address` is the leaf being proven.
+    pub fn compute_root(&self, address: &[u8]) -> Option<Hash> {
+        if self.index >= self.leaf_count {
+            return None;
+        }
+
+        let mut siblings = self.siblings.iter();
+        let (mut hash, mut index, mut size) = (hash_leaf(address), self.index, self.leaf_count);
+        while size > 1 {
+            // the last node of an odd-sized level has no sibling, and is promoted unchanged
+            if !(index == size - 1 && size % 2 == 1) {
+                let sibling = siblings.next()?;
+                hash = match index % 2 {
+                    0 => hash_node(&hash, sibling),
+                    _ => hash_node(sibling, &hash),
+                };
+            }
+            index /= 2;
+            size = size.div_ceil(2);
+        }
+
+        match siblings.next() {
+            Some(_) => None,
+            None => Some(hash_root(self.leaf_count, &hash)),
+        }
+    }
+
+    fn verify(&self, address: &[u8], root: &Hash) -> bool {
+        self.compute_root(address).as_ref() == Some(root)
+    }
+}
+
+impl<Address: AsRef<[u8]>> MerkleProof<Address> {
+    /// Returns whether the proof shows that `address` is (`true`) or is not (`false`) in the tree with `root`.
+    /// Returns an error if the proof is invalid for `address` and `root`.
+    pub fn verify(&self, address: &[u8], root: &Hash) -> Result<bool, CoreError> {
+        let valid = match self {
+            MerkleProof::Inclusion(proof) => proof.verify(address, root),
+            MerkleProof::Exclusion { lower, upper } => {
+                let lower_valid = match lower {
+                    Some((lower, proof)) => {
+                        lower.as_ref() < address && proof.verify(lower.as_ref(), root)
+                    }
+                    None => true,
+                };
+                let upper_valid = match upper {
+                    Some((upper, proof)) => {
+                        address < upper.as_ref() && proof.verify(upper.as_ref(), root)
+                    }
+                    None => true,
+                };
+                // the two leaves must be adjacent, or the first/last leaf if one of them is omitted
+                let adjacent = match (lower, upper) {
+                    (Some((_, lower)), Some((_, upper))) => {
+                        lower.index.checked_add(1) == Some(upper.index)
+                    }
+                    (Some((_, lower)), None) => {
+                        lower.index.checked_add(1) == Some(lower.leaf_count)
+                    }
+                    (None, Some((_, upper))) => upper.index == 0,
+                    (None, None) => *root == empty_root(),
+                };
+                lower_valid && upper_valid && adjacent
+            }
+        };
+
+        match (valid, self) {
+            (false, _) => Err(CoreError::InvalidSignatureRequest(
+                "Invalid Merkle proof.".to_string(),
+            )),
+            (true, MerkleProof::Inclusion(_)) => Ok(true),
+            (true, MerkleProof::Exclusion { .. }) => Ok(false),
+        }
+    }
+}
+
+impl MerkleAcl {
+    /// Checks the receiver of `tx` against the ACL, using `proof` from the auxiliary data.
+    ///
+    /// The architecture cannot be inferred from the transaction type, so needs to be given explicitly, eg.
+    /// `acl.is_satisfied_by::<Evm>(&tx, &proof)`.
+    pub fn is_satisfied_by<A: Architecture>(
+        self,
+        tx: &A::TransactionRequest,
+        proof: &MerkleProof<A::AddressRaw>,
+    ) -> Result<(), CoreError>
+    where
+        A::AddressRaw: AsRef<[u8]>,
+    {
+        let Some(receiver) = single_receiver::<A>(tx)? else {
+            return match self.allow_null_recipient {
+                true => Ok(()),
+                false => Err(CoreError::Evaluation(
+                    "Null recipients are not allowed.".to_string(),
+                )),
+            };
+        };
+
+        match self
+            .kind
+            .permits(proof.verify(receiver.as_ref(), &self.root)?)
+        {
+            true => Ok(()),
+            false => Err(CoreError::Evaluation(
+                "Transaction not allowed.".to_string(),
+            )),
+        }
+    }
+}
+
+/// A sorted Merkle tree of addresses, used to compute [`MerkleAcl`] roots and proofs off-chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree<Address> {
+    addresses: Vec<Address>,
+    /// Levels of the tree, from the leaves up to the tree root.
+    levels: Vec<Vec<Hash>>,
+}
+
+impl<Address: AsRef<[u8]> + Clone> MerkleTree<Address> {
+    /// Builds the tree from addresses in any order. Duplicates are removed.
+    pub fn new(mut addresses: Vec<Address>) -> Self {
+        addresses.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        addresses.dedup_by(|a, b| a.as_ref() == b.as_ref());
+
+        let mut levels = Vec::new();
+        let mut level: Vec<Hash> = addresses.iter().map(|a| hash_leaf(a.as_ref())).collect();
+        while level.len() > 1 {
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!("chunks are of size 1 or 2"),
+                })
+                .collect();
+            levels.push(level);
+            level = next;
+        }
+        levels.push(level);
+
+        Self { addresses, levels }
+    }
+
+    /// Root of the tree, committing to both the addresses and their number.
+    pub fn root(&self) -> Hash {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(tree_root) => hash_root(self.addresses.len() as u32, tree_root),
+            None => empty_root(),
+        }
+    }
+
+    /// Builds an inclusion proof if `address` is in the tree, or an exclusion proof otherwise.
+    pub fn proof(&self, address: &Address) -> MerkleProof<Address> {
+        match self
+            .addresses
+            .binary_search_by(|a| a.as_ref().cmp(address.as_ref()))
+        {
+            Ok(index) => MerkleProof::Inclusion(self.leaf_proof(index)),
+            Err(index) => MerkleProof::Exclusion {
+                lower: index
+                    .checked_sub(1)
+                    .map(|i| (self.addresses[i].clone(), self.leaf_proof(i))),
+                upper: self
+                    .addresses
+                    .get(index)
+                    .map(|upper| (upper.clone(), self.leaf_proof(index))),
+            },
+        }
+    }
+
+    fn leaf_proof(&self, index: usize) -> LeafProof {
+        let mut siblings = Vec::new();
+        let mut i = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(i ^ 1) {
+                siblings.push(*sibling);
+            }
+            i /= 2;
+        }
+
+        LeafProof {
+            index: index as u32,
+            leaf_count: self.addresses.len() as u32,
+            siblings,
+        }
+    }
+}
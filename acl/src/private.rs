@@ -0,0 +1,95 @@
+//! Private ACLs, where the config only holds salted hashes of the listed addresses.
+//!
+//! Anyone holding the config can still check whether a *given* address is listed, but cannot read the list,
+//! and the per-list salt prevents precomputed tables or matching entries across different lists.
+
+use alloc::{string::ToString, vec::Vec};
+
+use blake2::{Blake2s256, Digest};
+use codec::{Decode, Encode};
+use entropy_programs_core::{
+    Architecture, Error as CoreError, GetReceiver, SatisfiableForArchitecture,
+};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::AclKind;
+
+/// An ACL over salted Blake2s hashes of addresses, see [`PrivateAcl::hash_address`].
+#[derive(Clone, Debug, Encode, Decode, PartialEq, Eq, TypeInfo, Serialize, Deserialize)]
+pub struct PrivateAcl {
+    pub salt: [u8; 32],
+    pub hashed_addresses: Vec<[u8; 32]>,
+    pub kind: AclKind,
+    pub allow_null_recipient: bool,
+}
+
+impl PrivateAcl {
+    /// Builds the ACL from plaintext addresses. The salt should be random and unique to each list.
+    pub fn new<Address: AsRef<[u8]>>(
+        salt: [u8; 32],
+        addresses: impl IntoIterator<Item = Address>,
+        kind: AclKind,
+        allow_null_recipient: bool,
+    ) -> Self {
+        let mut hashed_addresses: Vec<[u8; 32]> = addresses
+            .into_iter()
+            .map(|address| Self::hash_address(&salt, address.as_ref()))
+            .collect();
+        // sorting hides the order addresses were given in
+        hashed_addresses.sort();
+        hashed_addresses.dedup();
+
+        Self {
+            salt,
+            hashed_addresses,
+            kind,
+            allow_null_recipient,
+        }
+    }
+
+    /// Hashes an address as `blake2s(salt || address)`.
+    pub fn hash_address(salt: &[u8; 32], address: &[u8]) -> [u8; 32] {
+        let mut hasher = Blake2s256::new();
+        hasher.update(salt);
+        hasher.update(address);
+        hasher.finalize().into()
+    }
+}
+
+/// Evaluates the ACL against the receiver of a transaction of any architecture. Every receiver of transactions
+/// with several must be permitted.
+///
+/// The architecture cannot be inferred from the transaction type, so needs to be given explicitly, eg.
+/// `SatisfiableForArchitecture::<Evm>::is_satisfied_by(acl, &tx)`.
+impl<A: Architecture> SatisfiableForArchitecture<A> for PrivateAcl
+where
+    A::AddressRaw: AsRef<[u8]>,
+{
+    fn is_satisfied_by(self, tx: &A::TransactionRequest) -> Result<(), CoreError> {
+        let receivers = tx.receivers().ok_or(CoreError::Evaluation(
+            "Transaction receivers could not be determined.".to_string(),
+        ))?;
+        if receivers.is_empty() {
+            return match self.allow_null_recipient {
+                true => Ok(()),
+                false => Err(CoreError::Evaluation(
+                    "Null recipients are not allowed.".to_string(),
+                )),
+            };
+        }
+
+        let permits = |receiver: A::Address| {
+            let hashed_receiver =
+                Self::hash_address(&self.salt, A::AddressRaw::from(receiver).as_ref());
+            self.kind
+                .permits(self.hashed_addresses.contains(&hashed_receiver))
+        };
+        match receivers.into_iter().all(permits) {
+            true => Ok(()),
+            false => Err(CoreError::Evaluation(
+                "Transaction not allowed.".to_string(),
+            )),
+        }
+    }
+}
@@ -1,32 +1,146 @@
-#![cfg(test)]
+use alloc::vec;
 
-use entropy_programs_core::{Acl, AclKind};
-use ethers_core::types::{NameOrAddress, TransactionRequest, H160};
+use entropy_programs_core::{GetCalldata, GetChainId, GetFee, GetNonce, GetValue, TryParse};
+use serde::{Deserialize, Serialize};
 
-use crate::Evaluate;
+use super::{merkle::LeafProof, *};
 
+/// Minimal architecture with numeric addresses, used to check that ACLs work for more than EVM.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TestArch;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TestTx {
+    from: Option<u32>,
+    to: Option<u32>,
+}
+
+impl Architecture for TestArch {
+    type Address = u32;
+    type AddressRaw = u32;
+    type Value = u64;
+    type ChainId = u64;
+    type Nonce = u64;
+    type Fee = u64;
+    type TransactionRequest = TestTx;
+}
+
+impl GetSender<TestArch> for TestTx {
+    fn sender(&self) -> Option<u32> {
+        self.from
+    }
+}
+
+impl GetReceiver<TestArch> for TestTx {
+    fn receiver(&self) -> Option<u32> {
+        self.to
+    }
+}
+
+impl GetValue<TestArch> for TestTx {
+    fn value(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl GetChainId<TestArch> for TestTx {
+    fn chain_id(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl GetCalldata<TestArch> for TestTx {
+    fn calldata(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl GetNonce<TestArch> for TestTx {
+    fn nonce(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl GetFee<TestArch> for TestTx {
+    fn fee(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl TryParse<TestArch> for TestTx {
+    fn try_parse(_raw_tx: &[u8]) -> Result<TestTx, CoreError> {
+        Err(CoreError::InvalidSignatureRequest("unsupported".into()))
+    }
+}
+
+#[test]
+fn test_acl_functions_properly_for_other_architectures() {
+    let tx = |from, to| TestTx { from, to };
+    let acl = |kind, allow_null_recipient| Acl::<u32> {
+        addresses: vec![1],
+        kind,
+        allow_null_recipient,
+    };
+    let evaluate = |acl: Acl<u32>, tx: TestTx| {
+        SatisfiableForArchitecture::<TestArch>::is_satisfied_by(acl, &tx).is_ok()
+    };
+
+    let allowlisted_acl = acl(AclKind::Allow, false);
+    assert!(evaluate(allowlisted_acl.clone(), tx(None, Some(1))));
+    assert!(!evaluate(allowlisted_acl.clone(), tx(None, Some(2))));
+    assert!(!evaluate(allowlisted_acl, tx(None, None)));
+
+    let denylisted_acl = acl(AclKind::Deny, false);
+    assert!(evaluate(denylisted_acl.clone(), tx(None, Some(2))));
+    assert!(!evaluate(denylisted_acl.clone(), tx(None, Some(1))));
+    assert!(!evaluate(denylisted_acl, tx(None, None)));
+
+    assert!(evaluate(acl(AclKind::Allow, true), tx(None, None)));
+    assert!(evaluate(acl(AclKind::Deny, true), tx(None, None)));
+    assert!(!evaluate(Acl::default(), tx(None, Some(1))));
+
+    // targeting the sender
+    let sender_allowlist = TargetedAcl {
+        acl: acl(AclKind::Allow, false),
+        target: AclTarget::Sender,
+    };
+    let evaluate = |acl: TargetedAcl<u32>, tx: TestTx| {
+        SatisfiableForArchitecture::<TestArch>::is_satisfied_by(acl, &tx).is_ok()
+    };
+    assert!(evaluate(sender_allowlist.clone(), tx(Some(1), Some(2))));
+    assert!(!evaluate(sender_allowlist.clone(), tx(Some(2), Some(1))));
+    assert!(!evaluate(sender_allowlist, tx(None, Some(1))));
+}
+
+#[cfg(feature = "evm")]
 #[test]
 fn test_acl_functions_properly() {
+    use entropy_programs_evm::{EvmTransactionRequest, LegacyTransactionRequest};
+
     let evm_address_1: [u8; 20] = [1u8; 20];
     let evm_address_2: [u8; 20] = [2u8; 20];
     let evm_address_3: [u8; 20] = [3u8; 20];
 
-    let to_address_1_tx = TransactionRequest {
+    let to_address_1_tx: EvmTransactionRequest = LegacyTransactionRequest {
         to: Some(NameOrAddress::Address(H160::from(evm_address_1))),
         ..Default::default()
-    };
-    let to_address_2_tx = TransactionRequest {
+    }
+    .into();
+    let to_address_2_tx: EvmTransactionRequest = LegacyTransactionRequest {
         to: Some(NameOrAddress::Address(H160::from(evm_address_2))),
         ..Default::default()
-    };
-    let to_address_3_tx = TransactionRequest {
+    }
+    .into();
+    let to_address_3_tx: EvmTransactionRequest = LegacyTransactionRequest {
         to: Some(NameOrAddress::Address(H160::from(evm_address_3))),
         ..Default::default()
-    };
-    let to_null_recipient_tx = TransactionRequest {
+    }
+    .into();
+    let to_null_recipient_tx: EvmTransactionRequest = LegacyTransactionRequest {
         to: None,
         ..Default::default()
-    };
+    }
+    .into();
 
     let allowlisted_acl = Acl::<[u8; 20]> {
         addresses: vec![evm_address_1],
@@ -34,23 +148,27 @@ fn test_acl_functions_properly() {
     };
 
     // should only let allowlisted_tx through
-    assert!(allowlisted_acl
-        .clone()
-        .is_satisfied_by(to_address_1_tx.clone())
-        .is_ok());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        allowlisted_acl.clone(),
+        &to_address_1_tx
+    )
+    .is_ok());
 
-    assert!(allowlisted_acl
-        .clone()
-        .is_satisfied_by(to_address_2_tx.clone())
-        .is_err());
-    assert!(allowlisted_acl
-        .clone()
-        .is_satisfied_by(to_address_3_tx.clone())
-        .is_err());
-    assert!(allowlisted_acl
-        .clone()
-        .is_satisfied_by(to_null_recipient_tx.clone())
-        .is_err());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        allowlisted_acl.clone(),
+        &to_address_2_tx
+    )
+    .is_err());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        allowlisted_acl.clone(),
+        &to_address_3_tx
+    )
+    .is_err());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        allowlisted_acl.clone(),
+        &to_null_recipient_tx
+    )
+    .is_err());
 
     let denylisted_acl = Acl::<[u8; 20]> {
         addresses: vec![evm_address_1],
@@ -59,21 +177,26 @@ fn test_acl_functions_properly() {
     };
 
     // should only block allowlisted and null recipient txs
-    assert!(denylisted_acl
-        .clone()
-        .is_satisfied_by(to_address_2_tx.clone())
-        .is_ok());
-    assert!(denylisted_acl
-        .clone()
-        .is_satisfied_by(to_address_3_tx.clone())
-        .is_ok());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        denylisted_acl.clone(),
+        &to_address_2_tx
+    )
+    .is_ok());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        denylisted_acl.clone(),
+        &to_address_3_tx
+    )
+    .is_ok());
 
-    assert!(denylisted_acl
-        .is_satisfied_by(to_address_1_tx.clone())
-        .is_err());
-    assert!(allowlisted_acl
-        .is_satisfied_by(to_null_recipient_tx.clone())
-        .is_err());
+    assert!(
+        SatisfiableForArchitecture::<Evm>::is_satisfied_by(denylisted_acl, &to_address_1_tx)
+            .is_err()
+    );
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        allowlisted_acl,
+        &to_null_recipient_tx
+    )
+    .is_err());
 
     let allowlisted_acl_with_null_recipient = Acl::<[u8; 20]> {
         addresses: vec![evm_address_1],
@@ -82,22 +205,27 @@ fn test_acl_functions_properly() {
     };
 
     // should only let allowlisted_tx and null recipient txs through
-    assert!(allowlisted_acl_with_null_recipient
-        .clone()
-        .is_satisfied_by(to_address_1_tx.clone())
-        .is_ok());
-    assert!(allowlisted_acl_with_null_recipient
-        .clone()
-        .is_satisfied_by(to_null_recipient_tx.clone())
-        .is_ok());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        allowlisted_acl_with_null_recipient.clone(),
+        &to_address_1_tx
+    )
+    .is_ok());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        allowlisted_acl_with_null_recipient.clone(),
+        &to_null_recipient_tx
+    )
+    .is_ok());
 
-    assert!(allowlisted_acl_with_null_recipient
-        .clone()
-        .is_satisfied_by(to_address_2_tx.clone())
-        .is_err());
-    assert!(allowlisted_acl_with_null_recipient
-        .is_satisfied_by(to_address_3_tx.clone())
-        .is_err());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        allowlisted_acl_with_null_recipient.clone(),
+        &to_address_2_tx
+    )
+    .is_err());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        allowlisted_acl_with_null_recipient,
+        &to_address_3_tx
+    )
+    .is_err());
 
     let denylisted_acl_with_null_recipient = Acl::<[u8; 20]> {
         addresses: vec![evm_address_1],
@@ -106,28 +234,421 @@ fn test_acl_functions_properly() {
     };
 
     // should only block allowlisted
-    assert!(denylisted_acl_with_null_recipient
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        denylisted_acl_with_null_recipient.clone(),
+        &to_address_2_tx
+    )
+    .is_ok());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        denylisted_acl_with_null_recipient.clone(),
+        &to_address_3_tx
+    )
+    .is_ok());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        denylisted_acl_with_null_recipient.clone(),
+        &to_null_recipient_tx
+    )
+    .is_ok());
+
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        denylisted_acl_with_null_recipient,
+        &to_address_1_tx
+    )
+    .is_err());
+
+    let empty_acl = Acl::<[u8; 20]>::default();
+
+    // should fail all txs
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        empty_acl.clone(),
+        &to_address_1_tx
+    )
+    .is_err());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        empty_acl.clone(),
+        &to_address_2_tx
+    )
+    .is_err());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        empty_acl.clone(),
+        &to_address_3_tx
+    )
+    .is_err());
+    assert!(
+        SatisfiableForArchitecture::<Evm>::is_satisfied_by(empty_acl, &to_null_recipient_tx)
+            .is_err()
+    );
+}
+
+#[cfg(feature = "evm")]
+#[test]
+fn test_acl_does_not_allow_ens_recipients() {
+    use entropy_programs_evm::LegacyTransactionRequest;
+
+    let to_ens_name_tx = LegacyTransactionRequest {
+        to: Some(NameOrAddress::Name("vitalik.eth".into())),
+        ..Default::default()
+    }
+    .into();
+
+    for kind in [AclKind::Allow, AclKind::Deny] {
+        let acl = Acl::<[u8; 20]> {
+            addresses: vec![[1u8; 20]],
+            kind,
+            allow_null_recipient: true,
+        };
+        assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(acl, &to_ens_name_tx).is_err());
+    }
+}
+
+#[cfg(feature = "evm")]
+#[test]
+fn test_acl_targets_function_properly() {
+    use entropy_programs_evm::{EvmTransactionRequest, LegacyTransactionRequest};
+
+    let listed = [1u8; 20];
+    let unlisted = [2u8; 20];
+    let tx = |from: Option<[u8; 20]>, to: [u8; 20]| -> EvmTransactionRequest {
+        LegacyTransactionRequest {
+            from: from.map(H160::from),
+            to: Some(NameOrAddress::Address(H160::from(to))),
+            ..Default::default()
+        }
+        .into()
+    };
+    let acl = |kind, target| TargetedAcl::<[u8; 20]> {
+        acl: Acl {
+            addresses: vec![listed],
+            kind,
+            allow_null_recipient: false,
+        },
+        target,
+    };
+    let evaluate = |acl: TargetedAcl<[u8; 20]>, tx: EvmTransactionRequest| {
+        SatisfiableForArchitecture::<Evm>::is_satisfied_by(acl, &tx).is_ok()
+    };
+
+    // sender allowlist ignores the receiver
+    let sender_allowlist = acl(AclKind::Allow, AclTarget::Sender);
+    assert!(evaluate(
+        sender_allowlist.clone(),
+        tx(Some(listed), unlisted)
+    ));
+    assert!(!evaluate(
+        sender_allowlist.clone(),
+        tx(Some(unlisted), listed)
+    ));
+
+    // sender denylist
+    let sender_denylist = acl(AclKind::Deny, AclTarget::Sender);
+    assert!(evaluate(
+        sender_denylist.clone(),
+        tx(Some(unlisted), listed)
+    ));
+    assert!(!evaluate(
+        sender_denylist.clone(),
+        tx(Some(listed), unlisted)
+    ));
+
+    // a missing sender never satisfies a sender ACL
+    assert!(!evaluate(sender_allowlist, tx(None, listed)));
+    assert!(!evaluate(sender_denylist, tx(None, listed)));
+
+    // both sides must be permitted
+    let both_allowlist = acl(AclKind::Allow, AclTarget::Both);
+    assert!(evaluate(both_allowlist.clone(), tx(Some(listed), listed)));
+    assert!(!evaluate(
+        both_allowlist.clone(),
+        tx(Some(listed), unlisted)
+    ));
+    assert!(!evaluate(
+        both_allowlist.clone(),
+        tx(Some(unlisted), listed)
+    ));
+    assert!(!evaluate(both_allowlist, tx(None, listed)));
+
+    let both_denylist = acl(AclKind::Deny, AclTarget::Both);
+    assert!(evaluate(
+        both_denylist.clone(),
+        tx(Some(unlisted), unlisted)
+    ));
+    assert!(!evaluate(both_denylist.clone(), tx(Some(listed), unlisted)));
+    assert!(!evaluate(both_denylist, tx(Some(unlisted), listed)));
+}
+
+#[test]
+fn test_acl_target_defaults_to_receiver() {
+    let acl: TargetedAcl<u32> = serde_json::from_str(
+        r#"{ "addresses": [1], "kind": "Allow", "allow_null_recipient": false }"#,
+    )
+    .unwrap();
+    assert_eq!(acl.target, AclTarget::Receiver);
+    assert_eq!(acl.acl.addresses, vec![1]);
+}
+
+#[test]
+fn test_acl_scale_encoding_is_unchanged() {
+    use codec::DecodeAll;
+
+    // an `Acl<[u8; 20]>` denying `[1u8; 20]` and allowing null recipients, as encoded by earlier versions
+    // (a compact length prefix, the addresses, then the kind and `allow_null_recipient`)
+    let encoded = [&[0x04][..], &[1u8; 20], &[0x01], &[0x01]].concat();
+    let acl = Acl::<[u8; 20]>::decode_all(&mut encoded.as_slice()).unwrap();
+    assert_eq!(
+        acl,
+        Acl {
+            addresses: vec![[1u8; 20]],
+            kind: AclKind::Deny,
+            allow_null_recipient: true,
+        }
+    );
+    assert_eq!(acl.encode(), encoded);
+}
+
+#[test]
+fn test_merkle_proofs_for_all_tree_sizes() {
+    for size in 0u8..10 {
+        // even addresses are in the tree, odd ones are not
+        let tree = MerkleTree::new((0..size).map(|i| [i * 2; 20]).collect());
+        let root = tree.root();
+
+        for i in 0..size * 2 + 2 {
+            let address = [i; 20];
+            let proof = tree.proof(&address);
+            assert_eq!(
+                proof.verify(&address, &root).unwrap(),
+                i % 2 == 0 && i < size * 2
+            );
+        }
+    }
+}
+
+#[test]
+fn test_merkle_proofs_cannot_be_forged() {
+    let tree = MerkleTree::new(vec![[1u8; 20], [3u8; 20], [5u8; 20], [7u8; 20], [9u8; 20]]);
+    let root = tree.root();
+
+    // an inclusion proof does not verify for another address
+    let proof = tree.proof(&[3u8; 20]);
+    assert!(proof.verify(&[4u8; 20], &root).is_err());
+    assert!(proof
+        .verify(&[3u8; 20], &MerkleTree::new(vec![[3u8; 20]]).root())
+        .is_err());
+
+    // an exclusion proof cannot skip over a leaf
+    let MerkleProof::Exclusion { lower, .. } = tree.proof(&[2u8; 20]) else {
+        panic!("expected an exclusion proof");
+    };
+    let MerkleProof::Exclusion { upper, .. } = tree.proof(&[6u8; 20]) else {
+        panic!("expected an exclusion proof");
+    };
+    let skipping = MerkleProof::Exclusion { lower, upper };
+    assert!(skipping.verify(&[3u8; 20], &root).is_err());
+
+    // nor can it omit a bound that exists
+    let MerkleProof::Exclusion { upper, .. } = tree.proof(&[4u8; 20]) else {
+        panic!("expected an exclusion proof");
+    };
+    let unbounded = MerkleProof::Exclusion { lower: None, upper };
+    assert!(unbounded.verify(&[4u8; 20], &root).is_err());
+
+    // nor claim an address is not in the tree when it is
+    let member = MerkleProof::Exclusion {
+        lower: Some(([1u8; 20], LeafProof::default())),
+        upper: None,
+    };
+    assert!(member.verify(&[1u8; 20], &root).is_err());
+}
+
+#[cfg(feature = "evm")]
+#[test]
+fn test_merkle_acl_functions_properly() {
+    use entropy_programs_evm::{EvmTransactionRequest, LegacyTransactionRequest};
+
+    let tree = MerkleTree::new(vec![[1u8; 20], [3u8; 20]]);
+    let tx_to = |address: [u8; 20]| -> EvmTransactionRequest {
+        LegacyTransactionRequest {
+            to: Some(NameOrAddress::Address(H160::from(address))),
+            ..Default::default()
+        }
+        .into()
+    };
+    let acl = |kind| MerkleAcl {
+        root: tree.root(),
+        kind,
+        allow_null_recipient: false,
+    };
+
+    let allowlist = acl(AclKind::Allow);
+    assert!(allowlist
         .clone()
-        .is_satisfied_by(to_address_2_tx.clone())
+        .is_satisfied_by::<Evm>(&tx_to([1u8; 20]), &tree.proof(&[1u8; 20]))
         .is_ok());
-    assert!(denylisted_acl_with_null_recipient
+    assert!(allowlist
         .clone()
-        .is_satisfied_by(to_address_3_tx.clone())
-        .is_ok());
-    assert!(denylisted_acl_with_null_recipient
+        .is_satisfied_by::<Evm>(&tx_to([2u8; 20]), &tree.proof(&[2u8; 20]))
+        .is_err());
+    // a proof for another address is rejected
+    assert!(allowlist
         .clone()
-        .is_satisfied_by(to_null_recipient_tx.clone())
-        .is_ok());
+        .is_satisfied_by::<Evm>(&tx_to([2u8; 20]), &tree.proof(&[1u8; 20]))
+        .is_err());
+    assert!(allowlist
+        .is_satisfied_by::<Evm>(&EvmTransactionRequest::default(), &tree.proof(&[1u8; 20]))
+        .is_err());
 
-    assert!(denylisted_acl_with_null_recipient
-        .is_satisfied_by(to_address_1_tx.clone())
+    let denylist = acl(AclKind::Deny);
+    assert!(denylist
+        .clone()
+        .is_satisfied_by::<Evm>(&tx_to([2u8; 20]), &tree.proof(&[2u8; 20]))
+        .is_ok());
+    assert!(denylist
+        .is_satisfied_by::<Evm>(&tx_to([3u8; 20]), &tree.proof(&[3u8; 20]))
         .is_err());
+}
 
-    let empty_acl = Acl::<[u8; 20]>::default();
+#[cfg(feature = "evm")]
+#[test]
+fn test_private_acl_functions_properly() {
+    use entropy_programs_evm::{EvmTransactionRequest, LegacyTransactionRequest};
 
-    // should fail all txs
-    assert!(empty_acl.clone().is_satisfied_by(to_address_1_tx).is_err());
-    assert!(empty_acl.clone().is_satisfied_by(to_address_2_tx).is_err());
-    assert!(empty_acl.clone().is_satisfied_by(to_address_3_tx).is_err());
-    assert!(empty_acl.is_satisfied_by(to_null_recipient_tx).is_err());
+    let listed = [1u8; 20];
+    let unlisted = [2u8; 20];
+    let tx_to = |to: Option<[u8; 20]>| -> EvmTransactionRequest {
+        LegacyTransactionRequest {
+            to: to.map(|a| NameOrAddress::Address(H160::from(a))),
+            ..Default::default()
+        }
+        .into()
+    };
+    let acl = |kind, allow_null_recipient| {
+        PrivateAcl::new([7u8; 32], [listed], kind, allow_null_recipient)
+    };
+    let evaluate = |acl: PrivateAcl, tx: EvmTransactionRequest| {
+        SatisfiableForArchitecture::<Evm>::is_satisfied_by(acl, &tx).is_ok()
+    };
+
+    let allowlist = acl(AclKind::Allow, false);
+    assert!(evaluate(allowlist.clone(), tx_to(Some(listed))));
+    assert!(!evaluate(allowlist.clone(), tx_to(Some(unlisted))));
+    assert!(!evaluate(allowlist, tx_to(None)));
+
+    let denylist = acl(AclKind::Deny, true);
+    assert!(evaluate(denylist.clone(), tx_to(Some(unlisted))));
+    assert!(!evaluate(denylist.clone(), tx_to(Some(listed))));
+    assert!(evaluate(denylist, tx_to(None)));
+
+    // the same address hashes differently under a different salt
+    let resalted = PrivateAcl {
+        salt: [8u8; 32],
+        ..acl(AclKind::Allow, false)
+    };
+    assert!(!evaluate(resalted, tx_to(Some(listed))));
+}
+
+#[test]
+fn test_private_acl_does_not_store_addresses() {
+    let acl = PrivateAcl::new(
+        [7u8; 32],
+        [[2u8; 20], [1u8; 20], [2u8; 20]],
+        AclKind::Allow,
+        false,
+    );
+    assert_eq!(acl.hashed_addresses.len(), 2);
+    assert!(acl.hashed_addresses.is_sorted());
+    assert!(acl
+        .hashed_addresses
+        .contains(&PrivateAcl::hash_address(&[7u8; 32], &[1u8; 20])));
+    assert!(!acl.hashed_addresses.iter().any(|h| h[..20] == [1u8; 20]));
+}
+
+#[cfg(feature = "evm")]
+#[test]
+fn test_capped_acl_functions_properly() {
+    use codec::{Decode, Encode};
+    use entropy_programs_evm::{LegacyTransactionRequest, U256};
+
+    let exchange = [1u8; 20];
+    let payroll = [2u8; 20];
+    let other = [3u8; 20];
+    let acl = CappedAcl::<[u8; 20], U256, U256> {
+        caps: vec![
+            Cap {
+                address: Some(exchange),
+                max_value: U256::exp10(19),
+                max_fee: None,
+                max_gas_price: Some(U256::from(1_000)),
+            },
+            Cap {
+                address: Some(payroll),
+                max_value: U256::exp10(18),
+                max_fee: Some(U256::from(21_000 * 100)),
+                max_gas_price: None,
+            },
+        ],
+    };
+    let tx = |to: Option<[u8; 20]>, value: U256, gas_price: u64| LegacyTransactionRequest {
+        to: to.map(|a| NameOrAddress::Address(H160::from(a))),
+        value: Some(value),
+        gas: Some(21_000.into()),
+        gas_price: Some(gas_price.into()),
+        ..Default::default()
+    };
+    let evaluate = |tx: LegacyTransactionRequest| {
+        SatisfiableForArchitecture::<Evm>::is_satisfied_by(acl.clone(), &tx.into()).is_ok()
+    };
+
+    assert!(evaluate(tx(Some(exchange), U256::exp10(19), 1_000)));
+    assert!(!evaluate(tx(Some(exchange), U256::exp10(19) + 1, 1)));
+    assert!(evaluate(tx(Some(payroll), U256::exp10(18), 100)));
+    assert!(!evaluate(tx(Some(payroll), U256::exp10(18) + 1, 100)));
+    // over the fee cap
+    assert!(!evaluate(tx(Some(payroll), U256::one(), 101)));
+    // over the gas price cap, however low the gas limit is
+    assert!(!evaluate(tx(Some(exchange), U256::one(), 1_001)));
+    assert!(!evaluate(LegacyTransactionRequest {
+        gas: Some(1.into()),
+        ..tx(Some(exchange), U256::one(), 1_001)
+    }));
+    // recipients and contract creations without a cap are not allowed
+    assert!(!evaluate(tx(Some(other), U256::zero(), 0)));
+    assert!(!evaluate(tx(None, U256::zero(), 0)));
+    // transactions without a value cannot be checked
+    assert!(!evaluate(LegacyTransactionRequest {
+        value: None,
+        ..tx(Some(exchange), U256::zero(), 0)
+    }));
+
+    let with_creation = CappedAcl::<[u8; 20], U256, U256> {
+        caps: vec![Cap {
+            address: None,
+            max_value: U256::zero(),
+            max_fee: None,
+            max_gas_price: None,
+        }],
+    };
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        with_creation.clone(),
+        &tx(None, U256::zero(), 0).into()
+    )
+    .is_ok());
+    assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+        with_creation,
+        &tx(None, U256::one(), 0).into()
+    )
+    .is_err());
+
+    // config can be given as JSON or SCALE
+    let json = r#"{ "caps": [
+        { "address": [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1], "max_value": "0x8ac7230489e80000", "max_gas_price": "0x3e8" },
+        { "address": [2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2], "max_value": "0xde0b6b3a7640000", "max_fee": "0x200b20" }
+    ] }"#;
+    assert_eq!(
+        serde_json::from_str::<CappedAcl<[u8; 20], U256, U256>>(json).unwrap(),
+        acl
+    );
+    assert_eq!(
+        CappedAcl::decode(&mut acl.encode().as_slice()).unwrap(),
+        acl
+    );
 }
@@ -0,0 +1,189 @@
+//! Constraints on where the outputs of a PSBT go.
+
+use alloc::{format, string::ToString, vec::Vec};
+
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::bip32::{ChildNumber, DerivationPath, Fingerprint, Xpub};
+use bitcoin::secp256k1::{PublicKey, Secp256k1, Verification};
+use bitcoin::{Address, CompressedPublicKey, Psbt, ScriptBuf};
+use entropy_programs_core::{Error as CoreError, GetSender, SatisfiableForArchitecture};
+use serde::{Deserialize, Serialize};
+
+use crate::{input_scripts, verified_fee, Btc};
+
+/// An extended public key that change addresses are derived from, with its origin as in PSBT key origins (eg.
+/// the master key fingerprint and `m/84'/0'/0'` for a BIP-84 account).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeKey {
+    pub xpub: Xpub,
+    pub fingerprint: Fingerprint,
+    pub path: DerivationPath,
+}
+
+impl ChangeKey {
+    /// Returns whether a PSBT key origin is on this key's change chain (ie. `<path>/1/<index>`), or also on its
+    /// receive chain (`<path>/0/<index>`) unless `change_only`, and the script is a single key script (P2PKH,
+    /// P2SH-P2WPKH, P2WPKH or key path only P2TR) of the derived key.
+    fn derives<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        origin: &(Fingerprint, DerivationPath),
+        script: &ScriptBuf,
+        change_only: bool,
+    ) -> bool {
+        let (fingerprint, path) = origin;
+        if *fingerprint != self.fingerprint {
+            return false;
+        }
+        let Some(remainder) = path.as_ref().strip_prefix(self.path.as_ref()) else {
+            return false;
+        };
+        let on_chain = match remainder {
+            [ChildNumber::Normal { index: 1 }, ChildNumber::Normal { .. }] => true,
+            [ChildNumber::Normal { index: 0 }, ChildNumber::Normal { .. }] => !change_only,
+            _ => false,
+        };
+        if !on_chain {
+            return false;
+        }
+        self.xpub
+            .derive_pub(secp, &remainder)
+            .is_ok_and(|derived| single_key_scripts(secp, derived.public_key).contains(script))
+    }
+}
+
+/// The scripts that can be spent with just a signature of the given key.
+fn single_key_scripts<C: Verification>(secp: &Secp256k1<C>, key: PublicKey) -> [ScriptBuf; 4] {
+    let key = CompressedPublicKey(key);
+    let p2wpkh = ScriptBuf::new_p2wpkh(&key.wpubkey_hash());
+    [
+        ScriptBuf::new_p2pkh(&key.pubkey_hash()),
+        ScriptBuf::new_p2sh(&p2wpkh.script_hash()),
+        p2wpkh,
+        ScriptBuf::new_p2tr(secp, key.0.x_only_public_key().0, None),
+    ]
+}
+
+/// Requires every output of a PSBT to pay either an allowed recipient or back to the signer, and optionally
+/// limits how much leaves the signer's wallet. This stops a compromised requester from swapping the change
+/// address for their own.
+///
+/// Every input must belong to the signer: either all inputs spend the same script, or each input's BIP-32 or
+/// taproot key origin in the PSBT derives its script from one of the `change_keys`. Otherwise a requester could
+/// add an input of their own, and have an output to its script pass as change.
+///
+/// Outputs pay back to the signer if they pay the script of one of the inputs, or a change address derived from
+/// one of the `change_keys` according to the output's key origin.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeConstraint {
+    /// Recipients that outputs may pay. Addresses of any network are accepted, since only their scripts are
+    /// compared.
+    #[serde(default)]
+    pub allowed_recipients: Vec<Address<NetworkUnchecked>>,
+    /// Keys that change addresses are derived from.
+    #[serde(default)]
+    pub change_keys: Vec<ChangeKey>,
+    /// The maximum outflow in satoshis, ie. the fee plus the outputs not paying back to the signer. PSBTs whose
+    /// input amounts can't be verified (see [`crate::verified_spent_outputs`]) don't satisfy a limit.
+    #[serde(default)]
+    pub max_outflow: Option<u64>,
+}
+
+impl ChangeConstraint {
+    /// Returns whether the output at the given index pays a change address derived from one of the change keys.
+    fn is_change<C: Verification>(&self, secp: &Secp256k1<C>, psbt: &Psbt, index: usize) -> bool {
+        let (Some(output), Some(tx_out)) =
+            (psbt.outputs.get(index), psbt.unsigned_tx.output.get(index))
+        else {
+            return false;
+        };
+        let origins = output
+            .bip32_derivation
+            .values()
+            .chain(output.tap_key_origins.values().map(|(_, origin)| origin));
+        self.derives_any(secp, origins, &tx_out.script_pubkey, true)
+    }
+
+    /// Returns whether the input at the given index spends an address derived from one of the change keys.
+    fn owns_input<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        psbt: &Psbt,
+        index: usize,
+        script: &ScriptBuf,
+    ) -> bool {
+        let Some(input) = psbt.inputs.get(index) else {
+            return false;
+        };
+        let origins = input
+            .bip32_derivation
+            .values()
+            .chain(input.tap_key_origins.values().map(|(_, origin)| origin));
+        self.derives_any(secp, origins, script, false)
+    }
+
+    fn derives_any<'a, C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        mut origins: impl Iterator<Item = &'a (Fingerprint, DerivationPath)>,
+        script: &ScriptBuf,
+        change_only: bool,
+    ) -> bool {
+        origins.any(|origin| {
+            self.change_keys
+                .iter()
+                .any(|key| key.derives(secp, origin, script, change_only))
+        })
+    }
+}
+
+impl SatisfiableForArchitecture<Btc> for ChangeConstraint {
+    fn is_satisfied_by(self, psbt: &Psbt) -> Result<(), CoreError> {
+        let allowed_recipients = self
+            .allowed_recipients
+            .iter()
+            .map(|address| address.assume_checked_ref().script_pubkey())
+            .collect::<Vec<_>>();
+        let secp = Secp256k1::verification_only();
+
+        let input_scripts = input_scripts(psbt).ok_or(CoreError::Evaluation(
+            "Unable to check the inputs, an input is missing its spent output.".to_string(),
+        ))?;
+        if GetSender::<Btc>::sender(psbt).is_none() {
+            for (index, script) in input_scripts.iter().enumerate() {
+                if !self.owns_input(&secp, psbt, index, script) {
+                    return Err(CoreError::Evaluation(format!(
+                        "Input {} doesn't belong to the signer.",
+                        index
+                    )));
+                }
+            }
+        }
+
+        let mut outflow = 0u64;
+        for (index, output) in psbt.unsigned_tx.output.iter().enumerate() {
+            if input_scripts.contains(&&output.script_pubkey) || self.is_change(&secp, psbt, index)
+            {
+                continue;
+            }
+            if !allowed_recipients.contains(&output.script_pubkey) {
+                return Err(CoreError::Evaluation(format!(
+                    "Output {} pays neither an allowed recipient nor a change address.",
+                    index
+                )));
+            }
+            outflow = outflow.saturating_add(output.value.to_sat());
+        }
+
+        if let Some(max_outflow) = self.max_outflow {
+            let outflow = outflow.saturating_add(verified_fee(psbt)?);
+            if outflow > max_outflow {
+                return Err(CoreError::Evaluation(format!(
+                    "Outflow of {} sats exceeds the maximum of {} sats.",
+                    outflow, max_outflow
+                )));
+            }
+        }
+        Ok(())
+    }
+}
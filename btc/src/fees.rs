@@ -0,0 +1,93 @@
+//! Constraints on the fee a PSBT pays.
+
+use alloc::{format, vec::Vec};
+
+use bitcoin::transaction::{predict_weight, InputWeightPrediction};
+use bitcoin::Psbt;
+use entropy_programs_core::{Error as CoreError, SatisfiableForArchitecture};
+use serde::{Deserialize, Serialize};
+
+use crate::{spent_output, verified_fee, Btc};
+
+/// Caps the fee of a PSBT, computed from its inputs' spent outputs, so that a compromised requester can't spend
+/// the inputs on fees. PSBTs whose input amounts can't be verified (see [`crate::verified_spent_outputs`]) don't satisfy
+/// the constraint.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeConstraint {
+    /// The maximum fee, in satoshis.
+    #[serde(default)]
+    pub max_fee: Option<u64>,
+    /// The maximum fee rate, in satoshis per virtual byte of the signed transaction (see [`predicted_vsize`]).
+    #[serde(default)]
+    pub max_fee_rate: Option<u64>,
+}
+
+/// Predicts the virtual size of a PSBT's transaction once signed, from the types of the scripts its inputs spend.
+/// Inputs whose signatures can't be predicted (eg. multisig P2WSH) are counted without them, so the size is
+/// underestimated and the fee rate overestimated rather than the other way around.
+pub fn predicted_vsize(psbt: &Psbt) -> u64 {
+    let inputs = psbt
+        .inputs
+        .iter()
+        .zip(&psbt.unsigned_tx.input)
+        .enumerate()
+        .map(|(index, (input, tx_in))| {
+            let Ok(spent_output) = spent_output(psbt, index) else {
+                return InputWeightPrediction::new(tx_in.script_sig.len(), Vec::<usize>::new());
+            };
+            let script = &spent_output.script_pubkey;
+            if script.is_p2wpkh() {
+                InputWeightPrediction::P2WPKH_MAX
+            } else if script.is_p2tr() {
+                InputWeightPrediction::P2TR_KEY_DEFAULT_SIGHASH
+            } else if script.is_p2pkh() {
+                InputWeightPrediction::P2PKH_COMPRESSED_MAX
+            } else if script.is_p2sh()
+                && input
+                    .redeem_script
+                    .as_ref()
+                    .is_some_and(|redeem_script| redeem_script.is_p2wpkh())
+            {
+                // nested P2WPKH, whose script signature only pushes the 22 byte redeem script
+                InputWeightPrediction::new(23, [72, 33])
+            } else {
+                InputWeightPrediction::new(tx_in.script_sig.len(), Vec::<usize>::new())
+            }
+        })
+        .collect::<Vec<_>>();
+    let output_script_lens = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .map(|output| output.script_pubkey.len());
+    predict_weight(inputs, output_script_lens).to_vbytes_ceil()
+}
+
+impl SatisfiableForArchitecture<Btc> for FeeConstraint {
+    fn is_satisfied_by(self, psbt: &Psbt) -> Result<(), CoreError> {
+        let fee = verified_fee(psbt)?;
+        if let Some(max_fee) = self.max_fee {
+            if fee > max_fee {
+                return Err(CoreError::Evaluation(format!(
+                    "Fee of {} sats exceeds the maximum of {} sats.",
+                    fee, max_fee
+                )));
+            }
+        }
+        if let Some(max_fee_rate) = self.max_fee_rate {
+            let vsize = predicted_vsize(psbt);
+            // compared as fee <= rate * vsize to avoid rounding
+            if max_fee_rate
+                .checked_mul(vsize)
+                .is_some_and(|max_fee| fee > max_fee)
+            {
+                return Err(CoreError::Evaluation(format!(
+                    "Fee rate of {:.1} sat/vB exceeds the maximum of {} sat/vB.",
+                    fee as f64 / vsize as f64,
+                    max_fee_rate
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! This includes the Bitcoin `Architecture`, for BIP-174 PSBTs.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{format, string::ToString, vec::Vec};
+use core::str::{self, FromStr};
+
+pub use bitcoin;
+use bitcoin::address::NetworkUnchecked;
+pub use bitcoin::{Address, Network, Psbt, ScriptBuf, TxOut};
+use entropy_programs_core::{
+    Architecture, Error as CoreError, GetCalldata, GetChainId, GetFee, GetNonce, GetReceiver,
+    GetSender, GetValue, TryParse,
+};
+use serde::{Deserialize, Serialize};
+
+pub mod change;
+pub mod fees;
+pub mod outputs;
+pub mod sighash;
+
+/// The magic bytes that binary PSBTs start with.
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+/// Bitcoin "architecture". Addresses are output scripts (scriptPubKeys), so that they don't depend on the
+/// network, and values and fees are in satoshis.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Btc;
+
+impl Architecture for Btc {
+    type Address = ScriptBuf;
+    type AddressRaw = Vec<u8>;
+    type Value = u64;
+    type ChainId = Network;
+    type Nonce = u64;
+    type Fee = u64;
+    type TransactionRequest = Psbt;
+}
+
+/// The output spent by an input, from its `witness_utxo` or `non_witness_utxo`. Errors if the input is missing
+/// both, or if its `non_witness_utxo` isn't the transaction the input spends from, has no such output, or
+/// disagrees with its `witness_utxo`.
+///
+/// Unlike [`Psbt::spend_utxo`], this doesn't panic on an out of bounds output index, which parsing doesn't check.
+pub fn spent_output(psbt: &Psbt, input_index: usize) -> Result<&TxOut, CoreError> {
+    let invalid = |e: &str| {
+        CoreError::Evaluation(format!(
+            "Invalid spent output of input {}: {}",
+            input_index, e
+        ))
+    };
+    let (Some(input), Some(tx_in)) = (
+        psbt.inputs.get(input_index),
+        psbt.unsigned_tx.input.get(input_index),
+    ) else {
+        return Err(invalid("no such input"));
+    };
+    let Some(previous_tx) = &input.non_witness_utxo else {
+        return input
+            .witness_utxo
+            .as_ref()
+            .ok_or_else(|| invalid("missing witness and non-witness UTXO"));
+    };
+    if previous_tx.compute_txid() != tx_in.previous_output.txid {
+        return Err(invalid("non-witness UTXO isn't the spent transaction"));
+    }
+    let output = previous_tx
+        .output
+        .get(tx_in.previous_output.vout as usize)
+        .ok_or_else(|| invalid("spent output index out of bounds"))?;
+    if input
+        .witness_utxo
+        .as_ref()
+        .is_some_and(|witness_utxo| witness_utxo != output)
+    {
+        return Err(invalid("witness UTXO doesn't match the non-witness UTXO"));
+    }
+    Ok(output)
+}
+
+/// The outputs spent by the inputs, with amounts and scripts that signatures over the PSBT commit to, for
+/// constraints on how much a PSBT spends (eg. its fee).
+///
+/// Legacy and segwit v0 signatures don't commit to the amounts of the other inputs, so a requester could
+/// understate them in `witness_utxo`s. Every input must therefore give its `non_witness_utxo`, which is checked
+/// to be the transaction it spends from, unless every input spends a taproot output, since taproot signatures
+/// commit to the amounts and scripts of every input's spent output.
+pub fn verified_spent_outputs(psbt: &Psbt) -> Result<Vec<&TxOut>, CoreError> {
+    let spent_outputs = (0..psbt.inputs.len())
+        .map(|index| spent_output(psbt, index))
+        .collect::<Result<Vec<_>, _>>()?;
+    if spent_outputs
+        .iter()
+        .all(|spent_output| spent_output.script_pubkey.is_p2tr())
+    {
+        return Ok(spent_outputs);
+    }
+    match psbt
+        .inputs
+        .iter()
+        .position(|input| input.non_witness_utxo.is_none())
+    {
+        Some(index) => Err(CoreError::Evaluation(format!(
+            "Input {} is missing its non-witness UTXO, so its amount can't be verified.",
+            index
+        ))),
+        None => Ok(spent_outputs),
+    }
+}
+
+/// The fee of a PSBT, ie. the inputs' amounts minus the outputs' amounts, with the inputs' amounts from
+/// [`verified_spent_outputs`].
+pub fn verified_fee(psbt: &Psbt) -> Result<u64, CoreError> {
+    let overflow = || CoreError::Evaluation("The PSBT's amounts overflow.".to_string());
+    let inputs = verified_spent_outputs(psbt)?
+        .iter()
+        .try_fold(0u64, |total, output| {
+            total.checked_add(output.value.to_sat())
+        })
+        .ok_or_else(overflow)?;
+    let outputs = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .try_fold(0u64, |total, output| {
+            total.checked_add(output.value.to_sat())
+        })
+        .ok_or_else(overflow)?;
+    inputs.checked_sub(outputs).ok_or(CoreError::Evaluation(
+        "The PSBT's outputs exceed its inputs.".to_string(),
+    ))
+}
+
+/// The scripts of the outputs spent by the inputs, or `None` if an input's spent output is missing or invalid
+/// (see [`spent_output`]).
+pub fn input_scripts(psbt: &Psbt) -> Option<Vec<&ScriptBuf>> {
+    (0..psbt.inputs.len())
+        .map(|index| {
+            spent_output(psbt, index)
+                .ok()
+                .map(|utxo| &utxo.script_pubkey)
+        })
+        .collect()
+}
+
+/// The outputs that don't provably pay back to the signer, by index. An output only provably pays the signer if
+/// every input spends the same script and the output pays it: a requester can add an input of their own, so
+/// paying the script of just one of the inputs doesn't make an output the signer's. These are all outputs if
+/// the inputs spend several scripts or their spent outputs aren't known.
+///
+/// Change paid to a different script than the inputs (eg. a new address derived from the same wallet) is an
+/// external output, see [`change::ChangeConstraint`] for checking it.
+pub fn external_outputs(psbt: &Psbt) -> Vec<usize> {
+    let sender = GetSender::<Btc>::sender(psbt);
+    psbt.unsigned_tx
+        .output
+        .iter()
+        .enumerate()
+        .filter(|(_, output)| sender.as_ref() != Some(&output.script_pubkey))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Returns the only item, or `None` if there are several different items.
+fn only<'a, T: PartialEq + 'a>(mut items: impl Iterator<Item = &'a T>) -> Option<&'a T> {
+    let first = items.next()?;
+    items.all(|item| item == first).then_some(first)
+}
+
+impl GetSender<Btc> for Psbt {
+    /// The script spent by the inputs, if they all spend the same script.
+    fn sender(&self) -> Option<ScriptBuf> {
+        only(input_scripts(self)?.into_iter()).cloned()
+    }
+}
+
+impl GetReceiver<Btc> for Psbt {
+    /// The script of the external outputs (see [`external_outputs`]) if they all have the same script, or of
+    /// all outputs if they all pay back to the sender. PSBTs with several recipients have no single receiver.
+    fn receiver(&self) -> Option<ScriptBuf> {
+        match self.receivers()?.as_slice() {
+            [receiver] => Some(receiver.clone()),
+            _ => None,
+        }
+    }
+
+    /// The scripts of the external outputs, ie. of every output not provably paying the signer, or of all
+    /// outputs if they all pay back to the sender. `None` for PSBTs without outputs.
+    fn receivers(&self) -> Option<Vec<ScriptBuf>> {
+        let outputs = &self.unsigned_tx.output;
+        let mut indexes = external_outputs(self);
+        if indexes.is_empty() {
+            indexes = (0..outputs.len()).collect();
+        }
+        let mut receivers: Vec<ScriptBuf> = Vec::new();
+        for index in indexes {
+            let script = &outputs[index].script_pubkey;
+            if !receivers.contains(script) {
+                receivers.push(script.clone());
+            }
+        }
+        (!receivers.is_empty()).then_some(receivers)
+    }
+}
+
+impl GetValue<Btc> for Psbt {
+    /// The total amount of the external outputs, see [`external_outputs`].
+    fn value(&self) -> Option<u64> {
+        external_outputs(self)
+            .iter()
+            .try_fold(0u64, |total, index| {
+                total.checked_add(self.unsigned_tx.output[*index].value.to_sat())
+            })
+    }
+}
+
+impl GetChainId<Btc> for Psbt {
+    /// PSBTs don't say which network they are for.
+    fn chain_id(&self) -> Option<Network> {
+        None
+    }
+}
+
+impl GetCalldata<Btc> for Psbt {
+    fn calldata(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl GetNonce<Btc> for Psbt {
+    /// Bitcoin transactions spend outputs rather than having a nonce.
+    fn nonce(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl GetFee<Btc> for Psbt {
+    /// The fee, see [`verified_fee`]. `None` if an input's amount can't be verified.
+    fn fee(&self) -> Option<u64> {
+        verified_fee(self).ok()
+    }
+}
+
+impl TryParse<Btc> for Psbt {
+    /// Parses either a binary PSBT or a base64 encoded one.
+    fn try_parse(raw_psbt: &[u8]) -> Result<Self, CoreError> {
+        let psbt = match raw_psbt.starts_with(PSBT_MAGIC) {
+            true => Psbt::deserialize(raw_psbt).map_err(|e| e.to_string()),
+            false => str::from_utf8(raw_psbt)
+                .map_err(|e| e.to_string())
+                .and_then(|base64| Psbt::from_str(base64.trim()).map_err(|e| e.to_string())),
+        };
+        psbt.map_err(|e| CoreError::InvalidSignatureRequest(format!("Unable to parse PSBT: {}", e)))
+    }
+}
+
+/// Parses an address for the given network into its output script, eg. for use in configs.
+pub fn parse_address(address: &str, network: Network) -> Result<ScriptBuf, CoreError> {
+    let address = Address::<NetworkUnchecked>::from_str(address)
+        .map_err(|e| CoreError::Evaluation(format!("Invalid address `{}`: {}", address, e)))?
+        .require_network(network)
+        .map_err(|e| CoreError::Evaluation(format!("Invalid address `{}`: {}", address, e)))?;
+    Ok(address.script_pubkey())
+}
+
+#[cfg(test)]
+mod tests;
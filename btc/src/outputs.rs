@@ -0,0 +1,45 @@
+//! Constraints on every output of a PSBT.
+
+use alloc::vec;
+
+use bitcoin::Psbt;
+use entropy_programs_core::{Error as CoreError, SatisfiableForArchitecture};
+use serde::{Deserialize, Serialize};
+
+use crate::{external_outputs, Btc};
+
+/// Evaluates a constraint against every external output of a PSBT (see [`external_outputs`]), so that eg. a cap
+/// on the value applies to each recipient of a PSBT with several recipients. Each output is evaluated as the PSBT
+/// with only that output, so its receiver and value are the output's script and amount.
+///
+/// PSBTs only paying back to their inputs are evaluated as they are.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EveryOutputConstraint<C> {
+    pub constraint: C,
+}
+
+impl<C> SatisfiableForArchitecture<Btc> for EveryOutputConstraint<C>
+where
+    C: SatisfiableForArchitecture<Btc> + Clone,
+{
+    fn is_satisfied_by(self, psbt: &Psbt) -> Result<(), CoreError> {
+        let external_outputs = external_outputs(psbt);
+        if external_outputs.is_empty() {
+            return self.constraint.is_satisfied_by(psbt);
+        }
+        for index in external_outputs {
+            self.constraint
+                .clone()
+                .is_satisfied_by(&with_output(psbt, index))?;
+        }
+        Ok(())
+    }
+}
+
+/// The PSBT with only the given output.
+fn with_output(psbt: &Psbt, index: usize) -> Psbt {
+    let mut psbt = psbt.clone();
+    psbt.unsigned_tx.output = vec![psbt.unsigned_tx.output.swap_remove(index)];
+    psbt.outputs = vec![psbt.outputs.swap_remove(index)];
+    psbt
+}
@@ -0,0 +1,68 @@
+//! Signature hashes of PSBT inputs.
+//!
+//! Each input is signed separately, so programs using the [`crate::Btc`] architecture sign the hash of a single
+//! input: [`custom_hash`] is for PSBTs with one input, and [`sighash`] gets the hash of any input.
+
+use alloc::{format, vec::Vec};
+
+use bitcoin::hashes::Hash;
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::{Psbt, TxOut};
+use entropy_programs_core::{Error as CoreError, TryParse};
+
+use crate::spent_output;
+
+/// The hash an input is signed over: the BIP-341 key path spend hash for taproot inputs, the BIP-143 hash for
+/// segwit v0 inputs, and the legacy hash for other inputs.
+///
+/// Taproot hashes commit to the spent outputs of all inputs, so every input must have one. Other inputs only
+/// need their own spent output, and P2SH and P2WSH inputs need their redeem or witness script. Spent outputs
+/// are checked with [`spent_output`].
+pub fn sighash(psbt: &Psbt, input_index: usize) -> Result<[u8; 32], CoreError> {
+    let invalid = |e: &dyn core::fmt::Display| {
+        CoreError::Evaluation(format!(
+            "Unable to compute the sighash of input {}: {}",
+            input_index, e
+        ))
+    };
+    let input = psbt
+        .inputs
+        .get(input_index)
+        .ok_or_else(|| invalid(&"no such input"))?;
+    // checked here, since `Psbt::sighash_ecdsa` panics on spent output indexes out of bounds
+    let script = &spent_output(psbt, input_index)?.script_pubkey;
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+
+    if script.is_p2tr() {
+        let prevouts = (0..psbt.inputs.len())
+            .map(|index| spent_output(psbt, index))
+            .collect::<Result<Vec<&TxOut>, _>>()?;
+        let sighash_type = input.taproot_hash_ty().map_err(|e| invalid(&e))?;
+        let sighash = cache
+            .taproot_key_spend_signature_hash(input_index, &Prevouts::All(&prevouts), sighash_type)
+            .map_err(|e| invalid(&e))?;
+        return Ok(sighash.to_byte_array());
+    }
+
+    let (message, _) = psbt
+        .sighash_ecdsa(input_index, &mut cache)
+        .map_err(|e| invalid(&e))?;
+    Ok(*message.as_ref())
+}
+
+/// The sighashes of all inputs, see [`sighash`].
+pub fn sighashes(psbt: &Psbt) -> Result<Vec<[u8; 32]>, CoreError> {
+    (0..psbt.inputs.len())
+        .map(|input_index| sighash(psbt, input_index))
+        .collect()
+}
+
+/// Computes the sighash of the PSBT in a signature request's message, for use as a program's `custom_hash`.
+/// Returns `None` unless the PSBT has exactly one input.
+pub fn custom_hash(message: &[u8]) -> Option<Vec<u8>> {
+    let psbt = Psbt::try_parse(message).ok()?;
+    if psbt.inputs.len() != 1 {
+        return None;
+    }
+    sighash(&psbt, 0).ok().map(|sighash| sighash.to_vec())
+}
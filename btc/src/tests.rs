@@ -0,0 +1,517 @@
+use alloc::{string::ToString, vec, vec::Vec};
+
+use bitcoin::bip32::{ChildNumber, DerivationPath, Fingerprint, KeySource, Xpriv, Xpub};
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::hashes::Hash;
+use bitcoin::hex::DisplayHex;
+use bitcoin::secp256k1::{PublicKey, Secp256k1};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::{
+    absolute::LockTime, transaction::Version, Amount, OutPoint, Sequence, Transaction, TxIn, TxOut,
+    WPubkeyHash, Witness,
+};
+use entropy_programs_acl::{Acl, AclKind};
+use entropy_programs_core::SatisfiableForArchitecture;
+
+use super::{
+    change::{ChangeConstraint, ChangeKey},
+    fees::{predicted_vsize, FeeConstraint},
+    outputs::EveryOutputConstraint,
+    sighash::*,
+    *,
+};
+
+fn p2wpkh(byte: u8) -> ScriptBuf {
+    ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array([byte; 20]))
+}
+
+fn p2tr(byte: u8) -> ScriptBuf {
+    let mut script = vec![0x51, 0x20];
+    script.extend([byte; 32]);
+    ScriptBuf::from_bytes(script)
+}
+
+fn tx_in(previous_output: OutPoint) -> TxIn {
+    TxIn {
+        previous_output,
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::MAX,
+        witness: Witness::new(),
+    }
+}
+
+/// A PSBT spending the given outputs to the given outputs. The spent outputs are given both as witness UTXOs,
+/// and as the outputs of a single previous transaction.
+fn psbt(inputs: &[(&ScriptBuf, u64)], outputs: &[(&ScriptBuf, u64)]) -> Psbt {
+    let previous_tx = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![tx_in(OutPoint::new(Hash::from_byte_array([7; 32]), 0))],
+        output: inputs
+            .iter()
+            .map(|(script, amount)| TxOut {
+                value: Amount::from_sat(*amount),
+                script_pubkey: (*script).clone(),
+            })
+            .collect(),
+    };
+    let tx = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: (0..inputs.len())
+            .map(|vout| tx_in(OutPoint::new(previous_tx.compute_txid(), vout as u32)))
+            .collect(),
+        output: outputs
+            .iter()
+            .map(|(script, amount)| TxOut {
+                value: Amount::from_sat(*amount),
+                script_pubkey: (*script).clone(),
+            })
+            .collect(),
+    };
+    let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+    for (input, spent_output) in psbt.inputs.iter_mut().zip(&previous_tx.output) {
+        input.witness_utxo = Some(spent_output.clone());
+        input.non_witness_utxo = Some(previous_tx.clone());
+    }
+    psbt
+}
+
+fn acl(kind: AclKind, addresses: &[&ScriptBuf]) -> Acl<Vec<u8>> {
+    Acl {
+        addresses: addresses.iter().map(|script| script.to_bytes()).collect(),
+        kind,
+        allow_null_recipient: false,
+    }
+}
+
+#[test]
+fn test_can_parse_binary_and_base64_psbts() {
+    let tx = psbt(&[(&p2wpkh(1), 10_000)], &[(&p2wpkh(2), 9_000)]);
+
+    assert_eq!(Psbt::try_parse(&tx.serialize()).unwrap(), tx);
+    assert_eq!(Psbt::try_parse(tx.to_string().as_bytes()).unwrap(), tx);
+    assert!(Psbt::try_parse(b"psbt\xffnot a psbt").is_err());
+    assert!(Psbt::try_parse(b"not base64").is_err());
+}
+
+#[test]
+fn test_gets_transaction_fields() {
+    let (sender, recipient) = (p2wpkh(1), p2wpkh(2));
+    let tx = psbt(
+        &[(&sender, 10_000), (&sender, 5_000)],
+        &[(&recipient, 12_000), (&sender, 2_000)],
+    );
+
+    assert_eq!(GetSender::<Btc>::sender(&tx), Some(sender.clone()));
+    // change back to the sender isn't part of the receiver or value
+    assert_eq!(GetReceiver::<Btc>::receiver(&tx), Some(recipient.clone()));
+    assert_eq!(GetValue::<Btc>::value(&tx), Some(12_000));
+    assert_eq!(GetFee::<Btc>::fee(&tx), Some(1_000));
+    assert_eq!(GetChainId::<Btc>::chain_id(&tx), None);
+    assert_eq!(GetNonce::<Btc>::nonce(&tx), None);
+
+    // several recipients have no single receiver
+    let tx = psbt(
+        &[(&sender, 10_000)],
+        &[(&recipient, 5_000), (&p2wpkh(3), 4_000)],
+    );
+    assert_eq!(GetReceiver::<Btc>::receiver(&tx), None);
+    assert_eq!(
+        GetReceiver::<Btc>::receivers(&tx),
+        Some(vec![recipient.clone(), p2wpkh(3)])
+    );
+    assert_eq!(GetValue::<Btc>::value(&tx), Some(9_000));
+
+    // consolidations pay the sender
+    let tx = psbt(&[(&sender, 10_000)], &[(&sender, 9_000)]);
+    assert_eq!(GetReceiver::<Btc>::receiver(&tx), Some(sender.clone()));
+    assert_eq!(GetValue::<Btc>::value(&tx), Some(0));
+
+    // inputs spending different scripts have no single sender
+    let tx = psbt(
+        &[(&sender, 10_000), (&p2wpkh(4), 5_000)],
+        &[(&recipient, 9_000)],
+    );
+    assert_eq!(GetSender::<Btc>::sender(&tx), None);
+
+    // so outputs to the script of one of them aren't change, eg. to an input the requester added themselves
+    let (victim, allowed, attacker) = (p2wpkh(1), p2wpkh(2), p2wpkh(9));
+    let tx = psbt(
+        &[(&victim, 1_000_000), (&attacker, 1_500)],
+        &[(&allowed, 1_000), (&attacker, 1_000_000)],
+    );
+    assert_eq!(
+        GetReceiver::<Btc>::receivers(&tx),
+        Some(vec![allowed.clone(), attacker.clone()])
+    );
+    assert_eq!(GetValue::<Btc>::value(&tx), Some(1_001_000));
+    assert!(SatisfiableForArchitecture::<Btc>::is_satisfied_by(
+        acl(AclKind::Allow, &[&allowed]),
+        &tx
+    )
+    .is_err());
+}
+
+#[test]
+fn test_can_parse_addresses() {
+    // from BIP-173
+    let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    let script = parse_address(address, Network::Bitcoin).unwrap();
+    assert_eq!(
+        script.to_hex_string(),
+        "0014751e76e8199196d454941c45d1b3a323f1433bd6"
+    );
+    assert!(parse_address(address, Network::Testnet).is_err());
+    assert!(parse_address(
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5",
+        Network::Bitcoin
+    )
+    .is_err());
+}
+
+#[test]
+fn test_computes_segwit_v0_sighashes() {
+    // native P2WPKH example from BIP-143
+    let tx: Transaction = deserialize_hex("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000").unwrap();
+    let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+    psbt.inputs[1].witness_utxo = Some(TxOut {
+        value: Amount::from_sat(600_000_000),
+        script_pubkey: ScriptBuf::from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap(),
+    });
+
+    assert_eq!(
+        sighash(&psbt, 1).unwrap().to_lower_hex_string(),
+        "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+    );
+    // the first input's spent output is missing
+    assert!(sighash(&psbt, 0).is_err());
+    assert!(sighashes(&psbt).is_err());
+    assert!(sighash(&psbt, 2).is_err());
+    // custom hashes are only for single input PSBTs
+    assert_eq!(custom_hash(&psbt.serialize()), None);
+}
+
+#[test]
+fn test_rejects_invalid_spent_outputs() {
+    let previous_tx = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![tx_in(OutPoint::new(Hash::from_byte_array([7; 32]), 0))],
+        output: vec![TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: p2wpkh(1),
+        }],
+    };
+    let spending = |previous_output: OutPoint| {
+        let mut tx = psbt(&[(&p2wpkh(1), 10_000)], &[(&p2wpkh(2), 9_000)]);
+        tx.unsigned_tx.input[0].previous_output = previous_output;
+        tx.inputs[0].witness_utxo = None;
+        tx.inputs[0].non_witness_utxo = Some(previous_tx.clone());
+        tx
+    };
+
+    let valid = spending(OutPoint::new(previous_tx.compute_txid(), 0));
+    assert_eq!(spent_output(&valid, 0).unwrap(), &previous_tx.output[0]);
+    assert!(custom_hash(&valid.serialize()).is_some());
+
+    // an output index past the end of the previous transaction parses, but has no sighash rather than panicking
+    let out_of_bounds = spending(OutPoint::new(previous_tx.compute_txid(), 5));
+    let parsed = Psbt::try_parse(&out_of_bounds.serialize()).unwrap();
+    assert!(spent_output(&parsed, 0).is_err());
+    assert!(sighash(&parsed, 0).is_err());
+    assert_eq!(custom_hash(&out_of_bounds.serialize()), None);
+    assert_eq!(GetSender::<Btc>::sender(&parsed), None);
+    assert!(FeeConstraint::default().is_satisfied_by(&parsed).is_err());
+
+    // the previous transaction must be the one the input spends from
+    let wrong_tx = spending(OutPoint::new(Hash::from_byte_array([7; 32]), 0));
+    assert!(spent_output(&wrong_tx, 0).is_err());
+    assert_eq!(custom_hash(&wrong_tx.serialize()), None);
+
+    // and agree with the witness UTXO
+    let mut disagreeing = valid.clone();
+    disagreeing.inputs[0].witness_utxo = Some(TxOut {
+        value: Amount::from_sat(1_000),
+        script_pubkey: p2wpkh(1),
+    });
+    assert!(spent_output(&disagreeing, 0).is_err());
+    assert!(sighash(&disagreeing, 0).is_err());
+}
+
+#[test]
+fn test_computes_taproot_sighashes() {
+    let tx = psbt(
+        &[(&p2tr(1), 10_000), (&p2wpkh(2), 5_000)],
+        &[(&p2wpkh(3), 14_000)],
+    );
+    let prevouts = tx
+        .iter_funding_utxos()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let expected = SighashCache::new(&tx.unsigned_tx)
+        .taproot_key_spend_signature_hash(0, &Prevouts::All(&prevouts), TapSighashType::Default)
+        .unwrap();
+
+    let sighashes = sighashes(&tx).unwrap();
+    assert_eq!(sighashes[0], expected.to_byte_array());
+    assert_ne!(sighashes[0], sighashes[1]);
+
+    let single_input = psbt(&[(&p2tr(1), 10_000)], &[(&p2wpkh(3), 9_000)]);
+    assert_eq!(
+        custom_hash(&single_input.serialize()),
+        Some(sighash(&single_input, 0).unwrap().to_vec())
+    );
+}
+
+#[test]
+fn test_acls_apply_to_the_receiver_or_every_output() {
+    let (sender, allowed, other) = (p2wpkh(1), p2wpkh(2), p2wpkh(3));
+    let single_recipient = psbt(&[(&sender, 10_000)], &[(&allowed, 5_000), (&sender, 4_000)]);
+    let several_recipients = psbt(&[(&sender, 10_000)], &[(&allowed, 5_000), (&other, 4_000)]);
+    let allowlist = acl(AclKind::Allow, &[&allowed]);
+
+    assert!(SatisfiableForArchitecture::<Btc>::is_satisfied_by(
+        allowlist.clone(),
+        &single_recipient
+    )
+    .is_ok());
+    // every recipient must be allowed
+    assert!(SatisfiableForArchitecture::<Btc>::is_satisfied_by(
+        allowlist.clone(),
+        &several_recipients
+    )
+    .is_err());
+    assert!(SatisfiableForArchitecture::<Btc>::is_satisfied_by(
+        Acl {
+            allow_null_recipient: true,
+            ..allowlist.clone()
+        },
+        &several_recipients
+    )
+    .is_err());
+    assert!(SatisfiableForArchitecture::<Btc>::is_satisfied_by(
+        acl(AclKind::Allow, &[&allowed, &other]),
+        &several_recipients
+    )
+    .is_ok());
+    assert!(SatisfiableForArchitecture::<Btc>::is_satisfied_by(
+        acl(AclKind::Deny, &[&other]),
+        &several_recipients
+    )
+    .is_err());
+
+    let every_output = |constraint: Acl<Vec<u8>>| EveryOutputConstraint { constraint };
+    assert!(every_output(allowlist.clone())
+        .is_satisfied_by(&single_recipient)
+        .is_ok());
+    assert!(every_output(allowlist)
+        .is_satisfied_by(&several_recipients)
+        .is_err());
+    assert!(every_output(acl(AclKind::Allow, &[&allowed, &other]))
+        .is_satisfied_by(&several_recipients)
+        .is_ok());
+    assert!(every_output(acl(AclKind::Deny, &[&other]))
+        .is_satisfied_by(&several_recipients)
+        .is_err());
+}
+
+#[test]
+fn test_caps_fees_and_fee_rates() {
+    // 1 P2WPKH input and 2 P2WPKH outputs, 141 vbytes once signed
+    let tx = psbt(
+        &[(&p2wpkh(1), 100_000)],
+        &[(&p2wpkh(2), 50_000), (&p2wpkh(1), 48_590)],
+    );
+    assert_eq!(predicted_vsize(&tx), 141);
+    assert_eq!(GetFee::<Btc>::fee(&tx), Some(1_410));
+
+    let fee_constraint = |max_fee, max_fee_rate| FeeConstraint {
+        max_fee,
+        max_fee_rate,
+    };
+    assert!(fee_constraint(None, None).is_satisfied_by(&tx).is_ok());
+    assert!(fee_constraint(Some(1_410), Some(10))
+        .is_satisfied_by(&tx)
+        .is_ok());
+    assert!(fee_constraint(Some(1_409), None)
+        .is_satisfied_by(&tx)
+        .is_err());
+    assert!(fee_constraint(None, Some(9)).is_satisfied_by(&tx).is_err());
+
+    // the fee is unknown without the spent outputs
+    let mut unknown_fee = tx.clone();
+    unknown_fee.inputs[0].witness_utxo = None;
+    unknown_fee.inputs[0].non_witness_utxo = None;
+    assert!(fee_constraint(Some(u64::MAX), None)
+        .is_satisfied_by(&unknown_fee)
+        .is_err());
+
+    // segwit v0 signatures don't commit to the other inputs' amounts, so they must be verified against the
+    // previous transactions
+    let mut understated = psbt(
+        &[(&p2wpkh(1), 100_000), (&p2wpkh(3), 1_000)],
+        &[(&p2wpkh(2), 100_500)],
+    );
+    assert_eq!(GetFee::<Btc>::fee(&understated), Some(500));
+    understated.inputs[1].non_witness_utxo = None;
+    understated.inputs[1].witness_utxo.as_mut().unwrap().value = Amount::from_sat(600);
+    assert_eq!(GetFee::<Btc>::fee(&understated), None);
+    assert!(fee_constraint(Some(u64::MAX), None)
+        .is_satisfied_by(&understated)
+        .is_err());
+    let mut config = ChangeConstraint {
+        allowed_recipients: vec![Address::from_script(&p2wpkh(2), Network::Bitcoin)
+            .unwrap()
+            .into_unchecked()],
+        change_keys: vec![],
+        max_outflow: Some(u64::MAX),
+    };
+    let mut understated = psbt(
+        &[(&p2wpkh(1), 100_000), (&p2wpkh(1), 1_000)],
+        &[(&p2wpkh(2), 100_500)],
+    );
+    assert!(config.clone().is_satisfied_by(&understated).is_ok());
+    understated.inputs[1].non_witness_utxo = None;
+    assert!(config.clone().is_satisfied_by(&understated).is_err());
+    // the outflow is only limited if configured
+    config.max_outflow = None;
+    assert!(config.is_satisfied_by(&understated).is_ok());
+
+    // while taproot signatures commit to every input's amount
+    let mut taproot = psbt(
+        &[(&p2tr(1), 100_000), (&p2tr(3), 1_000)],
+        &[(&p2wpkh(2), 100_500)],
+    );
+    for input in &mut taproot.inputs {
+        input.non_witness_utxo = None;
+    }
+    assert_eq!(GetFee::<Btc>::fee(&taproot), Some(500));
+    assert!(fee_constraint(Some(500), None)
+        .is_satisfied_by(&taproot)
+        .is_ok());
+}
+
+/// A BIP-84 account key, and the public key, PSBT key origin and P2WPKH script of an address on its given chain.
+fn account_key(chain: u32, index: u32) -> (ChangeKey, PublicKey, KeySource, ScriptBuf) {
+    let secp = Secp256k1::new();
+    let master = Xpriv::new_master(Network::Bitcoin, &[1; 32]).unwrap();
+    let account_path = DerivationPath::from_str("m/84'/0'/0'").unwrap();
+    let account = Xpub::from_priv(&secp, &master.derive_priv(&secp, &account_path).unwrap());
+    let path = account_path.extend([
+        ChildNumber::from_normal_idx(chain).unwrap(),
+        ChildNumber::from_normal_idx(index).unwrap(),
+    ]);
+    let key = Xpub::from_priv(&secp, &master.derive_priv(&secp, &path).unwrap()).to_pub();
+
+    let change_key = ChangeKey {
+        xpub: account,
+        fingerprint: master.fingerprint(&secp),
+        path: account_path,
+    };
+    let script = ScriptBuf::new_p2wpkh(&key.wpubkey_hash());
+    (change_key, key.0, (master.fingerprint(&secp), path), script)
+}
+
+#[test]
+fn test_requires_outputs_to_be_allowed_or_change() {
+    let (sender, recipient) = (p2wpkh(1), p2wpkh(2));
+    let (change_key, key, origin, change) = account_key(1, 5);
+    let mut tx = psbt(
+        &[(&sender, 100_000)],
+        &[(&recipient, 50_000), (&change, 49_000)],
+    );
+    let mut config: ChangeConstraint = serde_json::from_value(serde_json::json!({
+        "allowed_recipients": [Address::from_script(&recipient, Network::Bitcoin).unwrap()],
+        "change_keys": [change_key],
+    }))
+    .unwrap();
+
+    // the change output has no key origin
+    assert!(config.clone().is_satisfied_by(&tx).is_err());
+    tx.outputs[1].bip32_derivation.insert(key, origin.clone());
+    assert!(config.clone().is_satisfied_by(&tx).is_ok());
+
+    config.max_outflow = Some(51_000);
+    assert!(config.clone().is_satisfied_by(&tx).is_ok());
+    config.max_outflow = Some(50_999);
+    assert!(config.clone().is_satisfied_by(&tx).is_err());
+    config.max_outflow = None;
+
+    // a swapped change address with the key origin of the real one
+    let mut swapped = tx.clone();
+    swapped.unsigned_tx.output[1].script_pubkey = p2wpkh(3);
+    assert!(config.clone().is_satisfied_by(&swapped).is_err());
+
+    // addresses on the receive chain aren't change
+    let (_, receive_key, receive_origin, receive) = account_key(0, 5);
+    let mut receive_chain = psbt(
+        &[(&sender, 100_000)],
+        &[(&recipient, 50_000), (&receive, 49_000)],
+    );
+    receive_chain.outputs[1]
+        .bip32_derivation
+        .insert(receive_key, receive_origin);
+    assert!(config.clone().is_satisfied_by(&receive_chain).is_err());
+
+    // nor are addresses of another wallet
+    let mut other_wallet = tx.clone();
+    other_wallet.outputs[1]
+        .bip32_derivation
+        .insert(key, (Fingerprint::from([0; 4]), origin.1));
+    assert!(config.clone().is_satisfied_by(&other_wallet).is_err());
+
+    // other recipients aren't allowed
+    let mut unknown_recipient = tx.clone();
+    unknown_recipient.unsigned_tx.output[0].script_pubkey = p2wpkh(4);
+    assert!(config.is_satisfied_by(&unknown_recipient).is_err());
+}
+
+#[test]
+fn test_requires_inputs_to_belong_to_the_signer() {
+    let (victim, allowed, attacker) = (p2wpkh(1), p2wpkh(2), p2wpkh(9));
+    let config = ChangeConstraint {
+        allowed_recipients: vec![Address::from_script(&allowed, Network::Bitcoin)
+            .unwrap()
+            .into_unchecked()],
+        change_keys: vec![],
+        max_outflow: Some(5_000),
+    };
+
+    // an input of the requester's own doesn't make an output to its script change
+    let tx = psbt(
+        &[(&victim, 1_000_000), (&attacker, 1_500)],
+        &[(&allowed, 1_000), (&attacker, 1_000_000)],
+    );
+    assert!(config.clone().is_satisfied_by(&tx).is_err());
+    // while change back to the signer's only script is
+    let tx = psbt(
+        &[(&victim, 1_000_000), (&victim, 1_500)],
+        &[(&allowed, 1_000), (&victim, 1_000_000)],
+    );
+    assert!(config.clone().is_satisfied_by(&tx).is_ok());
+
+    // inputs spending different scripts must be derived from the change keys, on either chain
+    let (change_key, receive_key, receive_origin, receive) = account_key(0, 3);
+    let (_, change_input_key, change_input_origin, change_input) = account_key(1, 2);
+    let config = ChangeConstraint {
+        change_keys: vec![change_key],
+        ..config
+    };
+    let mut tx = psbt(
+        &[(&receive, 600_000), (&change_input, 400_000)],
+        &[(&allowed, 1_000), (&receive, 998_000)],
+    );
+    assert!(config.clone().is_satisfied_by(&tx).is_err());
+    tx.inputs[0]
+        .bip32_derivation
+        .insert(receive_key, receive_origin);
+    assert!(config.clone().is_satisfied_by(&tx).is_err());
+    tx.inputs[1]
+        .bip32_derivation
+        .insert(change_input_key, change_input_origin);
+    assert!(config.clone().is_satisfied_by(&tx).is_ok());
+    // but not with the key origin of another input
+    tx.inputs[1].bip32_derivation = tx.inputs[0].bip32_derivation.clone();
+    assert!(config.is_satisfied_by(&tx).is_err());
+}
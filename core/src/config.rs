@@ -0,0 +1,78 @@
+//! Types and serde helpers shared by the configs of programs for different architectures.
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+
+/// Represents either an allow or deny list.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Encode,
+    Decode,
+    TypeInfo,
+    MaxEncodedLen,
+    Serialize,
+    Deserialize,
+)]
+pub enum AclKind {
+    #[default]
+    Allow,
+    Deny,
+}
+
+impl AclKind {
+    /// Returns whether an item is permitted, given whether it is in the list.
+    pub fn permits(&self, listed: bool) -> bool {
+        matches!(
+            (listed, self),
+            (true, AclKind::Allow) | (false, AclKind::Deny)
+        )
+    }
+}
+
+/// (De)serializes a 4-byte function selector as a hex string, eg. `"0xa9059cbb"`. Use with
+/// `#[serde(with = "entropy_programs_core::config::selector_hex")]`, or the `vec` module for lists of selectors.
+pub mod selector_hex {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(selector: &[u8; 4], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(selector)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 4], D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = hex::decode(s.trim_start_matches("0x")).map_err(serde::de::Error::custom)?;
+        bytes
+            .try_into()
+            .map_err(|_| serde::de::Error::custom("selector must be 4 bytes"))
+    }
+
+    pub mod vec {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        #[derive(Serialize, Deserialize)]
+        struct Hex(#[serde(with = "super")] [u8; 4]);
+
+        pub fn serialize<S: Serializer>(
+            selectors: &[[u8; 4]],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(selectors.iter().map(|selector| Hex(*selector)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<[u8; 4]>, D::Error> {
+            let selectors = Vec::<Hex>::deserialize(deserializer)?;
+            Ok(selectors
+                .into_iter()
+                .map(|Hex(selector)| selector)
+                .collect())
+        }
+    }
+}
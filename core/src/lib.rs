@@ -1,6 +1,7 @@
 //! This supports core traits and types for supporting new architectures and programs, and interfacing with them.
 
 /// See the [`wit-bindgen` Rust guest example](https://github.com/bytecodealliance/wit-bindgen#guest-rust) for information on how to use this.
+#[allow(clippy::too_many_arguments)]
 pub mod bindgen {
     wit_bindgen::generate!({
         world: "program",
@@ -10,28 +11,48 @@ pub mod bindgen {
 
 pub use bindgen::Error;
 
+pub mod config;
 pub mod programs;
 
 pub use architecture::*;
+pub use config::AclKind;
 pub use programs::*;
 
 /// Each transaction-like architecture should implement these.
 pub mod architecture {
     use super::bindgen::Error;
+    use core::fmt::Debug;
     use serde::{Deserialize, Serialize};
 
     /// Trait for defining important types associated with an architecture.
     pub trait Architecture: Serialize + for<'de> Deserialize<'de> {
         /// Account type for that chain(SS58, H160, etc)
-        type Address: Eq + Serialize + for<'de> Deserialize<'de> + From<Self::AddressRaw>;
+        type Address: Eq
+            + Clone
+            + Debug
+            + Serialize
+            + for<'de> Deserialize<'de>
+            + From<Self::AddressRaw>;
         /// Account type as it is stored in the database
         type AddressRaw: Eq + Serialize + for<'de> Deserialize<'de> + From<Self::Address>;
+        /// Amount of the native asset transferred by a transaction (eg. wei as a U256 for EVM)
+        type Value: Ord + Clone + Debug + Serialize + for<'de> Deserialize<'de>;
+        /// Identifier of the chain a transaction is valid on (eg. EIP-155 chain id for EVM)
+        type ChainId: Eq + Clone + Debug + Serialize + for<'de> Deserialize<'de>;
+        /// Replay-protection counter of the sender's account
+        type Nonce: Ord + Clone + Debug + Serialize + for<'de> Deserialize<'de>;
+        /// Maximum fee a transaction can pay, denominated in the native asset
+        type Fee: Ord + Clone + Debug + Serialize + for<'de> Deserialize<'de>;
         /// Transaction request type for unsigned transactions
         type TransactionRequest: GetSender<Self>
             + GetReceiver<Self>
+            + GetValue<Self>
+            + GetChainId<Self>
+            + GetCalldata<Self>
+            + GetNonce<Self>
+            + GetFee<Self>
             + Serialize
             + for<'de> Deserialize<'de>
-            + Parse<Self>
             + TryParse<Self>;
     }
 
@@ -42,12 +63,53 @@ pub mod architecture {
 
     /// Trait for getting the the receiver of a transaction.
     pub trait GetReceiver<A: Architecture> {
+        /// The receiver, or `None` if the transaction has no receiver or no single receiver.
         fn receiver(&self) -> Option<A::Address>;
+
+        /// Every receiver of the transaction, for transactions that can have several (eg. batches). Empty if the
+        /// transaction has no receiver, and `None` if its receivers can't be determined, in which case constraints
+        /// on the receiver must not be satisfied.
+        fn receivers(&self) -> Option<Vec<A::Address>> {
+            Some(self.receiver().into_iter().collect())
+        }
+    }
+
+    /// Trait for getting the amount of the native asset transferred by a transaction.
+    pub trait GetValue<A: Architecture> {
+        fn value(&self) -> Option<A::Value>;
+    }
+
+    /// Trait for getting the chain id a transaction is bound to.
+    pub trait GetChainId<A: Architecture> {
+        fn chain_id(&self) -> Option<A::ChainId>;
+    }
+
+    /// Trait for getting the calldata (eg. contract call input) of a transaction.
+    pub trait GetCalldata<A: Architecture> {
+        fn calldata(&self) -> Option<&[u8]>;
+    }
+
+    /// Trait for getting the nonce of a transaction.
+    pub trait GetNonce<A: Architecture> {
+        fn nonce(&self) -> Option<A::Nonce>;
+    }
+
+    /// Trait for getting the maximum fee a transaction can pay.
+    pub trait GetFee<A: Architecture> {
+        fn fee(&self) -> Option<A::Fee>;
+
+        /// The maximum price the transaction pays per unit of gas (eg. the max fee per gas in wei for EVM), for
+        /// architectures that have one. `None` if it isn't known.
+        fn gas_price(&self) -> Option<A::Fee> {
+            None
+        }
     }
 
     /// DEPRECATED: Use `TryParse`
     ///
-    /// Trait for parsing a raw transaction request into its native transaction request struct.
+    /// Trait for parsing a raw transaction request into its native transaction request struct. This is no longer
+    /// required by `Architecture`, but existing implementations keep working.
+    #[deprecated(note = "Use `TryParse`, which is required by `Architecture`")]
     pub trait Parse<A: Architecture> {
         fn parse(raw_tx: String) -> Result<A::TransactionRequest, Error>;
     }
@@ -9,6 +9,7 @@ use crate::bindgen::Error;
 /// Programs using binary (or other unserialized) data must implement this. This is the most barebones trait for programs.
 pub trait Satisfiable {
     /// Indicates that the data satisfies the constraint.
+    #[allow(clippy::wrong_self_convention)]
     fn is_satisfied_by(self, data: &[u8]) -> Result<(), Error>;
 }
 
@@ -29,24 +30,25 @@ pub trait Satisfiable {
 ///    allow_null_recipient: false,
 /// };
 ///
-/// let non_blacklisted_recipient_tx = EvmTransactionRequest {
+/// let non_blacklisted_recipient_tx: EvmTransactionRequest = LegacyTransactionRequest {
 ///    to: Some(NameOrAddress::Address(H160::from(non_blacklisted_addr))),
 ///   ..Default::default()
-/// };
+/// }.into();
 ///
-/// let blacklisted_recipient_tx = EvmTransactionRequest {
+/// let blacklisted_recipient_tx: EvmTransactionRequest = LegacyTransactionRequest {
 ///    to: Some(NameOrAddress::Address(H160::from(blacklisted_addr_1))),
 ///   ..Default::default()
-/// };
+/// }.into();
 ///
 /// // This will be allowed, since the recipient is not in the blacklisted ACL.
-/// no_malicious_addresses.clone().is_satisfied_by(&non_blacklisted_recipient_tx)?;
+/// SatisfiableForArchitecture::<Evm>::is_satisfied_by(no_malicious_addresses.clone(), &non_blacklisted_recipient_tx)?;
 /// // This will return an error, because the recipient is not in the ACL.
-/// assert!(no_malicious_addresses.is_satisfied_by(&blacklisted_recipient_tx).is_err());
+/// assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(no_malicious_addresses, &blacklisted_recipient_tx).is_err());
 /// Ok::<(), CoreError>(())
 /// ```
 ///
 pub trait SatisfiableForArchitecture<A: Architecture> {
     /// Indicates that the transaction request satisfies the constraint.
+    #[allow(clippy::wrong_self_convention)]
     fn is_satisfied_by(self, tx: &<A as Architecture>::TransactionRequest) -> Result<(), Error>;
 }
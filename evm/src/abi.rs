@@ -0,0 +1,134 @@
+//! Decoding of contract calldata against JSON ABIs.
+//!
+//! Programs usually take the ABI (or just the fragments for the functions they care about) as part of their
+//! config, since [`Abi`] deserializes from the standard JSON ABI format.
+
+use alloc::{format, string::ToString, vec::Vec};
+
+use entropy_programs_core::Error as CoreError;
+pub use ethers_core::abi::{Abi, Function, Param, ParamType, Token};
+use ethers_core::types::{Bytes, H160, U256};
+
+/// The first 4 bytes of calldata, identifying the function being called.
+pub type Selector = [u8; 4];
+
+/// Gets the function selector of calldata, or `None` if it is too short to call a function.
+pub fn selector(calldata: &[u8]) -> Option<Selector> {
+    calldata.get(..4)?.try_into().ok()
+}
+
+/// A call made by a transaction or a contract, eg. one of the calls of a batch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Call {
+    /// The contract or account being called
+    pub to: H160,
+    pub value: U256,
+    pub data: Bytes,
+}
+
+/// A contract call decoded against an ABI.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedCall<'a> {
+    /// The ABI of the called function
+    pub function: &'a Function,
+    /// The arguments, in the order of the function's inputs
+    pub args: Vec<Token>,
+}
+
+impl DecodedCall<'_> {
+    /// Gets an argument by the name of its input.
+    pub fn arg(&self, name: &str) -> Option<&Token> {
+        self.function
+            .inputs
+            .iter()
+            .position(|input| input.name == name)
+            .and_then(|index| self.args.get(index))
+    }
+}
+
+/// Decodes calldata by looking up its selector in the ABI and decoding the arguments against the function's
+/// inputs. Errors if the function is not in the ABI or the arguments don't match its inputs.
+pub fn decode_calldata<'a>(abi: &'a Abi, calldata: &[u8]) -> Result<DecodedCall<'a>, CoreError> {
+    let selector = selector(calldata).ok_or(CoreError::Evaluation(
+        "Calldata is too short to contain a function selector.".to_string(),
+    ))?;
+    let function = abi
+        .functions()
+        .find(|function| function.short_signature() == selector)
+        .ok_or(CoreError::Evaluation(format!(
+            "Function with selector 0x{} not found in ABI.",
+            hex::encode(selector)
+        )))?;
+    let args = function.decode_input(&calldata[4..]).map_err(|e| {
+        CoreError::Evaluation(format!(
+            "Unable to decode arguments of `{}`: {}",
+            function.name, e
+        ))
+    })?;
+
+    Ok(DecodedCall { function, args })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ERC20_ABI: &str = r#"[
+        {
+            "type": "function",
+            "name": "transfer",
+            "inputs": [
+                { "name": "to", "type": "address" },
+                { "name": "amount", "type": "uint256" }
+            ],
+            "outputs": [{ "name": "", "type": "bool" }],
+            "stateMutability": "nonpayable"
+        },
+        {
+            "type": "event",
+            "name": "Transfer",
+            "inputs": [
+                { "name": "from", "type": "address", "indexed": true },
+                { "name": "to", "type": "address", "indexed": true },
+                { "name": "value", "type": "uint256", "indexed": false }
+            ],
+            "anonymous": false
+        }
+    ]"#;
+
+    // transfer(0x772b9a9e8aa1c9db861c6611a82d251db4fac990, 1000)
+    const TRANSFER_CALLDATA: &str = "a9059cbb000000000000000000000000772b9a9e8aa1c9db861c6611a82d251db4fac99000000000000000000000000000000000000000000000000000000000000003e8";
+
+    #[test]
+    fn can_decode_calldata_against_abi() {
+        let abi: Abi = serde_json::from_str(ERC20_ABI).unwrap();
+        let calldata = hex::decode(TRANSFER_CALLDATA).unwrap();
+        assert_eq!(selector(&calldata), Some([0xa9, 0x05, 0x9c, 0xbb]));
+
+        let call = decode_calldata(&abi, &calldata).unwrap();
+        assert_eq!(call.function.name, "transfer");
+        assert_eq!(
+            call.arg("to"),
+            Some(&Token::Address(H160::from_slice(
+                &hex::decode("772b9a9e8aa1c9db861c6611a82d251db4fac990").unwrap()
+            )))
+        );
+        assert_eq!(call.arg("amount"), Some(&Token::Uint(U256::from(1000))));
+        assert_eq!(call.arg("value"), None);
+    }
+
+    #[test]
+    fn rejects_unknown_or_malformed_calldata() {
+        let abi: Abi = serde_json::from_str(ERC20_ABI).unwrap();
+        let calldata = hex::decode(TRANSFER_CALLDATA).unwrap();
+
+        assert_eq!(selector(&calldata[..3]), None);
+        assert!(decode_calldata(&abi, &calldata[..3]).is_err());
+        // approve(address,uint256) is not in the ABI
+        let mut approve = calldata.clone();
+        approve[..4].copy_from_slice(&[0x09, 0x5e, 0xa7, 0xb3]);
+        assert!(decode_calldata(&abi, &approve).is_err());
+        // truncated arguments
+        assert!(decode_calldata(&abi, &calldata[..40]).is_err());
+    }
+}
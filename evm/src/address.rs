@@ -0,0 +1,247 @@
+//! Parsing of hex addresses, validating EIP-55 checksums.
+//!
+//! Config addresses should be parsed with [`parse_address`], or deserialized with the [`checksummed`],
+//! [`checksummed_option`], [`checksummed_vec`] or [`checksummed_option_vec`] modules, so that mistyped
+//! addresses are rejected rather than silently allowed.
+
+use alloc::{format, string::String, vec::Vec};
+
+use entropy_programs_core::Error as CoreError;
+use ethers_core::types::H160;
+use ethers_core::utils::to_checksum;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Parses a hex address, with or without a `0x` prefix. Mixed case addresses must have a valid EIP-55
+/// checksum, while all lowercase or all uppercase addresses aren't checksummed.
+pub fn parse_address(address: &str) -> Result<H160, CoreError> {
+    parse(address).map_err(CoreError::Evaluation)
+}
+
+fn parse(address: &str) -> Result<H160, String> {
+    let digits = address.strip_prefix("0x").unwrap_or(address);
+    if digits.len() != 40 {
+        return Err(format!(
+            "Address `{}` should have 40 hex digits, but has {}.",
+            address,
+            digits.len()
+        ));
+    }
+    if let Some(invalid) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "Address `{}` has an invalid hex digit `{}`.",
+            address, invalid
+        ));
+    }
+
+    let mut parsed = H160::zero();
+    hex::decode_to_slice(digits, parsed.as_bytes_mut()).expect("checked to be 40 hex digits");
+    let is_mixed_case = digits.chars().any(|c| c.is_ascii_lowercase())
+        && digits.chars().any(|c| c.is_ascii_uppercase());
+    let checksummed = to_checksum(&parsed, None);
+    if is_mixed_case && digits != &checksummed[2..] {
+        return Err(format!(
+            "Address `{}` has an invalid EIP-55 checksum, expected `{}`.",
+            address, checksummed
+        ));
+    }
+    Ok(parsed)
+}
+
+/// An address (de)serialized as a checksummed hex string.
+struct Checksummed(H160);
+
+impl Serialize for Checksummed {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_checksum(&self.0, None))
+    }
+}
+
+impl<'de> Deserialize<'de> for Checksummed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        parse(&address)
+            .map(Checksummed)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// (De)serializes an address with [`parse_address`], for use with `#[serde(with = "...")]`.
+pub mod checksummed {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(address: &H160, serializer: S) -> Result<S::Ok, S::Error> {
+        Checksummed(*address).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<H160, D::Error> {
+        Checksummed::deserialize(deserializer).map(|address| address.0)
+    }
+}
+
+/// (De)serializes an optional address with [`parse_address`], for use with `#[serde(with = "...")]`.
+pub mod checksummed_option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        address: &Option<H160>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        address.map(Checksummed).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<H160>, D::Error> {
+        Option::<Checksummed>::deserialize(deserializer)
+            .map(|address| address.map(|address| address.0))
+    }
+}
+
+/// (De)serializes a list of addresses with [`parse_address`], for use with `#[serde(with = "...")]`.
+pub mod checksummed_vec {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(addresses: &[H160], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(addresses.iter().map(|address| Checksummed(*address)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<H160>, D::Error> {
+        let addresses = Vec::<Checksummed>::deserialize(deserializer)?;
+        Ok(addresses.into_iter().map(|address| address.0).collect())
+    }
+}
+
+/// (De)serializes an optional list of addresses with [`parse_address`], for use with `#[serde(with = "...")]`.
+pub mod checksummed_option_vec {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        addresses: &Option<Vec<H160>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match addresses {
+            Some(addresses) => serializer.serialize_some(&ChecksummedVec(addresses)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<H160>>, D::Error> {
+        let addresses = Option::<Vec<Checksummed>>::deserialize(deserializer)?;
+        Ok(addresses.map(|addresses| addresses.into_iter().map(|address| address.0).collect()))
+    }
+
+    struct ChecksummedVec<'a>(&'a [H160]);
+
+    impl Serialize for ChecksummedVec<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::checksummed_vec::serialize(self.0, serializer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // from EIP-55
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn can_parse_addresses() {
+        let address = parse_address(CHECKSUMMED).unwrap();
+        assert_eq!(
+            address,
+            H160::from_slice(&hex::decode("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap())
+        );
+        assert_eq!(parse_address(&CHECKSUMMED[2..]).unwrap(), address);
+        assert_eq!(parse_address(&CHECKSUMMED.to_lowercase()).unwrap(), address);
+        assert_eq!(
+            parse_address(&format!("0x{}", CHECKSUMMED[2..].to_uppercase())).unwrap(),
+            address
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        let error = |address: &str| format!("{:?}", parse_address(address).unwrap_err());
+
+        assert!(error(&CHECKSUMMED[..41]).contains("should have 40 hex digits, but has 39"));
+        assert!(error(&format!("{}00", CHECKSUMMED)).contains("but has 42"));
+        assert!(error("").contains("but has 0"));
+        assert!(error(&CHECKSUMMED.replace('5', "g")).contains("invalid hex digit `g`"));
+        // a single character with the wrong case
+        assert!(error(&CHECKSUMMED.replace('F', "f")).contains(&format!(
+            "invalid EIP-55 checksum, expected `{}`",
+            CHECKSUMMED
+        )));
+    }
+
+    #[test]
+    fn can_deserialize_checksummed_addresses() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Config {
+            #[serde(with = "checksummed")]
+            address: H160,
+            #[serde(default, with = "checksummed_option")]
+            optional_address: Option<H160>,
+            #[serde(with = "checksummed_vec")]
+            addresses: Vec<H160>,
+            #[serde(default, with = "checksummed_option_vec")]
+            optional_addresses: Option<Vec<H160>>,
+        }
+
+        let address = parse_address(CHECKSUMMED).unwrap();
+        let json = format!(
+            r#"{{
+                "address": "{0}",
+                "optional_address": "{0}",
+                "addresses": ["{0}"],
+                "optional_addresses": ["{0}"]
+            }}"#,
+            CHECKSUMMED.to_lowercase()
+        );
+        let config: Config = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                address,
+                optional_address: Some(address),
+                addresses: vec![address],
+                optional_addresses: Some(vec![address]),
+            }
+        );
+        // serialized with checksums
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            format!(
+                r#"{{"address":"{0}","optional_address":"{0}","addresses":["{0}"],"optional_addresses":["{0}"]}}"#,
+                CHECKSUMMED
+            )
+        );
+
+        let json = format!(r#"{{ "address": "{0}", "addresses": [] }}"#, CHECKSUMMED);
+        let config = serde_json::from_str::<Config>(&json).unwrap();
+        assert_eq!(config.optional_address, None);
+        assert_eq!(config.optional_addresses, None);
+        let mistyped = CHECKSUMMED.replace('F', "f");
+        for json in [
+            format!(r#"{{ "address": "{0}", "addresses": [] }}"#, mistyped),
+            format!(
+                r#"{{ "address": "{0}", "optional_address": "{1}", "addresses": [] }}"#,
+                CHECKSUMMED, mistyped
+            ),
+            format!(
+                r#"{{ "address": "{0}", "addresses": ["{1}"] }}"#,
+                CHECKSUMMED, mistyped
+            ),
+            format!(
+                r#"{{ "address": "{0}", "addresses": [], "optional_addresses": ["{1}"] }}"#,
+                CHECKSUMMED, mistyped
+            ),
+        ] {
+            assert!(serde_json::from_str::<Config>(&json).is_err());
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Constraints on the chain a transaction can be signed for.
+
+use alloc::{format, string::ToString, vec::Vec};
+
+use entropy_programs_core::{
+    Error as CoreError, GetChainId, GetReceiver, SatisfiableForArchitecture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Evm, EvmTransactionRequest};
+
+/// Restricts the chains transactions are signed for, and requires EIP-155 replay protection.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainConstraint {
+    /// Chain ids transactions can be signed for. `None` allows any chain.
+    #[serde(default)]
+    pub allowed_chain_ids: Option<Vec<u64>>,
+    /// Whether legacy transactions without an EIP-155 chain id are allowed. These can be replayed on any chain,
+    /// so the allowed chain ids can't be enforced for them. Typed transactions always have a chain id.
+    #[serde(default)]
+    pub allow_missing_chain_id: bool,
+    /// Whether contract creations (transactions without a recipient) are rejected.
+    #[serde(default)]
+    pub reject_contract_creation: bool,
+}
+
+impl SatisfiableForArchitecture<Evm> for ChainConstraint {
+    fn is_satisfied_by(self, tx: &EvmTransactionRequest) -> Result<(), CoreError> {
+        if self.reject_contract_creation && GetReceiver::<Evm>::receiver(tx).is_none() {
+            return Err(CoreError::Evaluation(
+                "Contract creations are not allowed.".to_string(),
+            ));
+        }
+
+        let Some(chain_id) = GetChainId::<Evm>::chain_id(tx) else {
+            return match self.allow_missing_chain_id {
+                true => Ok(()),
+                false => Err(CoreError::Evaluation(
+                    "Transactions without EIP-155 replay protection are not allowed.".to_string(),
+                )),
+            };
+        };
+        match self.allowed_chain_ids {
+            Some(allowed) if !allowed.contains(&chain_id) => Err(CoreError::Evaluation(format!(
+                "Chain id {} not allowed.",
+                chain_id
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entropy_programs_core::TryParse;
+    use ethers_core::types::H160;
+
+    use super::*;
+    use crate::{Eip1559TransactionRequest, LegacyTransactionRequest};
+
+    #[test]
+    fn chain_constraint_functions_properly() {
+        let constraint = ChainConstraint {
+            allowed_chain_ids: Some(vec![1, 10]),
+            allow_missing_chain_id: false,
+            reject_contract_creation: true,
+        };
+        let evaluate = |tx: EvmTransactionRequest| {
+            SatisfiableForArchitecture::<Evm>::is_satisfied_by(constraint.clone(), &tx).is_ok()
+        };
+        let to = H160::repeat_byte(1);
+
+        // legacy transaction with chain id 1
+        let raw_tx = "0xef01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079018080";
+        assert!(evaluate(
+            EvmTransactionRequest::try_parse(raw_tx.as_bytes()).unwrap()
+        ));
+        assert!(evaluate(
+            Eip1559TransactionRequest::new().to(to).chain_id(10).into()
+        ));
+        assert!(!evaluate(
+            Eip1559TransactionRequest::new().to(to).chain_id(56).into()
+        ));
+        // no EIP-155 replay protection
+        assert!(!evaluate(LegacyTransactionRequest::new().to(to).into()));
+        // contract creation
+        assert!(!evaluate(
+            Eip1559TransactionRequest::new().chain_id(1).into()
+        ));
+
+        let permissive = ChainConstraint {
+            allow_missing_chain_id: true,
+            ..Default::default()
+        };
+        assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+            permissive.clone(),
+            &LegacyTransactionRequest::new().into()
+        )
+        .is_ok());
+        assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+            permissive,
+            &Eip1559TransactionRequest::new().chain_id(56).into()
+        )
+        .is_ok());
+
+        // config is given as JSON
+        let json = r#"{ "allowed_chain_ids": [1, 10], "reject_contract_creation": true }"#;
+        assert_eq!(
+            serde_json::from_str::<ChainConstraint>(json).unwrap(),
+            constraint
+        );
+    }
+}
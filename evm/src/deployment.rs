@@ -0,0 +1,287 @@
+//! Constraints on contract deployments, allowing only pre-approved init code.
+
+use alloc::{string::ToString, vec::Vec};
+
+use entropy_programs_core::{
+    Error as CoreError, GetCalldata, GetReceiver, SatisfiableForArchitecture,
+};
+use ethers_core::types::{H160, H256};
+use ethers_core::utils::{get_create2_address_from_hash, keccak256};
+use serde::{Deserialize, Serialize};
+
+use crate::{address, Evm, EvmTransactionRequest};
+
+/// A contract deployment, either directly or through a CREATE2 factory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Deployment {
+    /// A contract creation transaction, ie. one without a recipient.
+    Create { init_code_hash: H256 },
+    /// A call to a CREATE2 factory.
+    Create2 {
+        factory: H160,
+        salt: H256,
+        init_code_hash: H256,
+    },
+}
+
+impl Deployment {
+    /// Decodes the deployment a transaction makes, given the CREATE2 factories to look for. Returns `None` if
+    /// the transaction doesn't deploy a contract.
+    ///
+    /// Factories are expected to take the salt followed by the init code as calldata, like the deterministic
+    /// deployment proxy at `0x4e59b44847b379578588920ca78fbf26c0b4956c`.
+    pub fn decode(
+        tx: &EvmTransactionRequest,
+        factories: &[H160],
+    ) -> Result<Option<Self>, CoreError> {
+        let calldata = GetCalldata::<Evm>::calldata(tx).unwrap_or_default();
+        let Some(receiver) = GetReceiver::<Evm>::receiver(tx) else {
+            return Ok(Some(Deployment::Create {
+                init_code_hash: keccak256(calldata).into(),
+            }));
+        };
+        if !factories.contains(&receiver) {
+            return Ok(None);
+        }
+
+        if calldata.len() < 32 {
+            return Err(CoreError::Evaluation(
+                "CREATE2 factory calldata is too short to contain a salt.".to_string(),
+            ));
+        }
+        let (salt, init_code) = calldata.split_at(32);
+        Ok(Some(Deployment::Create2 {
+            factory: receiver,
+            salt: H256::from_slice(salt),
+            init_code_hash: keccak256(init_code).into(),
+        }))
+    }
+
+    /// The hash of the deployed contract's init code.
+    pub fn init_code_hash(&self) -> H256 {
+        match self {
+            Deployment::Create { init_code_hash } | Deployment::Create2 { init_code_hash, .. } => {
+                *init_code_hash
+            }
+        }
+    }
+
+    /// The address of the deployed contract, for CREATE2 deployments. The address of a direct deployment
+    /// depends on the sender and nonce.
+    pub fn create2_address(&self) -> Option<H160> {
+        match self {
+            Deployment::Create { .. } => None,
+            Deployment::Create2 {
+                factory,
+                salt,
+                init_code_hash,
+            } => Some(get_create2_address_from_hash(
+                *factory,
+                salt.as_bytes(),
+                init_code_hash.as_bytes(),
+            )),
+        }
+    }
+}
+
+/// Allows contract deployments only for pre-approved init code.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeploymentConstraint {
+    /// Keccak hashes of the init code that can be deployed by contract creation transactions.
+    #[serde(default)]
+    pub allowed_init_code_hashes: Vec<H256>,
+    /// CREATE2 factories, and what can be deployed through each of them.
+    #[serde(default)]
+    pub factories: Vec<Create2Factory>,
+    /// Whether transactions that don't deploy a contract are allowed.
+    #[serde(default)]
+    pub allow_other_transactions: bool,
+}
+
+/// A CREATE2 factory, see [`Deployment::decode`] for the expected calldata.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Create2Factory {
+    #[serde(with = "address::checksummed")]
+    pub address: H160,
+    pub allowed_deployments: Vec<Create2Deployment>,
+}
+
+/// Init code that can be deployed through a CREATE2 factory, optionally with a specific salt (and so address).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Create2Deployment {
+    /// The salt, or `None` to allow any salt.
+    #[serde(default)]
+    pub salt: Option<H256>,
+    pub init_code_hash: H256,
+}
+
+impl SatisfiableForArchitecture<Evm> for DeploymentConstraint {
+    fn is_satisfied_by(self, tx: &EvmTransactionRequest) -> Result<(), CoreError> {
+        let factories: Vec<H160> = self
+            .factories
+            .iter()
+            .map(|factory| factory.address)
+            .collect();
+        let allowed = match Deployment::decode(tx, &factories)? {
+            None => self.allow_other_transactions,
+            Some(Deployment::Create { init_code_hash }) => {
+                self.allowed_init_code_hashes.contains(&init_code_hash)
+            }
+            Some(Deployment::Create2 {
+                factory,
+                salt,
+                init_code_hash,
+            }) => self
+                .factories
+                .iter()
+                .filter(|allowed| allowed.address == factory)
+                .flat_map(|allowed| &allowed.allowed_deployments)
+                .any(|deployment| {
+                    deployment.init_code_hash == init_code_hash
+                        && deployment.salt.is_none_or(|allowed| allowed == salt)
+                }),
+        };
+
+        match allowed {
+            true => Ok(()),
+            false => Err(CoreError::Evaluation(
+                "Transaction is not an allowed deployment.".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Eip1559TransactionRequest, LegacyTransactionRequest};
+
+    const INIT_CODE: &[u8] = &[0x60, 0x80, 0x60, 0x40, 0x52];
+    const FACTORY: H160 = H160::repeat_byte(0xfa);
+
+    fn create(init_code: &[u8]) -> EvmTransactionRequest {
+        Eip1559TransactionRequest::new()
+            .data(init_code.to_vec())
+            .into()
+    }
+
+    fn create2(salt: H256, init_code: &[u8]) -> EvmTransactionRequest {
+        LegacyTransactionRequest::new()
+            .to(FACTORY)
+            .data([salt.as_bytes(), init_code].concat())
+            .into()
+    }
+
+    #[test]
+    fn can_decode_deployments() {
+        let init_code_hash = H256::from(keccak256(INIT_CODE));
+        assert_eq!(
+            Deployment::decode(&create(INIT_CODE), &[]).unwrap(),
+            Some(Deployment::Create { init_code_hash })
+        );
+
+        let deployment = Deployment::decode(&create2(H256::zero(), INIT_CODE), &[FACTORY])
+            .unwrap()
+            .unwrap();
+        assert_eq!(deployment.init_code_hash(), init_code_hash);
+        // example 0 from EIP-1014
+        let deployment = Deployment::Create2 {
+            factory: H160::zero(),
+            salt: H256::zero(),
+            init_code_hash: keccak256([0x00]).into(),
+        };
+        assert_eq!(
+            deployment.create2_address(),
+            Some(
+                "0x4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38"
+                    .parse()
+                    .unwrap()
+            )
+        );
+
+        // calls to other contracts, or factories that weren't given, aren't deployments
+        assert_eq!(
+            Deployment::decode(&create2(H256::zero(), INIT_CODE), &[]).unwrap(),
+            None
+        );
+        assert!(Deployment::decode(&create2(H256::zero(), &[]), &[FACTORY])
+            .unwrap()
+            .is_some());
+        let short_calldata: EvmTransactionRequest = LegacyTransactionRequest::new()
+            .to(FACTORY)
+            .data(vec![0u8; 31])
+            .into();
+        assert!(Deployment::decode(&short_calldata, &[FACTORY]).is_err());
+    }
+
+    #[test]
+    fn deployment_constraint_functions_properly() {
+        let init_code_hash = H256::from(keccak256(INIT_CODE));
+        let salt = H256::repeat_byte(1);
+        let constraint = DeploymentConstraint {
+            allowed_init_code_hashes: vec![init_code_hash],
+            factories: vec![Create2Factory {
+                address: FACTORY,
+                allowed_deployments: vec![Create2Deployment {
+                    salt: Some(salt),
+                    init_code_hash,
+                }],
+            }],
+            allow_other_transactions: false,
+        };
+        let evaluate = |tx: EvmTransactionRequest| {
+            SatisfiableForArchitecture::<Evm>::is_satisfied_by(constraint.clone(), &tx).is_ok()
+        };
+
+        assert!(evaluate(create(INIT_CODE)));
+        assert!(!evaluate(create(&INIT_CODE[1..])));
+        assert!(!evaluate(create(&[])));
+        assert!(evaluate(create2(salt, INIT_CODE)));
+        assert!(!evaluate(create2(H256::zero(), INIT_CODE)));
+        assert!(!evaluate(create2(salt, &INIT_CODE[1..])));
+        assert!(!evaluate(
+            LegacyTransactionRequest::new()
+                .to(H160::repeat_byte(1))
+                .into()
+        ));
+
+        // any salt, and other transactions
+        let permissive = DeploymentConstraint {
+            factories: vec![Create2Factory {
+                address: FACTORY,
+                allowed_deployments: vec![Create2Deployment {
+                    salt: None,
+                    init_code_hash,
+                }],
+            }],
+            allow_other_transactions: true,
+            ..Default::default()
+        };
+        let evaluate = |tx: EvmTransactionRequest| {
+            SatisfiableForArchitecture::<Evm>::is_satisfied_by(permissive.clone(), &tx).is_ok()
+        };
+        assert!(evaluate(create2(H256::zero(), INIT_CODE)));
+        assert!(!evaluate(create(INIT_CODE)));
+        assert!(evaluate(
+            LegacyTransactionRequest::new()
+                .to(H160::repeat_byte(1))
+                .into()
+        ));
+
+        // config is given as JSON
+        let json = format!(
+            r#"{{
+                "allowed_init_code_hashes": ["{:?}"],
+                "factories": [{{
+                    "address": "{:?}",
+                    "allowed_deployments": [{{ "salt": "{:?}", "init_code_hash": "{:?}" }}]
+                }}]
+            }}"#,
+            init_code_hash, FACTORY, salt, init_code_hash
+        );
+        assert_eq!(
+            serde_json::from_str::<DeploymentConstraint>(&json).unwrap(),
+            constraint
+        );
+    }
+}
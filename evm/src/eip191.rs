@@ -0,0 +1,195 @@
+//! EIP-191 `personal_sign` messages, and constraints on their content.
+//!
+//! Signature requests hold the message itself, without the prefix. Since what gets signed is the hash of the
+//! prefixed message, programs should return [`custom_hash`] from their own `custom_hash`.
+
+use alloc::{format, string::String, string::ToString, vec::Vec};
+
+use entropy_programs_core::{Error as CoreError, Satisfiable};
+use ethers_core::utils::keccak256;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The prefix of `personal_sign` messages (EIP-191 version `0x45`), followed by the message length in decimal.
+pub const PREFIX: &str = "\x19Ethereum Signed Message:\n";
+
+/// A `personal_sign` message.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eip191(pub Vec<u8>);
+
+impl Eip191 {
+    /// The message with the prefix and length applied, ie. what gets hashed.
+    pub fn prefixed(&self) -> Vec<u8> {
+        [
+            PREFIX.as_bytes(),
+            self.0.len().to_string().as_bytes(),
+            &self.0,
+        ]
+        .concat()
+    }
+
+    /// The hash that gets signed: `keccak256(prefix || len(message) || message)`.
+    pub fn hash(&self) -> [u8; 32] {
+        keccak256(self.prefixed())
+    }
+
+    /// The message as text, or `None` if it isn't UTF-8.
+    pub fn text(&self) -> Option<&str> {
+        core::str::from_utf8(&self.0).ok()
+    }
+}
+
+/// Hashes a signature request's message as a `personal_sign` message, for use as a program's `custom_hash`.
+pub fn custom_hash(message: &[u8]) -> Option<Vec<u8>> {
+    Some(Eip191(message.to_vec()).hash().to_vec())
+}
+
+/// Constrains the content of messages. Unset checks are skipped, and messages must be UTF-8 for the `prefix`
+/// and `pattern` checks.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageConstraint {
+    /// Text the message must start with, eg. `"example.com wants you to sign in"`.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Regular expression the message must match. Use `^` and `$` to match the whole message.
+    #[serde(default)]
+    pub pattern: Option<Pattern>,
+    /// Maximum length of the message, in bytes.
+    #[serde(default)]
+    pub max_length: Option<usize>,
+}
+
+/// A regular expression, compiled once when the config is parsed rather than on every evaluation.
+/// (De)serialized as the expression itself.
+#[derive(Clone, Debug)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, CoreError> {
+        Regex::new(pattern)
+            .map(Pattern)
+            .map_err(|e| CoreError::Evaluation(format!("Invalid message pattern: {}", e)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern)
+            .map(Pattern)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Satisfiable for MessageConstraint {
+    fn is_satisfied_by(self, message: &[u8]) -> Result<(), CoreError> {
+        if let Some(max_length) = self.max_length {
+            if message.len() > max_length {
+                return Err(CoreError::Evaluation(format!(
+                    "Message length {} exceeds the maximum of {}.",
+                    message.len(),
+                    max_length
+                )));
+            }
+        }
+        if self.prefix.is_none() && self.pattern.is_none() {
+            return Ok(());
+        }
+
+        let text = core::str::from_utf8(message)
+            .map_err(|e| CoreError::Evaluation(format!("Message is not valid UTF-8: {}", e)))?;
+        if let Some(prefix) = self.prefix {
+            if !text.starts_with(&prefix) {
+                return Err(CoreError::Evaluation(
+                    "Message does not start with the required prefix.".to_string(),
+                ));
+            }
+        }
+        if let Some(pattern) = self.pattern {
+            if !pattern.0.is_match(text) {
+                return Err(CoreError::Evaluation(
+                    "Message does not match the required pattern.".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_hash_personal_messages() {
+        let message = Eip191(b"Hello World".to_vec());
+        assert_eq!(
+            message.prefixed(),
+            b"\x19Ethereum Signed Message:\n11Hello World"
+        );
+        assert_eq!(
+            hex::encode(message.hash()),
+            "a1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+        );
+        assert_eq!(custom_hash(b"Hello World"), Some(message.hash().to_vec()));
+        assert_eq!(message.text(), Some("Hello World"));
+        assert_eq!(Eip191(vec![0xff]).text(), None);
+    }
+
+    #[test]
+    fn message_constraint_functions_properly() {
+        let constraint = MessageConstraint {
+            prefix: Some("example.com wants you to sign in".to_string()),
+            pattern: Some(Pattern::new(r"(?m)^Chain ID: 1$").unwrap()),
+            max_length: Some(100),
+        };
+        let evaluate = |message: &str| constraint.clone().is_satisfied_by(message.as_bytes());
+
+        assert!(evaluate("example.com wants you to sign in\nChain ID: 1").is_ok());
+        assert!(evaluate("evil.com wants you to sign in\nChain ID: 1").is_err());
+        assert!(evaluate("example.com wants you to sign in\nChain ID: 10").is_err());
+        assert!(evaluate(&format!(
+            "example.com wants you to sign in\nChain ID: 1\n{}",
+            "a".repeat(100)
+        ))
+        .is_err());
+        assert!(constraint.clone().is_satisfied_by(&[0xff]).is_err());
+
+        // only the length is checked for non-UTF-8 messages without text checks
+        let length_only = MessageConstraint {
+            max_length: Some(2),
+            ..Default::default()
+        };
+        assert!(length_only.clone().is_satisfied_by(&[0xff, 0xfe]).is_ok());
+        assert!(length_only.is_satisfied_by(&[0xff, 0xfe, 0xfd]).is_err());
+
+        // invalid patterns are rejected when the config is parsed
+        assert!(Pattern::new("(").is_err());
+        assert!(serde_json::from_str::<MessageConstraint>(r#"{ "pattern": "(" }"#).is_err());
+        assert_eq!(
+            serde_json::from_str::<MessageConstraint>(r#"{ "pattern": "^a+$" }"#)
+                .unwrap()
+                .pattern,
+            Some(Pattern::new("^a+$").unwrap())
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! The EIP-712 typed data architecture, for permits, orders, off-chain votes and other structured messages.
+//!
+//! Signature requests hold the JSON typed data, as passed to `eth_signTypedData_v4`. Since what gets signed is
+//! the EIP-712 hash rather than the message itself, programs using this architecture should return
+//! [`custom_hash`] from their own `custom_hash`.
+
+use alloc::{format, vec::Vec};
+
+use entropy_programs_core::{
+    Architecture, Error as CoreError, GetCalldata, GetChainId, GetFee, GetNonce, GetReceiver,
+    GetSender, GetValue, TryParse,
+};
+use ethers_core::types::transaction::eip712::Eip712 as _;
+pub use ethers_core::types::transaction::eip712::{EIP712Domain, Eip712DomainType, TypedData};
+use ethers_core::types::{H160, U256};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// EIP-712 typed data "architecture". The verifying contract of the domain is treated as the receiver.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Eip712;
+
+impl Architecture for Eip712 {
+    type Address = H160;
+    type AddressRaw = [u8; 20];
+    type Value = U256;
+    type ChainId = u64;
+    type Nonce = u64;
+    type Fee = U256;
+    type TransactionRequest = TypedData;
+}
+
+impl GetSender<Eip712> for TypedData {
+    /// The signer is implied by the signature, so there is no sender.
+    fn sender(&self) -> Option<H160> {
+        None
+    }
+}
+
+impl GetReceiver<Eip712> for TypedData {
+    /// The domain's verifying contract.
+    fn receiver(&self) -> Option<H160> {
+        self.domain.verifying_contract
+    }
+}
+
+impl GetValue<Eip712> for TypedData {
+    /// Typed data doesn't transfer the native asset. Amounts in the message are available through
+    /// [`message_uint`].
+    fn value(&self) -> Option<U256> {
+        None
+    }
+}
+
+impl GetChainId<Eip712> for TypedData {
+    /// The domain's chain id, or `None` if it is missing or doesn't fit in a `u64`.
+    fn chain_id(&self) -> Option<u64> {
+        self.domain.chain_id?.try_into().ok()
+    }
+}
+
+impl GetCalldata<Eip712> for TypedData {
+    fn calldata(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl GetNonce<Eip712> for TypedData {
+    /// Nonces are specific to each message type, so are available through [`message_uint`] instead.
+    fn nonce(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl GetFee<Eip712> for TypedData {
+    fn fee(&self) -> Option<U256> {
+        None
+    }
+}
+
+impl TryParse<Eip712> for TypedData {
+    /// Parses the JSON typed data, checking that it can be hashed against its types.
+    fn try_parse(raw_typed_data: &[u8]) -> Result<Self, CoreError> {
+        let typed_data: TypedData = serde_json::from_slice(raw_typed_data).map_err(|e| {
+            CoreError::InvalidSignatureRequest(format!("Unable to parse typed data: {}", e))
+        })?;
+        hash(&typed_data)?;
+        Ok(typed_data)
+    }
+}
+
+/// The domain separator, ie. the hash of the domain.
+pub fn domain_separator(typed_data: &TypedData) -> [u8; 32] {
+    typed_data.domain.separator()
+}
+
+/// The hash of the message, against its primary type.
+pub fn struct_hash(typed_data: &TypedData) -> Result<[u8; 32], CoreError> {
+    typed_data.struct_hash().map_err(|e| {
+        CoreError::InvalidSignatureRequest(format!("Unable to hash typed data: {}", e))
+    })
+}
+
+/// The hash that gets signed: `keccak256(0x1901 || domain separator || struct hash)`.
+pub fn hash(typed_data: &TypedData) -> Result<[u8; 32], CoreError> {
+    typed_data.encode_eip712().map_err(|e| {
+        CoreError::InvalidSignatureRequest(format!("Unable to hash typed data: {}", e))
+    })
+}
+
+/// Hashes the JSON typed data in a signature request's message, for use as a program's `custom_hash`.
+pub fn custom_hash(message: &[u8]) -> Option<Vec<u8>> {
+    let typed_data = TypedData::try_parse(message).ok()?;
+    hash(&typed_data).ok().map(|hash| hash.to_vec())
+}
+
+/// Gets a field of the message, using `.` to reach into nested structs (eg. `"order.maker"`). Only fields
+/// declared in the message's types are read, since other fields aren't part of what gets signed.
+pub fn message_field<'a>(typed_data: &'a TypedData, path: &str) -> Option<&'a JsonValue> {
+    let mut struct_type = &typed_data.primary_type;
+    let mut value: Option<&JsonValue> = None;
+    for field in path.split('.') {
+        let declared = typed_data
+            .types
+            .get(struct_type)?
+            .iter()
+            .find(|declared| declared.name == field)?;
+        value = Some(match value {
+            None => typed_data.message.get(field)?,
+            Some(value) => value.get(field)?,
+        });
+        struct_type = &declared.r#type;
+    }
+    value
+}
+
+/// Gets an `address` field of the message, see [`message_field`].
+pub fn message_address(typed_data: &TypedData, path: &str) -> Option<H160> {
+    message_field(typed_data, path)?.as_str()?.parse().ok()
+}
+
+/// Gets a `uint` field of the message, given either as a JSON number or a decimal or `0x`-prefixed hex
+/// string, see [`message_field`]. Errors if the field isn't a valid `uint` (eg. JSON numbers too large to be
+/// represented exactly), rather than treating it as missing.
+pub fn message_uint(typed_data: &TypedData, path: &str) -> Result<Option<U256>, CoreError> {
+    let Some(value) = message_field(typed_data, path) else {
+        return Ok(None);
+    };
+    let uint = match value {
+        JsonValue::Number(number) => number.as_u64().map(U256::from),
+        JsonValue::String(string) => match string.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).ok(),
+            None => U256::from_dec_str(string).ok(),
+        },
+        _ => None,
+    };
+    uint.map(Some).ok_or(CoreError::Evaluation(format!(
+        "Typed data field `{}` is not a valid uint.",
+        path
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example from EIP-712
+    const MAIL: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" }
+            ],
+            "Person": [
+                { "name": "name", "type": "string" },
+                { "name": "wallet", "type": "address" }
+            ],
+            "Mail": [
+                { "name": "from", "type": "Person" },
+                { "name": "to", "type": "Person" },
+                { "name": "contents", "type": "string" }
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+            "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+            "contents": "Hello, Bob!"
+        }
+    }"#;
+
+    #[test]
+    fn can_hash_typed_data() {
+        let typed_data = TypedData::try_parse(MAIL.as_bytes()).unwrap();
+        assert_eq!(
+            hex::encode(domain_separator(&typed_data)),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+        assert_eq!(
+            hex::encode(struct_hash(&typed_data).unwrap()),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+        assert_eq!(
+            hex::encode(custom_hash(MAIL.as_bytes()).unwrap()),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+    }
+
+    #[test]
+    fn can_get_domain_and_message_fields() {
+        let typed_data = TypedData::try_parse(MAIL.as_bytes()).unwrap();
+        assert_eq!(GetChainId::<Eip712>::chain_id(&typed_data), Some(1));
+        assert_eq!(
+            GetReceiver::<Eip712>::receiver(&typed_data),
+            Some(H160::repeat_byte(0xcc))
+        );
+        assert_eq!(GetSender::<Eip712>::sender(&typed_data), None);
+        assert_eq!(
+            message_address(&typed_data, "to.wallet"),
+            Some(H160::repeat_byte(0xbb))
+        );
+        assert_eq!(
+            message_field(&typed_data, "contents"),
+            Some(&JsonValue::String("Hello, Bob!".to_string()))
+        );
+        assert_eq!(message_field(&typed_data, "to.email"), None);
+
+        let mut permit = typed_data.clone();
+        let mut insert = |name: &str, value: JsonValue| {
+            permit
+                .types
+                .get_mut("Mail")
+                .unwrap()
+                .push(Eip712DomainType {
+                    name: name.to_string(),
+                    r#type: "uint256".to_string(),
+                });
+            permit.message.insert(name.to_string(), value);
+        };
+        insert("value", "1000".into());
+        insert("deadline", "0xff".into());
+        insert("nonce", 7.into());
+        insert("amount", 1e20.into());
+        assert_eq!(message_uint(&permit, "value").unwrap(), Some(1000.into()));
+        assert_eq!(message_uint(&permit, "deadline").unwrap(), Some(255.into()));
+        assert_eq!(message_uint(&permit, "nonce").unwrap(), Some(7.into()));
+        assert_eq!(message_uint(&permit, "expiry").unwrap(), None);
+        // values that aren't uints, or can't be represented exactly
+        assert!(message_uint(&permit, "contents").is_err());
+        assert!(message_uint(&permit, "amount").is_err());
+
+        // fields that aren't declared in the types aren't signed, so aren't read
+        let mut undeclared = typed_data.clone();
+        undeclared.message.insert("value".to_string(), 1.into());
+        let to = undeclared.message.get_mut("to").unwrap();
+        to["email"] = "bob@example.com".into();
+        assert_eq!(message_uint(&undeclared, "value").unwrap(), None);
+        assert_eq!(message_field(&undeclared, "to.email"), None);
+        assert_eq!(message_field(&undeclared, "contents.name"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_typed_data() {
+        assert!(TypedData::try_parse(b"not json").is_err());
+        // the message doesn't match its type
+        let invalid = MAIL.replace(r#""contents": "Hello, Bob!""#, r#""contents": 1"#);
+        assert!(TypedData::try_parse(invalid.as_bytes()).is_err());
+        assert_eq!(custom_hash(invalid.as_bytes()), None);
+    }
+}
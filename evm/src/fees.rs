@@ -0,0 +1,231 @@
+//! Ceilings on the gas and fees of EVM transactions.
+
+use alloc::{format, string::String, string::ToString};
+use core::{fmt, str::FromStr};
+
+use entropy_programs_core::{Error as CoreError, SatisfiableForArchitecture};
+use ethers_core::types::U256;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Evm, EvmTransactionRequest};
+
+/// Wei in a gwei.
+const GWEI: u64 = 1_000_000_000;
+
+/// An amount of wei, written as a decimal string of gwei with up to 9 decimal places, eg. `"1.5"`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Gwei(pub U256);
+
+impl Gwei {
+    /// The amount in wei.
+    pub fn wei(&self) -> U256 {
+        self.0
+    }
+}
+
+impl FromStr for Gwei {
+    type Err = CoreError;
+
+    fn from_str(gwei: &str) -> Result<Self, Self::Err> {
+        let invalid = || CoreError::Evaluation(format!("Invalid gwei amount `{}`.", gwei));
+        let (integer, decimals) = match gwei.split_once('.') {
+            Some((_, "")) => return Err(invalid()),
+            Some(parts) => parts,
+            None => (gwei, ""),
+        };
+        if integer.is_empty()
+            || decimals.len() > 9
+            || !integer
+                .chars()
+                .chain(decimals.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let integer = U256::from_dec_str(integer).map_err(|_| invalid())?;
+        let decimals = match decimals.is_empty() {
+            true => U256::zero(),
+            // right-pad to nine digits, eg. "5" is 0.5 gwei = 500_000_000 wei
+            false => U256::from_dec_str(&format!("{:0<9}", decimals)).map_err(|_| invalid())?,
+        };
+        integer
+            .checked_mul(GWEI.into())
+            .and_then(|wei| wei.checked_add(decimals))
+            .map(Gwei)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Gwei {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (integer, decimals) = self.0.div_mod(GWEI.into());
+        match decimals.is_zero() {
+            true => write!(f, "{}", integer),
+            false => {
+                let decimals = format!("{:0>9}", decimals.as_u64());
+                write!(f, "{}.{}", integer, decimals.trim_end_matches('0'))
+            }
+        }
+    }
+}
+
+impl Serialize for Gwei {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Gwei {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let gwei = String::deserialize(deserializer)?;
+        gwei.parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid gwei amount `{}`", gwei)))
+    }
+}
+
+/// Caps the gas and fees of legacy and typed transactions. Unset caps are not checked, and transactions missing
+/// a field that has a cap are rejected.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeConstraint {
+    /// Maximum gas limit.
+    #[serde(default)]
+    pub max_gas_limit: Option<u64>,
+    /// Maximum price per unit of gas: the gas price of legacy and EIP-2930 transactions, and the max fee per gas
+    /// of EIP-1559 and EIP-4844 transactions.
+    #[serde(default)]
+    pub max_gas_price: Option<Gwei>,
+    /// Maximum priority fee per unit of gas. Only EIP-1559 and EIP-4844 transactions have a priority fee.
+    #[serde(default)]
+    pub max_priority_fee_per_gas: Option<Gwei>,
+    /// Maximum total fee, ie. gas limit × gas price, plus the blob fee (blob gas × max fee per blob gas) of
+    /// EIP-4844 transactions, see [`EvmTransactionRequest::max_fee`].
+    #[serde(default)]
+    pub max_total_fee: Option<Gwei>,
+}
+
+/// Checks an optional transaction field against an optional cap.
+fn check_cap(name: &str, value: Option<U256>, cap: Option<U256>) -> Result<(), CoreError> {
+    let Some(cap) = cap else {
+        return Ok(());
+    };
+    let value = value.ok_or(CoreError::Evaluation(format!(
+        "Transaction has no {} to check against the cap.",
+        name
+    )))?;
+    match value > cap {
+        true => Err(CoreError::Evaluation(format!(
+            "Transaction {} {} exceeds the cap of {}.",
+            name, value, cap
+        ))),
+        false => Ok(()),
+    }
+}
+
+impl SatisfiableForArchitecture<Evm> for FeeConstraint {
+    fn is_satisfied_by(self, tx: &EvmTransactionRequest) -> Result<(), CoreError> {
+        check_cap("gas limit", tx.gas(), self.max_gas_limit.map(U256::from))?;
+        check_cap(
+            "gas price",
+            tx.max_fee_per_gas(),
+            self.max_gas_price.map(|cap| cap.wei()),
+        )?;
+        if matches!(
+            tx,
+            EvmTransactionRequest::Eip1559(_) | EvmTransactionRequest::Eip4844(_)
+        ) {
+            check_cap(
+                "priority fee",
+                tx.max_priority_fee_per_gas(),
+                self.max_priority_fee_per_gas.map(|cap| cap.wei()),
+            )?;
+        }
+        check_cap(
+            "total fee",
+            tx.max_fee(),
+            self.max_total_fee.map(|cap| cap.wei()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Eip1559TransactionRequest, LegacyTransactionRequest};
+
+    fn gwei(amount: &str) -> Gwei {
+        amount.parse().unwrap()
+    }
+
+    #[test]
+    fn can_parse_gwei_amounts() {
+        assert_eq!(gwei("30").wei(), U256::from(30 * GWEI));
+        assert_eq!(gwei("1.5").wei(), U256::from(1_500_000_000));
+        assert_eq!(gwei("0.000000001").wei(), U256::one());
+        assert_eq!(gwei("1.5").to_string(), "1.5");
+        assert_eq!(gwei("0.000000001").to_string(), "0.000000001");
+        assert_eq!(gwei("30.0").to_string(), "30");
+
+        for invalid in ["", ".5", "1.", "-1", "1.0000000001", "1e9", "0x10"] {
+            assert!(invalid.parse::<Gwei>().is_err(), "{}", invalid);
+        }
+        assert!(format!("{}0", U256::MAX).parse::<Gwei>().is_err());
+    }
+
+    #[test]
+    fn fee_constraint_functions_properly() {
+        let constraint: FeeConstraint = serde_json::from_str(
+            r#"{
+                "max_gas_limit": 100000,
+                "max_gas_price": "50",
+                "max_priority_fee_per_gas": "2.5",
+                "max_total_fee": "2100000"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(constraint.max_gas_price, Some(gwei("50")));
+        let evaluate = |tx: EvmTransactionRequest| {
+            SatisfiableForArchitecture::<Evm>::is_satisfied_by(constraint.clone(), &tx).is_ok()
+        };
+        let legacy = |gas: u64, gas_price: Gwei| -> EvmTransactionRequest {
+            LegacyTransactionRequest::new()
+                .gas(gas)
+                .gas_price(gas_price.wei())
+                .into()
+        };
+        let eip1559 = |gas: u64, max_fee: Gwei, priority_fee: Gwei| -> EvmTransactionRequest {
+            Eip1559TransactionRequest::new()
+                .gas(gas)
+                .max_fee_per_gas(max_fee.wei())
+                .max_priority_fee_per_gas(priority_fee.wei())
+                .into()
+        };
+
+        assert!(evaluate(legacy(21_000, gwei("50"))));
+        assert!(!evaluate(legacy(21_000, gwei("50.000000001"))));
+        assert!(!evaluate(legacy(100_001, gwei("1"))));
+        // 50000 gas at 50 gwei is over the total fee cap of 2100000 gwei
+        assert!(!evaluate(legacy(50_000, gwei("50"))));
+
+        assert!(evaluate(eip1559(21_000, gwei("50"), gwei("2.5"))));
+        assert!(!evaluate(eip1559(21_000, gwei("50"), gwei("2.6"))));
+        assert!(!evaluate(eip1559(21_000, gwei("51"), gwei("1"))));
+
+        // missing fields can't be checked
+        assert!(!evaluate(
+            LegacyTransactionRequest::new().gas(21_000).into()
+        ));
+        assert!(!evaluate(
+            Eip1559TransactionRequest::new()
+                .gas(21_000)
+                .max_fee_per_gas(1)
+                .into()
+        ));
+
+        assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+            FeeConstraint::default(),
+            &LegacyTransactionRequest::new().into()
+        )
+        .is_ok());
+    }
+}
@@ -2,16 +2,35 @@
 
 extern crate alloc;
 
-use alloc::string::String;
+use alloc::{string::String, vec, vec::Vec};
 
 use entropy_programs_core::{
-    Architecture, Error as CoreError, GetReceiver, GetSender, Parse, TryParse,
+    Architecture, Error as CoreError, GetCalldata, GetChainId, GetFee, GetNonce, GetReceiver,
+    GetSender, GetValue, TryParse,
 };
-pub use ethers_core::types::transaction::request::TransactionRequest as EvmTransactionRequest;
-pub use ethers_core::types::{NameOrAddress, H160};
-use rlp::Rlp;
+pub use ethers_core::types::{NameOrAddress, H160, U256};
 use serde::{Deserialize, Serialize};
 
+#[allow(deprecated)]
+use entropy_programs_core::Parse;
+
+pub mod abi;
+pub mod address;
+pub mod chain;
+pub mod deployment;
+#[cfg(feature = "eip191")]
+pub mod eip191;
+pub mod eip712;
+pub mod fees;
+pub mod multicall;
+pub mod safe;
+pub mod selector;
+pub mod token;
+mod transaction;
+pub mod user_operation;
+pub use eip712::Eip712;
+pub use transaction::*;
+
 /// EVM Architecture
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Evm;
@@ -19,79 +38,193 @@ pub struct Evm;
 impl Architecture for Evm {
     type Address = H160;
     type AddressRaw = [u8; 20];
+    type Value = U256;
+    type ChainId = u64;
+    type Nonce = u64;
+    type Fee = U256;
     type TransactionRequest = EvmTransactionRequest;
 }
 
 impl GetSender<Evm> for <Evm as Architecture>::TransactionRequest {
+    /// Unsigned transactions don't include the sender, so this is only set on requests built by hand.
     fn sender(&self) -> Option<<Evm as Architecture>::Address> {
-        self.from
+        match self {
+            Self::Legacy(tx) => tx.from,
+            Self::Eip2930(tx) => tx.tx.from,
+            Self::Eip1559(tx) => tx.from,
+            Self::Eip4844(_) => None,
+        }
     }
 }
 
 impl GetReceiver<Evm> for <Evm as Architecture>::TransactionRequest {
     fn receiver(&self) -> Option<<Evm as Architecture>::Address> {
-        match &self.to {
-            Some(to) => match to {
-                NameOrAddress::Address(addr) => Some(addr.to_owned()),
-                // This should never get returned because we Error on ENS names in the `parse`
-                // function
-                NameOrAddress::Name(_) => None,
-            },
+        let to = match self {
+            Self::Legacy(tx) => tx.to.as_ref(),
+            Self::Eip2930(tx) => tx.tx.to.as_ref(),
+            Self::Eip1559(tx) => tx.to.as_ref(),
+            Self::Eip4844(tx) => return Some(tx.to),
+        };
+        match to {
+            Some(NameOrAddress::Address(addr)) => Some(addr.to_owned()),
+            // This should never get returned because we Error on ENS names in the `parse`
+            // function
+            Some(NameOrAddress::Name(_)) => None,
             None => None,
         }
     }
+
+    /// ENS names can't be checked against addresses, so transactions to them have no known receivers.
+    fn receivers(&self) -> Option<Vec<<Evm as Architecture>::Address>> {
+        let to = match self {
+            Self::Legacy(tx) => tx.to.as_ref(),
+            Self::Eip2930(tx) => tx.tx.to.as_ref(),
+            Self::Eip1559(tx) => tx.to.as_ref(),
+            Self::Eip4844(tx) => return Some(vec![tx.to]),
+        };
+        match to {
+            Some(NameOrAddress::Name(_)) => None,
+            _ => Some(self.receiver().into_iter().collect()),
+        }
+    }
+}
+
+impl GetValue<Evm> for <Evm as Architecture>::TransactionRequest {
+    fn value(&self) -> Option<<Evm as Architecture>::Value> {
+        match self {
+            Self::Legacy(tx) => tx.value,
+            Self::Eip2930(tx) => tx.tx.value,
+            Self::Eip1559(tx) => tx.value,
+            Self::Eip4844(tx) => Some(tx.value),
+        }
+    }
+}
+
+impl GetChainId<Evm> for <Evm as Architecture>::TransactionRequest {
+    /// Legacy transactions without EIP-155 replay protection have no chain id.
+    fn chain_id(&self) -> Option<<Evm as Architecture>::ChainId> {
+        let chain_id = match self {
+            Self::Legacy(tx) => tx.chain_id,
+            Self::Eip2930(tx) => tx.tx.chain_id,
+            Self::Eip1559(tx) => tx.chain_id,
+            Self::Eip4844(tx) => Some(tx.chain_id),
+        };
+        chain_id.map(|id| id.as_u64())
+    }
+}
+
+impl GetCalldata<Evm> for <Evm as Architecture>::TransactionRequest {
+    fn calldata(&self) -> Option<&[u8]> {
+        let data = match self {
+            Self::Legacy(tx) => tx.data.as_ref(),
+            Self::Eip2930(tx) => tx.tx.data.as_ref(),
+            Self::Eip1559(tx) => tx.data.as_ref(),
+            Self::Eip4844(tx) => Some(&tx.data),
+        };
+        data.map(|data| data.as_ref())
+    }
+}
+
+impl GetNonce<Evm> for <Evm as Architecture>::TransactionRequest {
+    /// Nonces are capped at `2^64 - 1` by EIP-2681, so larger values are treated as missing.
+    fn nonce(&self) -> Option<<Evm as Architecture>::Nonce> {
+        let nonce = match self {
+            Self::Legacy(tx) => tx.nonce,
+            Self::Eip2930(tx) => tx.tx.nonce,
+            Self::Eip1559(tx) => tx.nonce,
+            Self::Eip4844(tx) => Some(tx.nonce),
+        };
+        nonce.and_then(|nonce| nonce.try_into().ok())
+    }
+}
+
+impl GetFee<Evm> for <Evm as Architecture>::TransactionRequest {
+    /// The maximum fee the transaction can pay, see [`EvmTransactionRequest::max_fee`].
+    fn fee(&self) -> Option<<Evm as Architecture>::Fee> {
+        self.max_fee()
+    }
+
+    /// See [`EvmTransactionRequest::max_fee_per_gas`].
+    fn gas_price(&self) -> Option<<Evm as Architecture>::Fee> {
+        self.max_fee_per_gas()
+    }
+}
+
+/// Encoding of the unsigned transaction in a signature request's message.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageFormat {
+    /// Detects the encoding from the first byte, see [`MessageFormat::detect`].
+    #[default]
+    Auto,
+    /// Raw RLP bytes, with the type byte for typed transactions
+    Rlp,
+    /// UTF-8 hex text of the raw RLP bytes, optionally `0x`-prefixed
+    Hex,
+}
+
+impl MessageFormat {
+    /// Detects whether a message is raw RLP or hex text. This is unambiguous because raw transactions start with
+    /// either an RLP list prefix (`0xc0` or above) or a type byte below `0x80`, none of which are hex digits.
+    pub fn detect(message: &[u8]) -> Self {
+        match message.first() {
+            Some(byte) if byte.is_ascii_hexdigit() => MessageFormat::Hex,
+            _ => MessageFormat::Rlp,
+        }
+    }
 }
 
+impl EvmTransactionRequest {
+    /// Parses a signature request's message in the given format, see [`TryParse`] for the default.
+    pub fn try_parse_as(message: &[u8], format: MessageFormat) -> Result<Self, CoreError> {
+        match format {
+            MessageFormat::Auto => Self::try_parse_as(message, MessageFormat::detect(message)),
+            MessageFormat::Rlp => decode_transaction(message),
+            MessageFormat::Hex => {
+                let message = message.strip_prefix(b"0x").unwrap_or(message);
+                let bytes = hex::decode(message).map_err(|e| {
+                    CoreError::InvalidSignatureRequest(format!("Unable to parse to RLP: {}", e))
+                })?;
+                decode_transaction(&bytes)
+            }
+        }
+    }
+}
+
+#[allow(deprecated)]
 impl Parse<Evm> for <Evm as Architecture>::TransactionRequest {
     fn parse(
         hex_rlp_raw_tx: String,
     ) -> Result<<Evm as Architecture>::TransactionRequest, CoreError> {
-        let bytes = hex::decode(hex_rlp_raw_tx.replace("0x", "")).map_err(|e| {
-            CoreError::InvalidSignatureRequest(format!("Unable to parse to RLP: {}", e))
-        })?;
-        let rlp = Rlp::new(&bytes);
-        match Self::decode_unsigned_rlp(&rlp) {
-            Ok(tx) => match tx.to {
-                // Clients shouldn't even be able to serialize tx reqs with ENS names, but it it
-                // does somehow, err
-                Some(NameOrAddress::Name(_)) => Err(CoreError::InvalidSignatureRequest(
-                    "ENS recipients not supported. Resolve to an address first.".to_string(),
-                )),
-                _ => Ok(tx),
-            },
-            Err(e) => Err(CoreError::InvalidSignatureRequest(format!(
-                "Unable to decode string: {}",
-                e
-            ))),
-        }
+        Self::try_parse_as(hex_rlp_raw_tx.as_bytes(), MessageFormat::Hex)
     }
 }
 
 impl TryParse<Evm> for <Evm as Architecture>::TransactionRequest {
-    /// TODO expect the hex-encoded RLP of the transaction request, so user doesn't have to hex::decode
+    /// Accepts either the raw RLP of the unsigned transaction or its hex encoding.
     fn try_parse(bytes: &[u8]) -> Result<Self, CoreError> {
-        let request_as_string = String::from_utf8(bytes.to_owned()).map_err(|e| {
-            CoreError::InvalidSignatureRequest(format!("Unable to parse to String: {}", e))
-        })?;
-        let into_bytes = hex::decode(request_as_string.replace("0x", "")).map_err(|e| {
-            CoreError::InvalidSignatureRequest(format!("Unable to parse to RLP: {}", e))
-        })?;
-        let rlp = Rlp::new(&into_bytes);
-
-        match Self::decode_unsigned_rlp(&rlp) {
-            Ok(tx) => match tx.to {
-                // Clients shouldn't even be able to serialize tx reqs with ENS names, but it it
-                // does somehow, err
-                Some(NameOrAddress::Name(_)) => Err(CoreError::InvalidSignatureRequest(
-                    "ENS recipients not supported. Resolve to an address first.".to_string(),
-                )),
-                _ => Ok(tx),
-            },
-            Err(e) => Err(CoreError::InvalidSignatureRequest(format!(
-                "Unable to decode string: {}",
-                e
-            ))),
-        }
+        Self::try_parse_as(bytes, MessageFormat::Auto)
+    }
+}
+
+/// Decodes an unsigned legacy or typed transaction, rejecting ENS recipients.
+fn decode_transaction(bytes: &[u8]) -> Result<EvmTransactionRequest, CoreError> {
+    let tx = EvmTransactionRequest::decode_unsigned(bytes).map_err(|e| {
+        CoreError::InvalidSignatureRequest(format!("Unable to decode string: {}", e))
+    })?;
+    let to = match &tx {
+        EvmTransactionRequest::Legacy(tx) => tx.to.as_ref(),
+        EvmTransactionRequest::Eip2930(tx) => tx.tx.to.as_ref(),
+        EvmTransactionRequest::Eip1559(tx) => tx.to.as_ref(),
+        EvmTransactionRequest::Eip4844(_) => None,
+    };
+    match to {
+        // Clients shouldn't even be able to serialize tx reqs with ENS names, but it it
+        // does somehow, err
+        Some(NameOrAddress::Name(_)) => Err(CoreError::InvalidSignatureRequest(
+            "ENS recipients not supported. Resolve to an address first.".to_string(),
+        )),
+        _ => Ok(tx),
     }
 }
 
@@ -103,11 +236,24 @@ mod tests {
 
     use super::*;
 
+    fn recipient() -> EvmAddress {
+        EvmAddress::from_str("772b9a9e8aa1c9db861c6611a82d251db4fac990").unwrap()
+    }
+
+    /// Typed transactions sign over the raw unsigned transaction, type byte included
+    fn assert_sighash_matches(raw_unsigned_tx: &str, unsigned_tx: &EvmTransactionRequest) {
+        let raw_bytes = hex::decode(raw_unsigned_tx.replace("0x", "")).unwrap();
+        assert_eq!(
+            unsigned_tx.sighash(),
+            H256::from(ethers_core::utils::keccak256(raw_bytes))
+        );
+    }
+
     #[test]
     fn can_parse_evm_rlp_transactions() {
         // This is `serializedUnsignedTx` from entropy-js threshold-server tests
         let raw_unsigned_tx = "0xef01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079018080".to_string();
-        let unsigned_tx = EvmTransactionRequest::parse(raw_unsigned_tx).unwrap();
+        let unsigned_tx = EvmTransactionRequest::try_parse(raw_unsigned_tx.as_bytes()).unwrap();
         assert_eq!(unsigned_tx.sender(), None);
         assert_eq!(
             unsigned_tx.receiver(),
@@ -115,6 +261,34 @@ mod tests {
         ); // manually removed the 0x
     }
 
+    #[test]
+    fn can_get_evm_transaction_fields() {
+        // nonce 1, gas price 0, gas limit 0, value 1, chain id 1, data "Created On Entropy"
+        let raw_unsigned_tx = "0xef01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079018080".to_string();
+        let unsigned_tx = EvmTransactionRequest::try_parse(raw_unsigned_tx.as_bytes()).unwrap();
+        assert_eq!(GetValue::<Evm>::value(&unsigned_tx), Some(U256::one()));
+        assert_eq!(GetChainId::<Evm>::chain_id(&unsigned_tx), Some(1));
+        assert_eq!(GetNonce::<Evm>::nonce(&unsigned_tx), Some(1));
+        assert_eq!(unsigned_tx.fee(), Some(U256::zero()));
+        assert_eq!(
+            unsigned_tx.calldata(),
+            Some(b"Created On Entropy".as_slice())
+        );
+
+        let priced_tx: EvmTransactionRequest = LegacyTransactionRequest::new()
+            .gas(21_000)
+            .gas_price(2)
+            .into();
+        assert_eq!(priced_tx.fee(), Some(U256::from(42_000)));
+        let unpriced_tx: EvmTransactionRequest = LegacyTransactionRequest::new().gas(21_000).into();
+        assert_eq!(unpriced_tx.fee(), None);
+        let overflowing_tx: EvmTransactionRequest = LegacyTransactionRequest::new()
+            .gas(U256::MAX)
+            .gas_price(2)
+            .into();
+        assert_eq!(overflowing_tx.fee(), None);
+    }
+
     /// Tests that the parsed transaction's sighash matches the client's sighash
     #[test]
     fn evm_parsed_sighash_matches_clients_sighash() {
@@ -129,17 +303,162 @@ mod tests {
             .as_slice(),
         );
 
-        let unsigned_tx = EvmTransactionRequest::parse(raw_unsigned_tx).unwrap();
+        let unsigned_tx = EvmTransactionRequest::try_parse(raw_unsigned_tx.as_bytes()).unwrap();
         assert_eq!(unsigned_tx.sighash(), known_expected_sighash);
     }
 
+    #[test]
+    fn can_parse_eip2930_transactions() {
+        // chain id 1, nonce 2, gas price 1 gwei, gas limit 21000, value 1, data "Created On Entropy", with
+        // one access list entry
+        let raw_unsigned_tx = "0x01f86d0102843b9aca0082520894772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079f838f794772b9a9e8aa1c9db861c6611a82d251db4fac990e1a00000000000000000000000000000000000000000000000000000000000000001";
+        let unsigned_tx = EvmTransactionRequest::try_parse(raw_unsigned_tx.as_bytes()).unwrap();
+        assert_eq!(unsigned_tx.transaction_type(), 0x01);
+        assert_eq!(unsigned_tx.sender(), None);
+        assert_eq!(unsigned_tx.receiver(), Some(recipient()));
+        assert_eq!(GetNonce::<Evm>::nonce(&unsigned_tx), Some(2));
+        assert_eq!(unsigned_tx.fee(), Some(U256::from(21_000_000_000_000u64)));
+        assert_eq!(unsigned_tx.access_list().unwrap().0.len(), 1);
+        assert_sighash_matches(raw_unsigned_tx, &unsigned_tx);
+    }
+
+    #[test]
+    fn can_parse_eip1559_transactions() {
+        // chain id 1, nonce 3, max priority fee 1 gwei, max fee 2 gwei, gas limit 21000, value 1, data
+        // "Created On Entropy"
+        let raw_unsigned_tx = "0x02f8390103843b9aca00847735940082520894772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079c0";
+        let unsigned_tx = EvmTransactionRequest::try_parse(raw_unsigned_tx.as_bytes()).unwrap();
+        assert_eq!(unsigned_tx.transaction_type(), 0x02);
+        assert_eq!(unsigned_tx.sender(), None);
+        assert_eq!(unsigned_tx.receiver(), Some(recipient()));
+        assert_eq!(GetChainId::<Evm>::chain_id(&unsigned_tx), Some(1));
+        assert_eq!(GetNonce::<Evm>::nonce(&unsigned_tx), Some(3));
+        assert_eq!(
+            unsigned_tx.max_priority_fee_per_gas(),
+            Some(U256::from(1_000_000_000))
+        );
+        assert_eq!(unsigned_tx.fee(), Some(U256::from(42_000_000_000_000u64)));
+        assert_eq!(
+            unsigned_tx.calldata(),
+            Some(b"Created On Entropy".as_slice())
+        );
+        assert_sighash_matches(raw_unsigned_tx, &unsigned_tx);
+
+        // same, but a contract creation with nonce 4, value 0 and gas limit 100000
+        let raw_unsigned_tx =
+            "0x02e60104843b9aca008477359400830186a080809243726561746564204f6e20456e74726f7079c0";
+        let unsigned_tx = EvmTransactionRequest::try_parse(raw_unsigned_tx.as_bytes()).unwrap();
+        assert_eq!(unsigned_tx.receiver(), None);
+        assert_eq!(GetValue::<Evm>::value(&unsigned_tx), Some(U256::zero()));
+        assert_sighash_matches(raw_unsigned_tx, &unsigned_tx);
+    }
+
+    #[test]
+    fn can_parse_eip4844_transactions() {
+        // chain id 1, nonce 1, max priority fee 1 gwei, max fee 2 gwei, gas limit 21000, value 1, no data,
+        // max blob fee 1 and a single blob
+        let raw_unsigned_tx = "0x03f84a0101843b9aca00847735940082520894772b9a9e8aa1c9db861c6611a82d251db4fac9900180c001e1a0010000000000000000000000000000000000000000000000000000000000002a";
+        let unsigned_tx = EvmTransactionRequest::try_parse(raw_unsigned_tx.as_bytes()).unwrap();
+        assert_eq!(unsigned_tx.transaction_type(), 0x03);
+        assert_eq!(unsigned_tx.sender(), None);
+        assert_eq!(unsigned_tx.receiver(), Some(recipient()));
+        assert_eq!(GetValue::<Evm>::value(&unsigned_tx), Some(U256::one()));
+        assert_eq!(unsigned_tx.calldata(), Some([].as_slice()));
+        assert_eq!(
+            unsigned_tx.fee(),
+            Some(U256::from(42_000_000_000_000u64 + GAS_PER_BLOB))
+        );
+        assert_sighash_matches(raw_unsigned_tx, &unsigned_tx);
+    }
+
+    #[test]
+    fn rejects_unsupported_or_signed_typed_transactions() {
+        // unknown transaction type
+        assert!(EvmTransactionRequest::try_parse(b"0x04c0").is_err());
+        // EIP-1559 transaction with a trailing signature field
+        let signed_tx = "0x02f83a0103843b9aca00847735940082520894772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079c001";
+        assert!(EvmTransactionRequest::try_parse(signed_tx.as_bytes()).is_err());
+        // EIP-1559 transaction with trailing bytes after the list
+        let trailing_tx = "0x02f8390103843b9aca00847735940082520894772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079c000";
+        assert!(EvmTransactionRequest::try_parse(trailing_tx.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_signed_or_malformed_legacy_transactions() {
+        let unsigned_tx = "ef01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079018080";
+        assert!(EvmTransactionRequest::try_parse(unsigned_tx.as_bytes()).is_ok());
+        // pre-EIP-155 transactions have no chain id
+        let pre_eip155_tx = "ec01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079";
+        assert!(EvmTransactionRequest::try_parse(pre_eip155_tx.as_bytes()).is_ok());
+
+        let invalid = [
+            // trailing bytes after the list
+            format!("{}00", unsigned_tx),
+            // a trailing field
+            "f001808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f707901808080".to_string(),
+            // a signature in place of EIP-155's empty r and s
+            "ef01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079250102".to_string(),
+            // missing fields
+            "ed01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f707901".to_string(),
+        ];
+        for tx in invalid {
+            assert!(
+                EvmTransactionRequest::try_parse(tx.as_bytes()).is_err(),
+                "{}",
+                tx
+            );
+        }
+    }
+
+    #[test]
+    fn can_parse_raw_and_hex_messages() {
+        let hex_tx = "ef01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079018080";
+        let raw_tx = hex::decode(hex_tx).unwrap();
+        let expected = EvmTransactionRequest::try_parse_as(&raw_tx, MessageFormat::Rlp).unwrap();
+        assert_eq!(MessageFormat::detect(&raw_tx), MessageFormat::Rlp);
+        assert_eq!(MessageFormat::detect(hex_tx.as_bytes()), MessageFormat::Hex);
+
+        assert_eq!(EvmTransactionRequest::try_parse(&raw_tx).unwrap(), expected);
+        assert_eq!(
+            EvmTransactionRequest::try_parse(hex_tx.as_bytes()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            EvmTransactionRequest::try_parse(format!("0x{}", hex_tx).as_bytes()).unwrap(),
+            expected
+        );
+
+        // raw typed transactions are detected by their type byte
+        let raw_typed_tx = hex::decode("02f8390103843b9aca00847735940082520894772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079c0").unwrap();
+        assert_eq!(
+            EvmTransactionRequest::try_parse(&raw_typed_tx)
+                .unwrap()
+                .transaction_type(),
+            0x02
+        );
+
+        // an explicit format is not second-guessed
+        assert!(EvmTransactionRequest::try_parse_as(&raw_tx, MessageFormat::Hex).is_err());
+        assert!(
+            EvmTransactionRequest::try_parse_as(hex_tx.as_bytes(), MessageFormat::Rlp).is_err()
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_parse_still_accepts_hex() {
+        let raw_unsigned_tx = "0xef01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079018080".to_string();
+        let unsigned_tx = EvmTransactionRequest::parse(raw_unsigned_tx).unwrap();
+        assert_eq!(unsigned_tx.receiver(), Some(recipient()));
+    }
+
     #[test]
     fn throws_error_parsing_malformed_evm_rlp() {
         let random_bytes =
             "0x1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079018080"
                 .to_string();
 
-        let unsigned_tx = EvmTransactionRequest::parse(random_bytes);
+        let unsigned_tx = EvmTransactionRequest::try_parse(random_bytes.as_bytes());
         assert!(unsigned_tx.is_err());
     }
 }
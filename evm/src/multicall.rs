@@ -0,0 +1,536 @@
+//! Unwrapping of batched calls, so that constraints can't be bypassed by wrapping a call in a multicall.
+//!
+//! [`MulticallConstraint`] evaluates a constraint against every call a transaction makes, recursively unwrapping
+//! Multicall3 batches and the `multicall` functions of routers.
+
+use alloc::{format, vec::Vec};
+
+use entropy_programs_core::{
+    Error as CoreError, GetCalldata, GetReceiver, GetValue, SatisfiableForArchitecture,
+};
+use ethers_core::abi::{decode, ParamType, Token};
+use ethers_core::types::{Bytes, H160, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::abi::{selector, Call, Selector};
+use crate::{address, Evm, EvmTransactionRequest};
+
+/// The address Multicall3 is deployed at on most chains.
+pub const MULTICALL3: H160 = H160([
+    0xca, 0x11, 0xbd, 0xe0, 0x59, 0x77, 0xb3, 0x63, 0x11, 0x67, 0x02, 0x88, 0x62, 0xbe, 0x2a, 0x17,
+    0x39, 0x76, 0xca, 0x11,
+]);
+
+/// Multicall3 `aggregate((address,bytes)[])`
+pub const AGGREGATE: Selector = [0x25, 0x2d, 0xba, 0x42];
+/// Multicall3 `tryAggregate(bool,(address,bytes)[])`
+pub const TRY_AGGREGATE: Selector = [0xbc, 0xe3, 0x8b, 0xd7];
+/// Multicall3 `blockAndAggregate((address,bytes)[])`
+pub const BLOCK_AND_AGGREGATE: Selector = [0xc3, 0x07, 0x7f, 0xa9];
+/// Multicall3 `tryBlockAndAggregate(bool,(address,bytes)[])`
+pub const TRY_BLOCK_AND_AGGREGATE: Selector = [0x39, 0x95, 0x42, 0xe9];
+/// Multicall3 `aggregate3((address,bool,bytes)[])`
+pub const AGGREGATE3: Selector = [0x82, 0xad, 0x56, 0xcb];
+/// Multicall3 `aggregate3Value((address,bool,uint256,bytes)[])`
+pub const AGGREGATE3_VALUE: Selector = [0x17, 0x4d, 0xea, 0x71];
+/// Router `multicall(bytes[])`, which delegate calls the router itself
+pub const MULTICALL: Selector = [0xac, 0x96, 0x50, 0xd8];
+/// Router `multicall(uint256,bytes[])`, with a deadline
+pub const MULTICALL_WITH_DEADLINE: Selector = [0x5a, 0xe4, 0x01, 0xdc];
+/// Router `multicall(bytes32,bytes[])`, with the previous block hash
+pub const MULTICALL_WITH_PREVIOUS_BLOCKHASH: Selector = [0x1f, 0x04, 0x64, 0xd1];
+
+/// The default for [`CallUnwrapper::max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 4;
+
+/// Unwraps batched calls into the calls they make.
+///
+/// Router `multicall`s only call the router itself, so they are always unwrapped. Multicall3 batches call
+/// arbitrary contracts, so they are only unwrapped for the given `multicall_contracts`: a call to any other
+/// contract using a Multicall3 function is an unknown wrapper, whose calls can't be trusted to be the ones it
+/// makes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallUnwrapper {
+    /// Contracts trusted to be Multicall3, eg. [`MULTICALL3`].
+    #[serde(default, with = "address::checksummed_vec")]
+    pub multicall_contracts: Vec<H160>,
+    /// How deeply batches can be nested. `None` uses [`DEFAULT_MAX_DEPTH`].
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Whether calls to unknown wrappers are allowed, and treated as a single call. When `false`, they are
+    /// rejected.
+    #[serde(default)]
+    pub allow_unknown_wrappers: bool,
+}
+
+impl CallUnwrapper {
+    /// Recursively unwraps a call into the calls it makes, or the call itself if it isn't a batch. Errors if
+    /// batches are nested more than the max depth, for unknown wrappers unless they are allowed, for empty batches
+    /// and for Multicall3 batches that don't forward all the value sent to them.
+    ///
+    /// The calls of router `multicall`s are to the router with the value sent to the batch, so constraints on
+    /// them also apply to the outer call's target and value.
+    pub fn unwrap(&self, call: Call) -> Result<Vec<Call>, CoreError> {
+        let mut calls = Vec::new();
+        self.unwrap_into(call, 0, &mut calls)?;
+        Ok(calls)
+    }
+
+    fn unwrap_into(
+        &self,
+        call: Call,
+        depth: usize,
+        calls: &mut Vec<Call>,
+    ) -> Result<(), CoreError> {
+        let Some(inner_calls) = self.inner_calls(&call)? else {
+            calls.push(call);
+            return Ok(());
+        };
+        if inner_calls.is_empty() {
+            return Err(CoreError::Evaluation(
+                "Batch calls without any calls are not allowed.".into(),
+            ));
+        }
+        let max_depth = self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        if depth >= max_depth {
+            return Err(CoreError::Evaluation(format!(
+                "Batch calls are nested more than {} deep.",
+                max_depth
+            )));
+        }
+        for inner_call in inner_calls {
+            self.unwrap_into(inner_call, depth + 1, calls)?;
+        }
+        Ok(())
+    }
+
+    /// Decodes the calls a batch makes. Returns `None` if the call isn't a batch.
+    fn inner_calls(&self, call: &Call) -> Result<Option<Vec<Call>>, CoreError> {
+        let Some(selector) = selector(&call.data) else {
+            return Ok(None);
+        };
+        let address = ParamType::Address;
+        let bytes = ParamType::Bytes;
+        let calls = |fields: Vec<ParamType>| ParamType::Array(ParamType::Tuple(fields).into());
+        let types = match selector {
+            AGGREGATE | BLOCK_AND_AGGREGATE => [calls([address, bytes].to_vec())].to_vec(),
+            TRY_AGGREGATE | TRY_BLOCK_AND_AGGREGATE => {
+                [ParamType::Bool, calls([address, bytes].to_vec())].to_vec()
+            }
+            AGGREGATE3 => [calls([address, ParamType::Bool, bytes].to_vec())].to_vec(),
+            AGGREGATE3_VALUE => [calls(
+                [address, ParamType::Bool, ParamType::Uint(256), bytes].to_vec(),
+            )]
+            .to_vec(),
+            MULTICALL => [ParamType::Array(bytes.into())].to_vec(),
+            MULTICALL_WITH_DEADLINE => {
+                [ParamType::Uint(256), ParamType::Array(bytes.into())].to_vec()
+            }
+            MULTICALL_WITH_PREVIOUS_BLOCKHASH => {
+                [ParamType::FixedBytes(32), ParamType::Array(bytes.into())].to_vec()
+            }
+            _ => return Ok(None),
+        };
+        let is_router_multicall = matches!(
+            selector,
+            MULTICALL | MULTICALL_WITH_DEADLINE | MULTICALL_WITH_PREVIOUS_BLOCKHASH
+        );
+        if !is_router_multicall && !self.multicall_contracts.contains(&call.to) {
+            return match self.allow_unknown_wrappers {
+                true => Ok(None),
+                false => Err(CoreError::Evaluation(format!(
+                    "Batch call to unknown wrapper {:?} not allowed.",
+                    call.to
+                ))),
+            };
+        }
+
+        let args = decode(&types, &call.data[4..])
+            .map_err(|e| CoreError::Evaluation(format!("Unable to decode batch call: {}", e)))?;
+        // the batch is always the last argument, and the types were checked when decoding
+        let batch = args
+            .into_iter()
+            .last()
+            .and_then(Token::into_array)
+            .expect("decoded as array");
+        let inner_calls = batch
+            .into_iter()
+            .map(|token| match token {
+                // delegate calls to the router, which all see the value sent to the multicall
+                Token::Bytes(data) => Call {
+                    to: call.to,
+                    value: call.value,
+                    data: data.into(),
+                },
+                Token::Tuple(fields) => {
+                    let mut fields = fields.into_iter();
+                    let to = fields.next().and_then(Token::into_address);
+                    let data = fields.next_back().and_then(Token::into_bytes);
+                    // only `aggregate3Value` calls have a value, just before the calldata
+                    let value = match selector {
+                        AGGREGATE3_VALUE => fields.next_back().and_then(Token::into_uint),
+                        _ => None,
+                    };
+                    Call {
+                        to: to.expect("decoded as address"),
+                        value: value.unwrap_or_default(),
+                        data: data.expect("decoded as bytes").into(),
+                    }
+                }
+                _ => unreachable!("decoded as bytes or tuple"),
+            })
+            .collect::<Vec<_>>();
+
+        // Multicall3 keeps any value its calls don't forward, where anyone can take it
+        if !is_router_multicall {
+            let forwarded = inner_calls
+                .iter()
+                .try_fold(U256::zero(), |total, call| total.checked_add(call.value));
+            if forwarded != Some(call.value) {
+                return Err(CoreError::Evaluation(
+                    "Value sent to a batch must be forwarded by its calls.".into(),
+                ));
+            }
+        }
+        Ok(Some(inner_calls))
+    }
+}
+
+/// The transaction with its recipient, value and calldata replaced by those of a call.
+fn with_call(tx: &EvmTransactionRequest, call: Call) -> EvmTransactionRequest {
+    let Call { to, value, data } = call;
+    let mut tx = tx.clone();
+    match &mut tx {
+        EvmTransactionRequest::Legacy(tx) => {
+            tx.to = Some(to.into());
+            tx.value = Some(value);
+            tx.data = Some(data);
+        }
+        EvmTransactionRequest::Eip2930(tx) => {
+            tx.tx.to = Some(to.into());
+            tx.tx.value = Some(value);
+            tx.tx.data = Some(data);
+        }
+        EvmTransactionRequest::Eip1559(tx) => {
+            tx.to = Some(to.into());
+            tx.value = Some(value);
+            tx.data = Some(data);
+        }
+        EvmTransactionRequest::Eip4844(tx) => {
+            tx.to = to;
+            tx.value = value;
+            tx.data = data;
+        }
+    }
+    tx
+}
+
+/// Evaluates a constraint against every call a transaction makes, see [`CallUnwrapper`]. Each call is evaluated
+/// as the transaction with the call's recipient, value and calldata.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MulticallConstraint<C> {
+    pub constraint: C,
+    #[serde(flatten)]
+    pub unwrapper: CallUnwrapper,
+}
+
+impl<C> SatisfiableForArchitecture<Evm> for MulticallConstraint<C>
+where
+    C: SatisfiableForArchitecture<Evm> + Clone,
+{
+    fn is_satisfied_by(self, tx: &EvmTransactionRequest) -> Result<(), CoreError> {
+        // contract creations don't call anything
+        let Some(to) = GetReceiver::<Evm>::receiver(tx) else {
+            return self.constraint.is_satisfied_by(tx);
+        };
+        let call = Call {
+            to,
+            value: GetValue::<Evm>::value(tx).unwrap_or_default(),
+            data: Bytes::from(
+                GetCalldata::<Evm>::calldata(tx)
+                    .unwrap_or_default()
+                    .to_vec(),
+            ),
+        };
+        for call in self.unwrapper.unwrap(call)? {
+            self.constraint
+                .clone()
+                .is_satisfied_by(&with_call(tx, call))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::abi::encode;
+    use ethers_core::utils::id;
+
+    use super::*;
+    use crate::token::{TokenConstraint, TRANSFER};
+    use crate::{Eip1559TransactionRequest, LegacyTransactionRequest};
+
+    const ROUTER: H160 = H160::repeat_byte(0x40);
+    const TOKEN: H160 = H160::repeat_byte(0x70);
+    const ALICE: H160 = H160::repeat_byte(0xa1);
+    const MALLORY: H160 = H160::repeat_byte(0x66);
+
+    fn calldata(selector: Selector, args: &[Token]) -> Bytes {
+        [selector.as_slice(), &encode(args)].concat().into()
+    }
+
+    fn transfer(to: H160) -> Bytes {
+        calldata(TRANSFER, &[Token::Address(to), Token::Uint(100.into())])
+    }
+
+    fn aggregate(calls: &[(H160, Bytes)]) -> Bytes {
+        let calls = calls
+            .iter()
+            .map(|(to, data)| Token::Tuple(vec![Token::Address(*to), Token::Bytes(data.to_vec())]))
+            .collect();
+        calldata(AGGREGATE, &[Token::Array(calls)])
+    }
+
+    fn multicall(calls: &[Bytes]) -> Bytes {
+        let calls = calls
+            .iter()
+            .map(|data| Token::Bytes(data.to_vec()))
+            .collect();
+        calldata(MULTICALL, &[Token::Array(calls)])
+    }
+
+    fn call(to: H160, data: Bytes) -> Call {
+        Call {
+            to,
+            value: U256::zero(),
+            data,
+        }
+    }
+
+    #[test]
+    fn selectors_match_signatures() {
+        assert_eq!(AGGREGATE, id("aggregate((address,bytes)[])"));
+        assert_eq!(TRY_AGGREGATE, id("tryAggregate(bool,(address,bytes)[])"));
+        assert_eq!(
+            BLOCK_AND_AGGREGATE,
+            id("blockAndAggregate((address,bytes)[])")
+        );
+        assert_eq!(
+            TRY_BLOCK_AND_AGGREGATE,
+            id("tryBlockAndAggregate(bool,(address,bytes)[])")
+        );
+        assert_eq!(AGGREGATE3, id("aggregate3((address,bool,bytes)[])"));
+        assert_eq!(
+            AGGREGATE3_VALUE,
+            id("aggregate3Value((address,bool,uint256,bytes)[])")
+        );
+        assert_eq!(MULTICALL, id("multicall(bytes[])"));
+        assert_eq!(MULTICALL_WITH_DEADLINE, id("multicall(uint256,bytes[])"));
+        assert_eq!(
+            MULTICALL_WITH_PREVIOUS_BLOCKHASH,
+            id("multicall(bytes32,bytes[])")
+        );
+        assert_eq!(
+            MULTICALL3,
+            "0xcA11bde05977b3631167028862bE2a173976CA11"
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn can_unwrap_batches() {
+        let unwrapper = CallUnwrapper {
+            multicall_contracts: vec![MULTICALL3],
+            ..Default::default()
+        };
+
+        // not a batch
+        assert_eq!(
+            unwrapper.unwrap(call(TOKEN, transfer(ALICE))).unwrap(),
+            vec![call(TOKEN, transfer(ALICE))]
+        );
+
+        // Multicall3 with a nested router multicall, which sees the value sent to it
+        let aggregate3_value = calldata(
+            AGGREGATE3_VALUE,
+            &[Token::Array(vec![
+                Token::Tuple(vec![
+                    Token::Address(TOKEN),
+                    Token::Bool(false),
+                    Token::Uint(0.into()),
+                    Token::Bytes(transfer(ALICE).to_vec()),
+                ]),
+                Token::Tuple(vec![
+                    Token::Address(ROUTER),
+                    Token::Bool(true),
+                    Token::Uint(5.into()),
+                    Token::Bytes(multicall(&[vec![1].into(), vec![2].into()]).to_vec()),
+                ]),
+            ])],
+        );
+        assert_eq!(
+            unwrapper
+                .unwrap(Call {
+                    to: MULTICALL3,
+                    value: 5.into(),
+                    data: aggregate3_value,
+                })
+                .unwrap(),
+            vec![
+                call(TOKEN, transfer(ALICE)),
+                Call {
+                    to: ROUTER,
+                    value: 5.into(),
+                    data: vec![1].into(),
+                },
+                Call {
+                    to: ROUTER,
+                    value: 5.into(),
+                    data: vec![2].into(),
+                },
+            ]
+        );
+
+        // other Multicall3 functions, and router multicalls with a deadline
+        let try_aggregate = calldata(
+            TRY_AGGREGATE,
+            &[
+                Token::Bool(true),
+                Token::Array(vec![Token::Tuple(vec![
+                    Token::Address(TOKEN),
+                    Token::Bytes(transfer(ALICE).to_vec()),
+                ])]),
+            ],
+        );
+        assert_eq!(
+            unwrapper.unwrap(call(MULTICALL3, try_aggregate)).unwrap(),
+            vec![call(TOKEN, transfer(ALICE))]
+        );
+        let with_deadline = calldata(
+            MULTICALL_WITH_DEADLINE,
+            &[
+                Token::Uint(1_700_000_000.into()),
+                Token::Array(vec![Token::Bytes(vec![1])]),
+            ],
+        );
+        assert_eq!(
+            unwrapper.unwrap(call(ROUTER, with_deadline)).unwrap(),
+            vec![call(ROUTER, vec![1].into())]
+        );
+
+        // unknown wrappers, too deeply nested batches and malformed batches
+        let batch = aggregate(&[(TOKEN, transfer(ALICE))]);
+        assert!(unwrapper.unwrap(call(ROUTER, batch.clone())).is_err());
+        let allowing_unknown_wrappers = CallUnwrapper {
+            allow_unknown_wrappers: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            allowing_unknown_wrappers
+                .unwrap(call(ROUTER, batch.clone()))
+                .unwrap(),
+            vec![call(ROUTER, batch)]
+        );
+        let mut nested = transfer(ALICE);
+        for _ in 0..DEFAULT_MAX_DEPTH {
+            nested = multicall(&[nested]);
+        }
+        assert_eq!(
+            unwrapper
+                .unwrap(call(ROUTER, nested.clone()))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(unwrapper
+            .unwrap(call(ROUTER, multicall(&[nested])))
+            .is_err());
+        assert!(unwrapper
+            .unwrap(call(
+                ROUTER,
+                multicall(&[vec![1].into()])[..40].to_vec().into()
+            ))
+            .is_err());
+
+        // empty batches
+        assert!(unwrapper.unwrap(call(MULTICALL3, aggregate(&[]))).is_err());
+        assert!(unwrapper.unwrap(call(ROUTER, multicall(&[]))).is_err());
+
+        // value that Multicall3 would keep
+        let with_value = |data: Bytes, value: u64| Call {
+            to: MULTICALL3,
+            value: value.into(),
+            data,
+        };
+        assert!(unwrapper
+            .unwrap(with_value(aggregate(&[(TOKEN, transfer(ALICE))]), 1))
+            .is_err());
+        let aggregate3_value = |value: u64| {
+            calldata(
+                AGGREGATE3_VALUE,
+                &[Token::Array(vec![Token::Tuple(vec![
+                    Token::Address(ALICE),
+                    Token::Bool(false),
+                    Token::Uint(value.into()),
+                    Token::Bytes(vec![]),
+                ])])],
+            )
+        };
+        assert!(unwrapper.unwrap(with_value(aggregate3_value(5), 5)).is_ok());
+        assert!(unwrapper
+            .unwrap(with_value(aggregate3_value(4), 5))
+            .is_err());
+    }
+
+    #[test]
+    fn multicall_constraint_functions_properly() {
+        let constraint: MulticallConstraint<TokenConstraint> = serde_json::from_str(&format!(
+            r#"{{
+                "constraint": {{ "allowed_recipients": ["{:?}"] }},
+                "multicall_contracts": ["{:?}"],
+                "max_depth": 2
+            }}"#,
+            ALICE, MULTICALL3
+        ))
+        .unwrap();
+        assert_eq!(constraint.unwrapper.max_depth, Some(2));
+        let evaluate = |to: H160, data: Bytes| {
+            let tx: EvmTransactionRequest =
+                Eip1559TransactionRequest::new().to(to).data(data).into();
+            SatisfiableForArchitecture::<Evm>::is_satisfied_by(constraint.clone(), &tx).is_ok()
+        };
+
+        assert!(evaluate(TOKEN, transfer(ALICE)));
+        assert!(!evaluate(TOKEN, transfer(MALLORY)));
+        assert!(evaluate(
+            MULTICALL3,
+            aggregate(&[(TOKEN, transfer(ALICE)), (TOKEN, transfer(ALICE))])
+        ));
+        // a forbidden transfer can't be hidden in a batch
+        assert!(!evaluate(
+            MULTICALL3,
+            aggregate(&[(TOKEN, transfer(ALICE)), (TOKEN, transfer(MALLORY))])
+        ));
+        assert!(!evaluate(
+            MULTICALL3,
+            aggregate(&[(TOKEN, multicall(&[transfer(MALLORY)]))])
+        ));
+        assert!(!evaluate(ROUTER, aggregate(&[(TOKEN, transfer(ALICE))])));
+        // nested more than twice
+        assert!(!evaluate(
+            TOKEN,
+            multicall(&[multicall(&[multicall(&[transfer(ALICE)])])])
+        ));
+        // empty batches don't skip the constraint
+        assert!(!evaluate(MULTICALL3, aggregate(&[])));
+        assert!(!evaluate(ROUTER, multicall(&[])));
+
+        // contract creations are evaluated as they are
+        assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+            constraint,
+            &LegacyTransactionRequest::new().into()
+        )
+        .is_err());
+    }
+}
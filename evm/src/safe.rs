@@ -0,0 +1,528 @@
+//! Safe (formerly Gnosis Safe) multisig transactions.
+//!
+//! When signing as one of a Safe's owners, the signature request holds the `SafeTx` typed data and uses the
+//! [`Eip712`] architecture, whose hash is the safeTxHash. Transactions executing a Safe transaction with
+//! `execTransaction` use the [`Evm`] architecture. [`SafeConstraint`] supports both.
+
+use alloc::{format, string::ToString, vec::Vec};
+
+use entropy_programs_core::{
+    Error as CoreError, GetCalldata, GetChainId, GetReceiver, SatisfiableForArchitecture,
+};
+use ethers_core::abi::{decode, encode, ParamType, Token};
+use ethers_core::types::{Bytes, H160, U256};
+use ethers_core::utils::keccak256;
+use serde::{Deserialize, Serialize};
+
+use crate::abi::{selector, Selector};
+use crate::eip712::{self, message_address, message_field, message_uint, TypedData};
+use crate::{address, Eip712, Evm, EvmTransactionRequest};
+
+/// `execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)`
+pub const EXEC_TRANSACTION: Selector = [0x6a, 0x76, 0x12, 0x02];
+
+/// The EIP-712 type of Safe transactions.
+const SAFE_TX_TYPE: &str = "SafeTx(address to,uint256 value,bytes data,uint8 operation,uint256 safeTxGas,uint256 baseGas,uint256 gasPrice,address gasToken,address refundReceiver,uint256 nonce)";
+/// The EIP-712 domain type of Safes, from version 1.3.0.
+const SAFE_DOMAIN_TYPE: &str = "EIP712Domain(uint256 chainId,address verifyingContract)";
+
+/// How a Safe makes its call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Operation {
+    #[default]
+    Call,
+    /// Runs the target's code in the context of the Safe, giving it full control over the Safe.
+    DelegateCall,
+}
+
+impl TryFrom<U256> for Operation {
+    type Error = CoreError;
+
+    fn try_from(operation: U256) -> Result<Self, Self::Error> {
+        match operation {
+            operation if operation.is_zero() => Ok(Operation::Call),
+            operation if operation == U256::one() => Ok(Operation::DelegateCall),
+            _ => Err(CoreError::Evaluation(format!(
+                "Invalid Safe operation {}.",
+                operation
+            ))),
+        }
+    }
+}
+
+/// The fields of a Safe transaction, as in the `SafeTx` typed data.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SafeTx {
+    pub to: H160,
+    pub value: U256,
+    pub data: Bytes,
+    pub operation: Operation,
+    pub safe_tx_gas: U256,
+    pub base_gas: U256,
+    pub gas_price: U256,
+    pub gas_token: H160,
+    pub refund_receiver: H160,
+    pub nonce: U256,
+}
+
+impl SafeTx {
+    /// Decodes the Safe transaction from `execTransaction` calldata. The calldata doesn't include the nonce,
+    /// which is the Safe's current nonce when it is executed, so it has to be given.
+    pub fn decode_exec_transaction(calldata: &[u8], nonce: U256) -> Result<Self, CoreError> {
+        if selector(calldata) != Some(EXEC_TRANSACTION) {
+            return Err(CoreError::Evaluation(
+                "Calldata is not a call to `execTransaction`.".to_string(),
+            ));
+        }
+        let types = [
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Bytes,
+            ParamType::Uint(8),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Bytes,
+        ];
+        let mut args = decode(&types, &calldata[4..])
+            .map_err(|e| {
+                CoreError::Evaluation(format!("Unable to decode `execTransaction` call: {}", e))
+            })?
+            .into_iter();
+        // the types were checked when decoding
+        let mut next = || args.next().expect("decoded one token per type");
+        let address = |token: Token| token.into_address().expect("decoded as address");
+        let uint = |token: Token| token.into_uint().expect("decoded as uint");
+
+        Ok(SafeTx {
+            to: address(next()),
+            value: uint(next()),
+            data: next().into_bytes().expect("decoded as bytes").into(),
+            operation: uint(next()).try_into()?,
+            safe_tx_gas: uint(next()),
+            base_gas: uint(next()),
+            gas_price: uint(next()),
+            gas_token: address(next()),
+            refund_receiver: address(next()),
+            nonce,
+        })
+    }
+
+    /// The EIP-712 struct hash of the transaction.
+    pub fn struct_hash(&self) -> [u8; 32] {
+        let operation = match self.operation {
+            Operation::Call => 0u8,
+            Operation::DelegateCall => 1,
+        };
+        keccak256(encode(&[
+            Token::FixedBytes(keccak256(SAFE_TX_TYPE).to_vec()),
+            Token::Address(self.to),
+            Token::Uint(self.value),
+            Token::FixedBytes(keccak256(&self.data).to_vec()),
+            Token::Uint(operation.into()),
+            Token::Uint(self.safe_tx_gas),
+            Token::Uint(self.base_gas),
+            Token::Uint(self.gas_price),
+            Token::Address(self.gas_token),
+            Token::Address(self.refund_receiver),
+            Token::Uint(self.nonce),
+        ]))
+    }
+}
+
+/// A Safe transaction, with the Safe and chain it is for.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SafeTransaction {
+    pub safe: H160,
+    pub chain_id: u64,
+    pub tx: SafeTx,
+}
+
+impl SafeTransaction {
+    /// Gets the Safe transaction from `SafeTx` typed data. Returns `None` if the typed data is of another type,
+    /// and errors if it is a `SafeTx` whose hash isn't the safeTxHash of its fields.
+    pub fn from_typed_data(typed_data: &TypedData) -> Result<Option<Self>, CoreError> {
+        if typed_data.primary_type != "SafeTx" {
+            return Ok(None);
+        }
+        let invalid = |field: &str| {
+            CoreError::Evaluation(format!("SafeTx typed data has an invalid `{}`.", field))
+        };
+        let address = |field: &str| message_address(typed_data, field).ok_or(invalid(field));
+        let uint = |field: &str| message_uint(typed_data, field)?.ok_or(invalid(field));
+        let data = message_field(typed_data, "data")
+            .and_then(|data| data.as_str())
+            .and_then(|data| data.parse::<Bytes>().ok())
+            .ok_or(invalid("data"))?;
+
+        let transaction = SafeTransaction {
+            safe: typed_data
+                .domain
+                .verifying_contract
+                .ok_or(invalid("verifyingContract"))?,
+            chain_id: typed_data
+                .domain
+                .chain_id
+                .and_then(|chain_id| chain_id.try_into().ok())
+                .ok_or(invalid("chainId"))?,
+            tx: SafeTx {
+                to: address("to")?,
+                value: uint("value")?,
+                data,
+                operation: uint("operation")?.try_into()?,
+                safe_tx_gas: uint("safeTxGas")?,
+                base_gas: uint("baseGas")?,
+                gas_price: uint("gasPrice")?,
+                gas_token: address("gasToken")?,
+                refund_receiver: address("refundReceiver")?,
+                nonce: uint("nonce")?,
+            },
+        };
+        // what gets signed has to be the transaction that was decoded, eg. not a look-alike type
+        if eip712::hash(typed_data)? != transaction.safe_tx_hash() {
+            return Err(CoreError::Evaluation(
+                "SafeTx typed data doesn't hash to its safeTxHash.".to_string(),
+            ));
+        }
+        Ok(Some(transaction))
+    }
+
+    /// Gets the Safe transaction a transaction executes with `execTransaction`, given the Safe's nonce. Returns
+    /// `None` if the transaction doesn't call `execTransaction`, and errors if it has no chain id, since the
+    /// safeTxHash depends on it.
+    pub fn from_exec_transaction(
+        tx: &EvmTransactionRequest,
+        nonce: U256,
+    ) -> Result<Option<Self>, CoreError> {
+        let (Some(safe), Some(calldata)) = (
+            GetReceiver::<Evm>::receiver(tx),
+            GetCalldata::<Evm>::calldata(tx),
+        ) else {
+            return Ok(None);
+        };
+        if selector(calldata) != Some(EXEC_TRANSACTION) {
+            return Ok(None);
+        }
+        Ok(Some(SafeTransaction {
+            safe,
+            chain_id: GetChainId::<Evm>::chain_id(tx).ok_or(CoreError::Evaluation(
+                "Transaction calling execTransaction has no chain id.".to_string(),
+            ))?,
+            tx: SafeTx::decode_exec_transaction(calldata, nonce)?,
+        }))
+    }
+
+    /// The EIP-712 domain separator of the Safe.
+    pub fn domain_separator(&self) -> [u8; 32] {
+        keccak256(encode(&[
+            Token::FixedBytes(keccak256(SAFE_DOMAIN_TYPE).to_vec()),
+            Token::Uint(self.chain_id.into()),
+            Token::Address(self.safe),
+        ]))
+    }
+
+    /// The safeTxHash, which the owners sign.
+    pub fn safe_tx_hash(&self) -> [u8; 32] {
+        keccak256(
+            [
+                [0x19, 0x01].as_slice(),
+                &self.domain_separator(),
+                &self.tx.struct_hash(),
+            ]
+            .concat(),
+        )
+    }
+}
+
+/// Constrains Safe transactions, forbidding `DELEGATECALL` operations unless to an allowed target (eg. the
+/// `MultiSendCallOnly` contract used for batches).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafeConstraint {
+    /// Safes that transactions can be for. `None` allows any Safe.
+    #[serde(default, with = "address::checksummed_option_vec")]
+    pub allowed_safes: Option<Vec<H160>>,
+    /// Whether `DELEGATECALL` operations are allowed to any target.
+    #[serde(default)]
+    pub allow_delegate_call: bool,
+    /// Targets `DELEGATECALL` operations are allowed to.
+    #[serde(default, with = "address::checksummed_vec")]
+    pub allowed_delegate_call_targets: Vec<H160>,
+    /// Whether signature requests that aren't Safe transactions are allowed.
+    #[serde(default)]
+    pub allow_other_transactions: bool,
+}
+
+impl SafeConstraint {
+    fn check(self, transaction: Option<SafeTransaction>) -> Result<(), CoreError> {
+        let Some(SafeTransaction { safe, tx, .. }) = transaction else {
+            return match self.allow_other_transactions {
+                true => Ok(()),
+                false => Err(CoreError::Evaluation(
+                    "Only Safe transactions are allowed.".to_string(),
+                )),
+            };
+        };
+
+        if let Some(safes) = &self.allowed_safes {
+            if !safes.contains(&safe) {
+                return Err(CoreError::Evaluation(format!(
+                    "Safe {:?} not allowed.",
+                    safe
+                )));
+            }
+        }
+
+        if tx.operation == Operation::DelegateCall
+            && !self.allow_delegate_call
+            && !self.allowed_delegate_call_targets.contains(&tx.to)
+        {
+            return Err(CoreError::Evaluation(format!(
+                "DELEGATECALL to {:?} not allowed.",
+                tx.to
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl SatisfiableForArchitecture<Eip712> for SafeConstraint {
+    fn is_satisfied_by(self, typed_data: &TypedData) -> Result<(), CoreError> {
+        self.check(SafeTransaction::from_typed_data(typed_data)?)
+    }
+}
+
+impl SatisfiableForArchitecture<Evm> for SafeConstraint {
+    fn is_satisfied_by(self, tx: &EvmTransactionRequest) -> Result<(), CoreError> {
+        // the nonce is only needed for the safeTxHash, not to check the transaction
+        self.check(SafeTransaction::from_exec_transaction(tx, U256::zero())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entropy_programs_core::TryParse;
+    use ethers_core::utils::id;
+
+    use super::*;
+    use crate::LegacyTransactionRequest;
+
+    const SAFE: H160 = H160::repeat_byte(0x5a);
+    const TARGET: H160 = H160::repeat_byte(0x70);
+    const MULTI_SEND: H160 = H160::repeat_byte(0x3d);
+
+    fn safe_tx(to: H160, operation: Operation) -> SafeTx {
+        SafeTx {
+            to,
+            value: 1000.into(),
+            data: vec![0xde, 0xad, 0xbe, 0xef].into(),
+            operation,
+            nonce: 7.into(),
+            ..Default::default()
+        }
+    }
+
+    fn typed_data(tx: &SafeTx) -> TypedData {
+        let operation = match tx.operation {
+            Operation::Call => 0,
+            Operation::DelegateCall => 1,
+        };
+        let json = format!(
+            r#"{{
+                "types": {{
+                    "EIP712Domain": [
+                        {{ "name": "chainId", "type": "uint256" }},
+                        {{ "name": "verifyingContract", "type": "address" }}
+                    ],
+                    "SafeTx": [
+                        {{ "name": "to", "type": "address" }},
+                        {{ "name": "value", "type": "uint256" }},
+                        {{ "name": "data", "type": "bytes" }},
+                        {{ "name": "operation", "type": "uint8" }},
+                        {{ "name": "safeTxGas", "type": "uint256" }},
+                        {{ "name": "baseGas", "type": "uint256" }},
+                        {{ "name": "gasPrice", "type": "uint256" }},
+                        {{ "name": "gasToken", "type": "address" }},
+                        {{ "name": "refundReceiver", "type": "address" }},
+                        {{ "name": "nonce", "type": "uint256" }}
+                    ]
+                }},
+                "primaryType": "SafeTx",
+                "domain": {{ "chainId": 1, "verifyingContract": "{:?}" }},
+                "message": {{
+                    "to": "{:?}",
+                    "value": "{}",
+                    "data": "{}",
+                    "operation": {},
+                    "safeTxGas": "0",
+                    "baseGas": "0",
+                    "gasPrice": "0",
+                    "gasToken": "{:?}",
+                    "refundReceiver": "{:?}",
+                    "nonce": {}
+                }}
+            }}"#,
+            SAFE, tx.to, tx.value, tx.data, operation, tx.gas_token, tx.refund_receiver, tx.nonce
+        );
+        TypedData::try_parse(json.as_bytes()).unwrap()
+    }
+
+    fn exec_transaction(tx: &SafeTx) -> EvmTransactionRequest {
+        let operation = match tx.operation {
+            Operation::Call => 0u8,
+            Operation::DelegateCall => 1,
+        };
+        let args = encode(&[
+            Token::Address(tx.to),
+            Token::Uint(tx.value),
+            Token::Bytes(tx.data.to_vec()),
+            Token::Uint(operation.into()),
+            Token::Uint(tx.safe_tx_gas),
+            Token::Uint(tx.base_gas),
+            Token::Uint(tx.gas_price),
+            Token::Address(tx.gas_token),
+            Token::Address(tx.refund_receiver),
+            Token::Bytes(vec![0u8; 65]),
+        ]);
+        LegacyTransactionRequest::new()
+            .to(SAFE)
+            .chain_id(1)
+            .data([EXEC_TRANSACTION.as_slice(), &args].concat())
+            .into()
+    }
+
+    #[test]
+    fn exec_transaction_selector_matches_signature() {
+        assert_eq!(
+            EXEC_TRANSACTION,
+            id("execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)")
+        );
+    }
+
+    #[test]
+    fn can_decode_safe_transactions() {
+        let tx = safe_tx(TARGET, Operation::Call);
+        let typed_data = typed_data(&tx);
+
+        let transaction = SafeTransaction::from_typed_data(&typed_data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            transaction,
+            SafeTransaction {
+                safe: SAFE,
+                chain_id: 1,
+                tx: tx.clone(),
+            }
+        );
+        // the safeTxHash is the EIP-712 hash of the typed data
+        assert_eq!(
+            transaction.safe_tx_hash(),
+            eip712::hash(&typed_data).unwrap()
+        );
+        assert_eq!(
+            transaction.domain_separator(),
+            eip712::domain_separator(&typed_data)
+        );
+
+        // execTransaction calldata decodes to the same transaction, given the nonce
+        assert_eq!(
+            SafeTransaction::from_exec_transaction(&exec_transaction(&tx), 7.into())
+                .unwrap()
+                .unwrap(),
+            transaction
+        );
+        assert_eq!(
+            SafeTransaction::from_exec_transaction(
+                &LegacyTransactionRequest::new().to(SAFE).into(),
+                7.into()
+            )
+            .unwrap(),
+            None
+        );
+        // the chain id isn't assumed if it's missing
+        let mut without_chain_id = exec_transaction(&tx);
+        if let EvmTransactionRequest::Legacy(legacy) = &mut without_chain_id {
+            legacy.chain_id = None;
+        }
+        assert!(SafeTransaction::from_exec_transaction(&without_chain_id, 7.into()).is_err());
+        let calldata = GetCalldata::<Evm>::calldata(&exec_transaction(&tx))
+            .unwrap()
+            .to_vec();
+        assert!(SafeTx::decode_exec_transaction(&calldata[..100], 7.into()).is_err());
+
+        // other typed data, or SafeTx typed data whose types don't match the safeTxHash
+        let mut other = typed_data.clone();
+        other.primary_type = "Mail".to_string();
+        assert_eq!(SafeTransaction::from_typed_data(&other).unwrap(), None);
+        let mut look_alike = typed_data;
+        look_alike.types.get_mut("SafeTx").unwrap().pop();
+        assert!(SafeTransaction::from_typed_data(&look_alike).is_err());
+    }
+
+    #[test]
+    fn safe_constraint_functions_properly() {
+        let constraint: SafeConstraint = serde_json::from_str(&format!(
+            r#"{{ "allowed_safes": ["{:?}"], "allowed_delegate_call_targets": ["{:?}"] }}"#,
+            SAFE, MULTI_SEND
+        ))
+        .unwrap();
+        let evaluate_typed_data = |tx: &SafeTx| {
+            SatisfiableForArchitecture::<Eip712>::is_satisfied_by(
+                constraint.clone(),
+                &typed_data(tx),
+            )
+            .is_ok()
+        };
+        let evaluate_exec_transaction = |tx: &SafeTx| {
+            SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+                constraint.clone(),
+                &exec_transaction(tx),
+            )
+            .is_ok()
+        };
+
+        for evaluate in [
+            &evaluate_typed_data as &dyn Fn(&SafeTx) -> bool,
+            &evaluate_exec_transaction,
+        ] {
+            assert!(evaluate(&safe_tx(TARGET, Operation::Call)));
+            assert!(evaluate(&safe_tx(MULTI_SEND, Operation::DelegateCall)));
+            assert!(!evaluate(&safe_tx(TARGET, Operation::DelegateCall)));
+        }
+
+        // other transactions and Safes
+        assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+            constraint.clone(),
+            &LegacyTransactionRequest::new().to(TARGET).into()
+        )
+        .is_err());
+        let other_safe = SafeConstraint {
+            allowed_safes: Some(vec![TARGET]),
+            allow_delegate_call: true,
+            ..Default::default()
+        };
+        assert!(SatisfiableForArchitecture::<Eip712>::is_satisfied_by(
+            other_safe,
+            &typed_data(&safe_tx(TARGET, Operation::Call))
+        )
+        .is_err());
+        let permissive = SafeConstraint {
+            allow_delegate_call: true,
+            allow_other_transactions: true,
+            ..Default::default()
+        };
+        assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+            permissive.clone(),
+            &exec_transaction(&safe_tx(TARGET, Operation::DelegateCall))
+        )
+        .is_ok());
+        assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+            permissive,
+            &LegacyTransactionRequest::new().to(TARGET).into()
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,362 @@
+//! ACLs over the functions a transaction calls and their arguments, rather than just its recipient.
+
+use alloc::{format, string::String, string::ToString, vec::Vec};
+
+use entropy_programs_core::{
+    AclKind, Error as CoreError, GetCalldata, GetReceiver, SatisfiableForArchitecture,
+};
+use ethers_core::types::{H160, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::abi::{decode_calldata, selector, Abi, DecodedCall, Selector, Token};
+use crate::{address, Evm, EvmTransactionRequest};
+
+/// An allow or deny list of (contract, function) pairs, where functions are identified by the 4-byte selector
+/// at the start of the calldata. Entries can also constrain the function's arguments, which are decoded against
+/// the ABI given in the config.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SelectorAcl {
+    pub entries: Vec<SelectorEntry>,
+    pub kind: AclKind,
+    #[serde(default)]
+    pub allow_null_recipient: bool,
+    /// Whether transactions that don't call a function (ie. have no calldata, like plain transfers) are allowed.
+    #[serde(default)]
+    pub allow_empty_calldata: bool,
+    /// The JSON ABI (or just the fragments) of the functions whose arguments are constrained.
+    #[serde(default)]
+    pub abi: Abi,
+}
+
+/// A function on a contract, or on any contract.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorEntry {
+    /// The contract, or `None` to match the function on any contract.
+    #[serde(default, with = "address::checksummed_option")]
+    pub contract: Option<H160>,
+    /// The selector, written in hex, eg. `"0xa9059cbb"`.
+    #[serde(with = "entropy_programs_core::config::selector_hex")]
+    pub selector: Selector,
+    /// Constraints the arguments must all satisfy for the entry to match. Calls whose arguments can't be decoded
+    /// against the ABI are rejected.
+    #[serde(default)]
+    pub args: Vec<ArgConstraint>,
+}
+
+/// A constraint on an argument of a function, by the name of its input in the ABI.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArgConstraint {
+    pub name: String,
+    /// The allowed values of an `address` argument. `None` allows any address.
+    #[serde(default, with = "address::checksummed_option_vec")]
+    pub allowed_addresses: Option<Vec<H160>>,
+    /// The inclusive minimum of a `uint` argument, eg. to deny unlimited approvals in a denylist.
+    #[serde(default)]
+    pub min: Option<U256>,
+    /// The inclusive maximum of a `uint` argument.
+    #[serde(default)]
+    pub max: Option<U256>,
+}
+
+impl ArgConstraint {
+    fn is_satisfied_by(&self, call: &DecodedCall) -> Result<bool, CoreError> {
+        let arg = call.arg(&self.name).ok_or(CoreError::Evaluation(format!(
+            "Function `{}` has no argument `{}`.",
+            call.function.name, self.name
+        )))?;
+        let type_error = || {
+            CoreError::Evaluation(format!(
+                "Argument `{}` of `{}` has the wrong type for its constraint.",
+                self.name, call.function.name
+            ))
+        };
+        if let Some(allowed_addresses) = &self.allowed_addresses {
+            let Token::Address(address) = arg else {
+                return Err(type_error());
+            };
+            if !allowed_addresses.contains(address) {
+                return Ok(false);
+            }
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let Token::Uint(value) = arg else {
+                return Err(type_error());
+            };
+            if self.min.is_some_and(|min| *value < min) || self.max.is_some_and(|max| *value > max)
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl SelectorAcl {
+    /// Whether an entry matches a call to `contract` with the given calldata.
+    fn matches(
+        &self,
+        entry: &SelectorEntry,
+        contract: H160,
+        selector: Selector,
+        calldata: &[u8],
+    ) -> Result<bool, CoreError> {
+        if entry.selector != selector || entry.contract.is_some_and(|c| c != contract) {
+            return Ok(false);
+        }
+        if entry.args.is_empty() {
+            return Ok(true);
+        }
+        let call = decode_calldata(&self.abi, calldata)?;
+        for arg in &entry.args {
+            if !arg.is_satisfied_by(&call)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl SatisfiableForArchitecture<Evm> for SelectorAcl {
+    fn is_satisfied_by(self, tx: &EvmTransactionRequest) -> Result<(), CoreError> {
+        let receivers = GetReceiver::<Evm>::receivers(tx).ok_or(CoreError::Evaluation(
+            "Transaction receivers could not be determined.".to_string(),
+        ))?;
+        if receivers.len() > 1 {
+            return Err(CoreError::Evaluation(
+                "Transactions with several receivers are not supported.".to_string(),
+            ));
+        }
+        // contract creations don't call a function, their calldata is the init code
+        let Some(receiver) = receivers.first().copied() else {
+            return match self.allow_null_recipient {
+                true => Ok(()),
+                false => Err(CoreError::Evaluation(
+                    "Null recipients are not allowed.".to_string(),
+                )),
+            };
+        };
+
+        let calldata = GetCalldata::<Evm>::calldata(tx).unwrap_or_default();
+        if calldata.is_empty() {
+            return match self.allow_empty_calldata {
+                true => Ok(()),
+                false => Err(CoreError::Evaluation(
+                    "Transactions without calldata are not allowed.".to_string(),
+                )),
+            };
+        }
+        let selector = selector(calldata).ok_or(CoreError::Evaluation(
+            "Calldata is too short to contain a function selector.".to_string(),
+        ))?;
+
+        let mut listed = false;
+        for entry in &self.entries {
+            if self.matches(entry, receiver, selector, calldata)? {
+                listed = true;
+                break;
+            }
+        }
+        match self.kind.permits(listed) {
+            true => Ok(()),
+            false => Err(CoreError::Evaluation(
+                "Function call not allowed.".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::abi::encode;
+
+    use super::*;
+    use crate::token::{APPROVE, TRANSFER};
+    use crate::LegacyTransactionRequest;
+
+    const TOKEN: H160 = H160::repeat_byte(0x70);
+    const OTHER_TOKEN: H160 = H160::repeat_byte(0x71);
+    const ALICE: H160 = H160::repeat_byte(0xa1);
+    const BOB: H160 = H160::repeat_byte(0xb0);
+
+    const ERC20_ABI: &str = r#"[
+        {
+            "type": "function",
+            "name": "approve",
+            "inputs": [
+                { "name": "spender", "type": "address" },
+                { "name": "amount", "type": "uint256" }
+            ],
+            "outputs": [{ "name": "", "type": "bool" }],
+            "stateMutability": "nonpayable"
+        }
+    ]"#;
+
+    fn call(to: Option<H160>, calldata: &[u8]) -> EvmTransactionRequest {
+        LegacyTransactionRequest {
+            to: to.map(Into::into),
+            data: Some(calldata.to_vec().into()),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    fn approve(spender: H160, amount: impl Into<U256>) -> Vec<u8> {
+        [
+            APPROVE.as_slice(),
+            &encode(&[Token::Address(spender), Token::Uint(amount.into())]),
+        ]
+        .concat()
+    }
+
+    fn evaluate(acl: &SelectorAcl, tx: EvmTransactionRequest) -> bool {
+        SatisfiableForArchitecture::<Evm>::is_satisfied_by(acl.clone(), &tx).is_ok()
+    }
+
+    #[test]
+    fn selector_acl_functions_properly() {
+        // transfers of `TOKEN` are allowed, as is `approve` on any contract
+        let allowlist = SelectorAcl {
+            entries: vec![
+                SelectorEntry {
+                    contract: Some(TOKEN),
+                    selector: TRANSFER,
+                    args: vec![],
+                },
+                SelectorEntry {
+                    contract: None,
+                    selector: APPROVE,
+                    args: vec![],
+                },
+            ],
+            ..Default::default()
+        };
+        let transfer_args = [TRANSFER.as_slice(), &[0u8; 64]].concat();
+        assert!(evaluate(&allowlist, call(Some(TOKEN), &transfer_args)));
+        assert!(!evaluate(
+            &allowlist,
+            call(Some(OTHER_TOKEN), &transfer_args)
+        ));
+        assert!(evaluate(&allowlist, call(Some(OTHER_TOKEN), &APPROVE)));
+        assert!(!evaluate(
+            &allowlist,
+            call(Some(TOKEN), &[0xde, 0xad, 0xbe, 0xef])
+        ));
+        // plain transfers, contract creations and truncated selectors
+        assert!(!evaluate(&allowlist, call(Some(TOKEN), &[])));
+        assert!(!evaluate(&allowlist, call(None, &transfer_args)));
+        assert!(!evaluate(&allowlist, call(Some(TOKEN), &TRANSFER[..3])));
+
+        let denylist = SelectorAcl {
+            entries: vec![SelectorEntry {
+                contract: None,
+                selector: APPROVE,
+                args: vec![],
+            }],
+            kind: AclKind::Deny,
+            allow_null_recipient: true,
+            allow_empty_calldata: true,
+            ..Default::default()
+        };
+        assert!(!evaluate(&denylist, call(Some(TOKEN), &APPROVE)));
+        assert!(evaluate(&denylist, call(Some(TOKEN), &transfer_args)));
+        assert!(evaluate(&denylist, call(Some(TOKEN), &[])));
+        assert!(evaluate(&denylist, call(None, &APPROVE)));
+        // a truncated selector cannot be checked against the list
+        assert!(!evaluate(&denylist, call(Some(TOKEN), &APPROVE[..2])));
+
+        assert!(!evaluate(
+            &SelectorAcl::default(),
+            call(Some(TOKEN), &TRANSFER)
+        ));
+    }
+
+    #[test]
+    fn selector_acl_constrains_arguments() {
+        let json = format!(
+            r#"{{
+                "entries": [{{
+                    "contract": "{}",
+                    "selector": "0x095ea7b3",
+                    "args": [
+                        {{ "name": "spender", "allowed_addresses": ["{}"] }},
+                        {{ "name": "amount", "max": "0x3e8" }}
+                    ]
+                }}],
+                "kind": "Allow",
+                "abi": {}
+            }}"#,
+            ethers_core::utils::to_checksum(&TOKEN, None),
+            ethers_core::utils::to_checksum(&BOB, None),
+            ERC20_ABI
+        );
+        let acl: SelectorAcl = serde_json::from_str(&json).unwrap();
+        assert_eq!(acl.entries[0].args[1].max, Some(1000.into()));
+
+        assert!(evaluate(&acl, call(Some(TOKEN), &approve(BOB, 1000))));
+        assert!(!evaluate(&acl, call(Some(TOKEN), &approve(BOB, 1001))));
+        assert!(!evaluate(&acl, call(Some(TOKEN), &approve(ALICE, 1))));
+        assert!(!evaluate(&acl, call(Some(OTHER_TOKEN), &approve(BOB, 1))));
+        // arguments that can't be decoded
+        assert!(!evaluate(&acl, call(Some(TOKEN), &approve(BOB, 1)[..40])));
+
+        // a denylist rejects calls that can't be decoded rather than letting them through
+        let denylist = SelectorAcl {
+            kind: AclKind::Deny,
+            ..acl.clone()
+        };
+        assert!(evaluate(&denylist, call(Some(TOKEN), &approve(BOB, 1001))));
+        assert!(!evaluate(&denylist, call(Some(TOKEN), &approve(BOB, 1000))));
+        assert!(!evaluate(
+            &denylist,
+            call(Some(TOKEN), &approve(BOB, 1)[..40])
+        ));
+
+        // unlimited approvals to any spender can be denied with a minimum
+        let unlimited_approvals = SelectorAcl {
+            entries: vec![SelectorEntry {
+                contract: None,
+                selector: APPROVE,
+                args: vec![ArgConstraint {
+                    name: "amount".to_string(),
+                    min: Some(U256::MAX),
+                    ..Default::default()
+                }],
+            }],
+            kind: AclKind::Deny,
+            ..acl.clone()
+        };
+        assert!(!evaluate(
+            &unlimited_approvals,
+            call(Some(TOKEN), &approve(ALICE, U256::MAX))
+        ));
+        assert!(evaluate(
+            &unlimited_approvals,
+            call(Some(TOKEN), &approve(ALICE, U256::MAX - 1))
+        ));
+        assert!(evaluate(
+            &unlimited_approvals,
+            call(Some(OTHER_TOKEN), &approve(BOB, 1))
+        ));
+
+        // constraints on arguments that aren't in the ABI, or of the wrong type
+        let mut misconfigured = acl.clone();
+        misconfigured.entries[0].args = vec![ArgConstraint {
+            name: "value".to_string(),
+            max: Some(1.into()),
+            ..Default::default()
+        }];
+        assert!(!evaluate(
+            &misconfigured,
+            call(Some(TOKEN), &approve(BOB, 1))
+        ));
+        misconfigured.entries[0].args = vec![ArgConstraint {
+            name: "spender".to_string(),
+            max: Some(1.into()),
+            ..Default::default()
+        }];
+        assert!(!evaluate(
+            &misconfigured,
+            call(Some(TOKEN), &approve(BOB, 1))
+        ));
+    }
+}
@@ -0,0 +1,604 @@
+//! Decoding of standard ERC-20, ERC-721 and ERC-1155 token calls, and a constraint on them.
+//!
+//! For token transfers the real recipient and amount are in the calldata, while the transaction's `to` is the
+//! token contract and its `value` is usually zero.
+
+use alloc::{format, string::ToString, vec::Vec};
+
+use entropy_programs_core::{
+    Error as CoreError, GetCalldata, GetReceiver, GetValue, SatisfiableForArchitecture,
+};
+use ethers_core::abi::{decode, ParamType, Token};
+use ethers_core::types::{Bytes, H160, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{abi::selector, abi::Selector, address, Evm, EvmTransactionRequest};
+
+/// `transfer(address,uint256)`
+pub const TRANSFER: Selector = [0xa9, 0x05, 0x9c, 0xbb];
+/// `transferFrom(address,address,uint256)`
+pub const TRANSFER_FROM: Selector = [0x23, 0xb8, 0x72, 0xdd];
+/// `approve(address,uint256)`
+pub const APPROVE: Selector = [0x09, 0x5e, 0xa7, 0xb3];
+/// ERC-721 `safeTransferFrom(address,address,uint256)`
+pub const SAFE_TRANSFER_FROM: Selector = [0x42, 0x84, 0x2e, 0x0e];
+/// ERC-721 `safeTransferFrom(address,address,uint256,bytes)`
+pub const SAFE_TRANSFER_FROM_WITH_DATA: Selector = [0xb8, 0x8d, 0x4f, 0xde];
+/// ERC-1155 `safeTransferFrom(address,address,uint256,uint256,bytes)`
+pub const SAFE_TRANSFER_FROM_ERC1155: Selector = [0xf2, 0x42, 0x43, 0x2a];
+/// ERC-1155 `safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)`
+pub const SAFE_BATCH_TRANSFER_FROM: Selector = [0x2e, 0xb2, 0xc2, 0xd6];
+/// `setApprovalForAll(address,bool)`
+pub const SET_APPROVAL_FOR_ALL: Selector = [0xa2, 0x2c, 0xb4, 0x65];
+
+/// Approvals of at least this amount (2^255) are treated as unlimited, since no real balance comes close and
+/// wallets use `U256::MAX` or amounts just below it.
+pub const UNLIMITED_APPROVAL_THRESHOLD: U256 = U256([0, 0, 0, 1 << 63]);
+
+/// A call to a standard token function.
+///
+/// ERC-20 and ERC-721 share the `transferFrom` and `approve` selectors, so `amount` is the token id for
+/// ERC-721 calls to those.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenAction {
+    /// ERC-20 `transfer`
+    Transfer { token: H160, to: H160, amount: U256 },
+    /// ERC-20 or ERC-721 `transferFrom`
+    TransferFrom {
+        token: H160,
+        from: H160,
+        to: H160,
+        amount: U256,
+    },
+    /// ERC-20 or ERC-721 `approve`
+    Approve {
+        token: H160,
+        spender: H160,
+        amount: U256,
+    },
+    /// ERC-721 or ERC-1155 `safeTransferFrom`. ERC-721 transfers have an amount of 1.
+    SafeTransferFrom {
+        token: H160,
+        from: H160,
+        to: H160,
+        id: U256,
+        amount: U256,
+        data: Bytes,
+    },
+    /// ERC-1155 `safeBatchTransferFrom`, transferring `amounts[i]` of each token id `ids[i]`
+    SafeBatchTransferFrom {
+        token: H160,
+        from: H160,
+        to: H160,
+        ids: Vec<U256>,
+        amounts: Vec<U256>,
+        data: Bytes,
+    },
+    /// ERC-721 or ERC-1155 `setApprovalForAll`
+    SetApprovalForAll {
+        token: H160,
+        operator: H160,
+        approved: bool,
+    },
+}
+
+impl TokenAction {
+    /// Decodes the token call a transaction makes. Returns `None` if the transaction doesn't call one of the
+    /// standard token functions, and errors if it does but the arguments are malformed.
+    pub fn decode(tx: &EvmTransactionRequest) -> Result<Option<Self>, CoreError> {
+        let (Some(token), Some(calldata)) = (
+            GetReceiver::<Evm>::receiver(tx),
+            GetCalldata::<Evm>::calldata(tx),
+        ) else {
+            return Ok(None);
+        };
+        let Some(selector) = selector(calldata) else {
+            return Ok(None);
+        };
+
+        let address = ParamType::Address;
+        let uint = ParamType::Uint(256);
+        let types = match selector {
+            TRANSFER | APPROVE => [address, uint].to_vec(),
+            TRANSFER_FROM | SAFE_TRANSFER_FROM => [address.clone(), address, uint].to_vec(),
+            SAFE_TRANSFER_FROM_WITH_DATA => {
+                [address.clone(), address, uint, ParamType::Bytes].to_vec()
+            }
+            SAFE_TRANSFER_FROM_ERC1155 => [
+                address.clone(),
+                address,
+                uint.clone(),
+                uint,
+                ParamType::Bytes,
+            ]
+            .to_vec(),
+            SAFE_BATCH_TRANSFER_FROM => [
+                address.clone(),
+                address,
+                ParamType::Array(uint.clone().into()),
+                ParamType::Array(uint.into()),
+                ParamType::Bytes,
+            ]
+            .to_vec(),
+            SET_APPROVAL_FOR_ALL => [address, ParamType::Bool].to_vec(),
+            _ => return Ok(None),
+        };
+        let mut args = decode(&types, &calldata[4..])
+            .map_err(|e| {
+                CoreError::Evaluation(format!("Unable to decode token call arguments: {}", e))
+            })?
+            .into_iter();
+        // the types were checked when decoding
+        let mut next = || args.next().expect("decoded one token per type");
+        let address = |token: Token| token.into_address().expect("decoded as address");
+        let uint = |token: Token| token.into_uint().expect("decoded as uint");
+        let uints = |token: Token| -> Vec<U256> {
+            let uints = token.into_array().expect("decoded as array");
+            uints.into_iter().map(uint).collect()
+        };
+
+        Ok(Some(match selector {
+            TRANSFER => TokenAction::Transfer {
+                token,
+                to: address(next()),
+                amount: uint(next()),
+            },
+            APPROVE => TokenAction::Approve {
+                token,
+                spender: address(next()),
+                amount: uint(next()),
+            },
+            TRANSFER_FROM => TokenAction::TransferFrom {
+                token,
+                from: address(next()),
+                to: address(next()),
+                amount: uint(next()),
+            },
+            SAFE_BATCH_TRANSFER_FROM => {
+                let from = address(next());
+                let to = address(next());
+                let ids = uints(next());
+                let amounts = uints(next());
+                if ids.len() != amounts.len() {
+                    return Err(CoreError::Evaluation(
+                        "Batch transfer ids and amounts have different lengths.".to_string(),
+                    ));
+                }
+                TokenAction::SafeBatchTransferFrom {
+                    token,
+                    from,
+                    to,
+                    ids,
+                    amounts,
+                    data: next().into_bytes().expect("decoded as bytes").into(),
+                }
+            }
+            SET_APPROVAL_FOR_ALL => TokenAction::SetApprovalForAll {
+                token,
+                operator: address(next()),
+                approved: next().into_bool().expect("decoded as bool"),
+            },
+            erc721_or_erc1155 => {
+                let from = address(next());
+                let to = address(next());
+                let id = uint(next());
+                let amount = match erc721_or_erc1155 {
+                    SAFE_TRANSFER_FROM_ERC1155 => uint(next()),
+                    _ => U256::one(),
+                };
+                let data = match erc721_or_erc1155 {
+                    SAFE_TRANSFER_FROM => Bytes::default(),
+                    _ => next().into_bytes().expect("decoded as bytes").into(),
+                };
+                TokenAction::SafeTransferFrom {
+                    token,
+                    from,
+                    to,
+                    id,
+                    amount,
+                    data,
+                }
+            }
+        }))
+    }
+
+    /// The token contract being called.
+    pub fn token(&self) -> H160 {
+        match self {
+            TokenAction::Transfer { token, .. }
+            | TokenAction::TransferFrom { token, .. }
+            | TokenAction::Approve { token, .. }
+            | TokenAction::SafeTransferFrom { token, .. }
+            | TokenAction::SafeBatchTransferFrom { token, .. }
+            | TokenAction::SetApprovalForAll { token, .. } => *token,
+        }
+    }
+
+    /// The address receiving tokens, an allowance or operator rights.
+    pub fn recipient(&self) -> H160 {
+        match self {
+            TokenAction::Transfer { to, .. }
+            | TokenAction::TransferFrom { to, .. }
+            | TokenAction::SafeTransferFrom { to, .. }
+            | TokenAction::SafeBatchTransferFrom { to, .. } => *to,
+            TokenAction::Approve { spender, .. } => *spender,
+            TokenAction::SetApprovalForAll { operator, .. } => *operator,
+        }
+    }
+
+    /// The amount transferred or approved, or `None` for `setApprovalForAll`. For batch transfers this is the
+    /// total over every token id, saturating at `U256::MAX`.
+    pub fn amount(&self) -> Option<U256> {
+        match self {
+            TokenAction::Transfer { amount, .. }
+            | TokenAction::TransferFrom { amount, .. }
+            | TokenAction::Approve { amount, .. }
+            | TokenAction::SafeTransferFrom { amount, .. } => Some(*amount),
+            TokenAction::SafeBatchTransferFrom { amounts, .. } => Some(
+                amounts
+                    .iter()
+                    .fold(U256::zero(), |total, amount| total.saturating_add(*amount)),
+            ),
+            TokenAction::SetApprovalForAll { .. } => None,
+        }
+    }
+
+    /// Whether this revokes operator rights (`setApprovalForAll` with `false`), which doesn't give the recipient
+    /// anything.
+    pub fn is_revocation(&self) -> bool {
+        matches!(
+            self,
+            TokenAction::SetApprovalForAll {
+                approved: false,
+                ..
+            }
+        )
+    }
+
+    /// Whether this approves the recipient to spend an unlimited amount: `approve` with at least
+    /// [`UNLIMITED_APPROVAL_THRESHOLD`], or `setApprovalForAll` granting operator rights over every token in the
+    /// collection.
+    pub fn is_unlimited_approval(&self) -> bool {
+        match self {
+            TokenAction::Approve { amount, .. } => *amount >= UNLIMITED_APPROVAL_THRESHOLD,
+            TokenAction::SetApprovalForAll { approved, .. } => *approved,
+            _ => false,
+        }
+    }
+}
+
+/// Constrains the token calls a transaction makes, see [`TokenAction`]. Token calls sending a native value are
+/// not allowed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenConstraint {
+    /// Addresses allowed to receive tokens, allowances or operator rights. `None` allows any recipient. Operator
+    /// rights can be revoked from any address.
+    #[serde(default, with = "address::checksummed_option_vec")]
+    pub allowed_recipients: Option<Vec<H160>>,
+    /// Per-token caps on the amount transferred or approved. Tokens without a cap are not limited.
+    #[serde(default)]
+    pub caps: Vec<TokenCap>,
+    /// Whether unlimited approvals are allowed, see [`TokenAction::is_unlimited_approval`].
+    #[serde(default)]
+    pub allow_unlimited_approvals: bool,
+    /// Whether transactions that don't call a standard token function are allowed.
+    #[serde(default)]
+    pub allow_other_transactions: bool,
+}
+
+/// The maximum amount of a token that can be transferred or approved in a single transaction.
+///
+/// Since ERC-721 `transferFrom` and `approve` take a token id rather than an amount, caps should only be
+/// set for fungible (ERC-20 or ERC-1155) tokens.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenCap {
+    #[serde(with = "address::checksummed")]
+    pub token: H160,
+    pub max_amount: U256,
+}
+
+impl SatisfiableForArchitecture<Evm> for TokenConstraint {
+    fn is_satisfied_by(self, tx: &EvmTransactionRequest) -> Result<(), CoreError> {
+        let Some(action) = TokenAction::decode(tx)? else {
+            return match self.allow_other_transactions {
+                true => Ok(()),
+                false => Err(CoreError::Evaluation(
+                    "Only token transfers and approvals are allowed.".to_string(),
+                )),
+            };
+        };
+
+        // token functions aren't payable, so any value would go to the token contract
+        if !GetValue::<Evm>::value(tx).unwrap_or_default().is_zero() {
+            return Err(CoreError::Evaluation(
+                "Token calls sending a native value are not allowed.".to_string(),
+            ));
+        }
+
+        if let Some(recipients) = &self.allowed_recipients {
+            if !action.is_revocation() && !recipients.contains(&action.recipient()) {
+                return Err(CoreError::Evaluation(format!(
+                    "Token recipient {:?} not allowed.",
+                    action.recipient()
+                )));
+            }
+        }
+
+        if action.is_unlimited_approval() && !self.allow_unlimited_approvals {
+            return Err(CoreError::Evaluation(
+                "Unlimited token approvals are not allowed.".to_string(),
+            ));
+        }
+
+        if let (Some(cap), Some(amount)) = (
+            self.caps.iter().find(|cap| cap.token == action.token()),
+            action.amount(),
+        ) {
+            if amount > cap.max_amount {
+                return Err(CoreError::Evaluation(format!(
+                    "Token amount {} exceeds the cap of {}.",
+                    amount, cap.max_amount
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::abi::encode;
+    use ethers_core::utils::id;
+
+    use super::*;
+    use crate::LegacyTransactionRequest;
+
+    const TOKEN: H160 = H160::repeat_byte(0x70);
+    const ALICE: H160 = H160::repeat_byte(0xa1);
+    const BOB: H160 = H160::repeat_byte(0xb0);
+
+    fn call(selector: Selector, args: &[Token]) -> EvmTransactionRequest {
+        LegacyTransactionRequest::new()
+            .to(TOKEN)
+            .data([selector.as_slice(), &encode(args)].concat())
+            .into()
+    }
+
+    #[test]
+    fn selectors_match_signatures() {
+        assert_eq!(TRANSFER, id("transfer(address,uint256)"));
+        assert_eq!(TRANSFER_FROM, id("transferFrom(address,address,uint256)"));
+        assert_eq!(APPROVE, id("approve(address,uint256)"));
+        assert_eq!(
+            SAFE_TRANSFER_FROM,
+            id("safeTransferFrom(address,address,uint256)")
+        );
+        assert_eq!(
+            SAFE_TRANSFER_FROM_WITH_DATA,
+            id("safeTransferFrom(address,address,uint256,bytes)")
+        );
+        assert_eq!(
+            SAFE_TRANSFER_FROM_ERC1155,
+            id("safeTransferFrom(address,address,uint256,uint256,bytes)")
+        );
+        assert_eq!(
+            SAFE_BATCH_TRANSFER_FROM,
+            id("safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)")
+        );
+        assert_eq!(SET_APPROVAL_FOR_ALL, id("setApprovalForAll(address,bool)"));
+    }
+
+    #[test]
+    fn can_decode_token_actions() {
+        let transfer = call(TRANSFER, &[Token::Address(BOB), Token::Uint(5.into())]);
+        assert_eq!(
+            TokenAction::decode(&transfer).unwrap(),
+            Some(TokenAction::Transfer {
+                token: TOKEN,
+                to: BOB,
+                amount: 5.into()
+            })
+        );
+
+        let erc1155_transfer = call(
+            SAFE_TRANSFER_FROM_ERC1155,
+            &[
+                Token::Address(ALICE),
+                Token::Address(BOB),
+                Token::Uint(7.into()),
+                Token::Uint(100.into()),
+                Token::Bytes(b"memo".to_vec()),
+            ],
+        );
+        assert_eq!(
+            TokenAction::decode(&erc1155_transfer).unwrap(),
+            Some(TokenAction::SafeTransferFrom {
+                token: TOKEN,
+                from: ALICE,
+                to: BOB,
+                id: 7.into(),
+                amount: 100.into(),
+                data: b"memo".to_vec().into(),
+            })
+        );
+
+        let uints = |uints: &[u64]| {
+            Token::Array(
+                uints
+                    .iter()
+                    .map(|uint| Token::Uint((*uint).into()))
+                    .collect(),
+            )
+        };
+        let batch_transfer = |ids: &[u64], amounts: &[u64]| {
+            call(
+                SAFE_BATCH_TRANSFER_FROM,
+                &[
+                    Token::Address(ALICE),
+                    Token::Address(BOB),
+                    uints(ids),
+                    uints(amounts),
+                    Token::Bytes(vec![]),
+                ],
+            )
+        };
+        let action = TokenAction::decode(&batch_transfer(&[7, 8], &[100, 20]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            action,
+            TokenAction::SafeBatchTransferFrom {
+                token: TOKEN,
+                from: ALICE,
+                to: BOB,
+                ids: vec![7.into(), 8.into()],
+                amounts: vec![100.into(), 20.into()],
+                data: Bytes::default(),
+            }
+        );
+        assert_eq!(action.recipient(), BOB);
+        assert_eq!(action.amount(), Some(120.into()));
+        assert!(TokenAction::decode(&batch_transfer(&[7, 8], &[100])).is_err());
+
+        let erc721_transfer = call(
+            SAFE_TRANSFER_FROM,
+            &[
+                Token::Address(ALICE),
+                Token::Address(BOB),
+                Token::Uint(7.into()),
+            ],
+        );
+        let action = TokenAction::decode(&erc721_transfer).unwrap().unwrap();
+        assert_eq!(action.recipient(), BOB);
+        assert_eq!(action.amount(), Some(U256::one()));
+
+        let approval = call(
+            SET_APPROVAL_FOR_ALL,
+            &[Token::Address(BOB), Token::Bool(true)],
+        );
+        let action = TokenAction::decode(&approval).unwrap().unwrap();
+        assert_eq!(action.recipient(), BOB);
+        assert!(action.is_unlimited_approval());
+
+        let approve = |amount: U256| {
+            TokenAction::decode(&call(APPROVE, &[Token::Address(BOB), Token::Uint(amount)]))
+                .unwrap()
+                .unwrap()
+        };
+        assert!(approve(U256::MAX - 1).is_unlimited_approval());
+        assert!(approve(U256::one() << 255).is_unlimited_approval());
+        assert!(!approve((U256::one() << 255) - 1).is_unlimited_approval());
+
+        // not token calls
+        assert_eq!(
+            TokenAction::decode(&call([0xde, 0xad, 0xbe, 0xef], &[])).unwrap(),
+            None
+        );
+        assert_eq!(
+            TokenAction::decode(&LegacyTransactionRequest::new().to(TOKEN).into()).unwrap(),
+            None
+        );
+        // malformed token calls
+        assert!(TokenAction::decode(&call(TRANSFER, &[Token::Address(BOB)])).is_err());
+    }
+
+    #[test]
+    fn token_constraint_functions_properly() {
+        let constraint = TokenConstraint {
+            allowed_recipients: Some(vec![BOB]),
+            caps: vec![TokenCap {
+                token: TOKEN,
+                max_amount: 1000.into(),
+            }],
+            allow_unlimited_approvals: false,
+            allow_other_transactions: false,
+        };
+        let evaluate = |tx: EvmTransactionRequest| {
+            SatisfiableForArchitecture::<Evm>::is_satisfied_by(constraint.clone(), &tx).is_ok()
+        };
+        let transfer =
+            |to: H160, amount: U256| call(TRANSFER, &[Token::Address(to), Token::Uint(amount)]);
+        let approve = |spender: H160, amount: U256| {
+            call(APPROVE, &[Token::Address(spender), Token::Uint(amount)])
+        };
+
+        assert!(evaluate(transfer(BOB, 1000.into())));
+        assert!(!evaluate(transfer(BOB, 1001.into())));
+        assert!(!evaluate(transfer(ALICE, 1.into())));
+        assert!(evaluate(approve(BOB, 10.into())));
+        assert!(!evaluate(approve(BOB, U256::MAX)));
+        // unlimited approvals are rejected even for tokens without a cap
+        let other_token_approval: EvmTransactionRequest = LegacyTransactionRequest::new()
+            .to(H160::repeat_byte(0x71))
+            .data(
+                [
+                    APPROVE.as_slice(),
+                    &encode(&[Token::Address(BOB), Token::Uint(U256::MAX - 1)]),
+                ]
+                .concat(),
+            )
+            .into();
+        assert!(!evaluate(other_token_approval));
+        assert!(!evaluate(approve(BOB, UNLIMITED_APPROVAL_THRESHOLD)));
+        // token calls can't send value
+        let mut with_value = transfer(BOB, 1.into());
+        if let EvmTransactionRequest::Legacy(tx) = &mut with_value {
+            tx.value = Some(1.into());
+        }
+        assert!(!evaluate(with_value));
+        assert!(!evaluate(approve(ALICE, 10.into())));
+        assert!(!evaluate(call(
+            SET_APPROVAL_FOR_ALL,
+            &[Token::Address(BOB), Token::Bool(true)]
+        )));
+        // revoking operator rights is fine, including from recipients that aren't allowed
+        assert!(evaluate(call(
+            SET_APPROVAL_FOR_ALL,
+            &[Token::Address(BOB), Token::Bool(false)]
+        )));
+        assert!(evaluate(call(
+            SET_APPROVAL_FOR_ALL,
+            &[Token::Address(ALICE), Token::Bool(false)]
+        )));
+        // batch transfers are checked against the recipients and the cap on their total
+        let batch_transfer = |to: H160, amounts: &[U256]| {
+            call(
+                SAFE_BATCH_TRANSFER_FROM,
+                &[
+                    Token::Address(ALICE),
+                    Token::Address(to),
+                    Token::Array(amounts.iter().map(|_| Token::Uint(1.into())).collect()),
+                    Token::Array(amounts.iter().copied().map(Token::Uint).collect()),
+                    Token::Bytes(vec![]),
+                ],
+            )
+        };
+        assert!(evaluate(batch_transfer(BOB, &[600.into(), 400.into()])));
+        assert!(!evaluate(batch_transfer(BOB, &[600.into(), 401.into()])));
+        assert!(!evaluate(batch_transfer(BOB, &[U256::MAX, 2.into()])));
+        assert!(!evaluate(batch_transfer(ALICE, &[1.into()])));
+        assert!(!evaluate(LegacyTransactionRequest::new().to(BOB).into()));
+
+        // config is given as JSON
+        let json = r#"{
+            "allowed_recipients": ["0xb0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0"],
+            "caps": [{ "token": "0x7070707070707070707070707070707070707070", "max_amount": "0x3e8" }]
+        }"#;
+        assert_eq!(
+            serde_json::from_str::<TokenConstraint>(json).unwrap(),
+            constraint
+        );
+
+        let permissive = TokenConstraint {
+            allow_unlimited_approvals: true,
+            allow_other_transactions: true,
+            ..Default::default()
+        };
+        assert!(SatisfiableForArchitecture::<Evm>::is_satisfied_by(
+            permissive,
+            &approve(ALICE, U256::MAX)
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,257 @@
+//! Typed (EIP-2718) EVM transaction requests.
+
+use alloc::vec::Vec;
+
+pub use ethers_core::types::transaction::eip1559::Eip1559TransactionRequest;
+pub use ethers_core::types::transaction::eip2930::{
+    AccessList, AccessListItem, Eip2930TransactionRequest,
+};
+pub use ethers_core::types::transaction::request::TransactionRequest as LegacyTransactionRequest;
+use ethers_core::types::{transaction::eip2718::TypedTransaction, Bytes, H160, H256, U256, U64};
+use ethers_core::utils::keccak256;
+use rlp::{Decodable, DecoderError, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+
+/// Blob gas used per blob, from EIP-4844.
+pub const GAS_PER_BLOB: u64 = 1 << 17;
+
+/// An unsigned EVM transaction request of any of the supported EIP-2718 transaction types.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum EvmTransactionRequest {
+    /// Legacy transaction, optionally with EIP-155 replay protection
+    #[serde(rename = "0x00", alias = "0x0")]
+    Legacy(LegacyTransactionRequest),
+    /// EIP-2930 access list transaction (type `0x01`)
+    #[serde(rename = "0x01", alias = "0x1")]
+    Eip2930(Eip2930TransactionRequest),
+    /// EIP-1559 dynamic fee transaction (type `0x02`)
+    #[serde(rename = "0x02", alias = "0x2")]
+    Eip1559(Eip1559TransactionRequest),
+    /// EIP-4844 blob transaction (type `0x03`)
+    #[serde(rename = "0x03", alias = "0x3")]
+    Eip4844(Eip4844TransactionRequest),
+}
+
+/// An unsigned EIP-4844 blob transaction request. Only the signed payload is included: blobs, commitments and
+/// proofs are not part of the signature.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip4844TransactionRequest {
+    pub chain_id: U64,
+    pub nonce: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas: U256,
+    /// Blob transactions cannot create contracts, so always have a recipient.
+    pub to: H160,
+    pub value: U256,
+    pub data: Bytes,
+    pub access_list: AccessList,
+    pub max_fee_per_blob_gas: U256,
+    pub blob_versioned_hashes: Vec<H256>,
+}
+
+impl Eip4844TransactionRequest {
+    /// Gets the unsigned transaction's RLP encoding, without the type byte.
+    pub fn rlp(&self) -> Bytes {
+        let mut rlp = RlpStream::new();
+        rlp.begin_list(11);
+        rlp.append(&self.chain_id);
+        rlp.append(&self.nonce);
+        rlp.append(&self.max_priority_fee_per_gas);
+        rlp.append(&self.max_fee_per_gas);
+        rlp.append(&self.gas);
+        rlp.append(&self.to);
+        rlp.append(&self.value);
+        rlp.append(&self.data.as_ref());
+        rlp.append(&self.access_list);
+        rlp.append(&self.max_fee_per_blob_gas);
+        rlp.append_list(&self.blob_versioned_hashes);
+        rlp.out().freeze().into()
+    }
+}
+
+impl Decodable for Eip4844TransactionRequest {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 11 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+
+        Ok(Self {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            max_priority_fee_per_gas: rlp.val_at(2)?,
+            max_fee_per_gas: rlp.val_at(3)?,
+            gas: rlp.val_at(4)?,
+            to: rlp.val_at(5)?,
+            value: rlp.val_at(6)?,
+            data: rlp.val_at::<Vec<u8>>(7)?.into(),
+            access_list: rlp.val_at(8)?,
+            max_fee_per_blob_gas: rlp.val_at(9)?,
+            blob_versioned_hashes: rlp.list_at(10)?,
+        })
+    }
+}
+
+/// Checks that the RLP is a single list, without trailing bytes, with one of the expected numbers of fields.
+fn check_unsigned_list(rlp: &Rlp, expected_fields: &[usize]) -> Result<(), DecoderError> {
+    if !expected_fields.contains(&rlp.item_count()?) {
+        return Err(DecoderError::RlpIncorrectListLen);
+    }
+    if rlp.payload_info()?.total() != rlp.as_raw().len() {
+        return Err(DecoderError::RlpInconsistentLengthAndData);
+    }
+    Ok(())
+}
+
+impl EvmTransactionRequest {
+    /// Decodes an unsigned transaction, detecting its type from the EIP-2718 envelope: a legacy transaction is
+    /// an RLP list, while typed transactions are prefixed with their type byte.
+    pub fn decode_unsigned(bytes: &[u8]) -> Result<Self, DecoderError> {
+        let (&first, payload) = bytes.split_first().ok_or(DecoderError::RlpIsTooShort)?;
+        if first >= 0xc0 {
+            let rlp = Rlp::new(bytes);
+            // unsigned legacy transactions have 6 fields, or 9 with EIP-155's `chain_id, 0, 0` in place of the
+            // signature, and the legacy decoder doesn't check for trailing fields or a real signature
+            check_unsigned_list(&rlp, &[6, 9])?;
+            if rlp.item_count()? == 9 && !(rlp.at(7)?.is_empty() && rlp.at(8)?.is_empty()) {
+                return Err(DecoderError::Custom("Legacy transaction is signed"));
+            }
+            return Ok(Self::Legacy(LegacyTransactionRequest::decode_unsigned_rlp(
+                &rlp,
+            )?));
+        }
+
+        let rlp = Rlp::new(payload);
+        // the typed decoders don't check for trailing fields, eg. a signature
+        let expected_fields = match first {
+            0x01 => 8,
+            0x02 => 9,
+            0x03 => 11,
+            _ => return Err(DecoderError::Custom("Unsupported transaction type")),
+        };
+        check_unsigned_list(&rlp, &[expected_fields])?;
+
+        match first {
+            0x01 => Ok(Self::Eip2930(rlp.as_val()?)),
+            0x02 => Ok(Self::Eip1559(rlp.as_val()?)),
+            _ => Ok(Self::Eip4844(rlp.as_val()?)),
+        }
+    }
+
+    /// EIP-2718 transaction type.
+    pub fn transaction_type(&self) -> u8 {
+        match self {
+            Self::Legacy(_) => 0x00,
+            Self::Eip2930(_) => 0x01,
+            Self::Eip1559(_) => 0x02,
+            Self::Eip4844(_) => 0x03,
+        }
+    }
+
+    /// Gets the unsigned transaction's encoding, including the type byte for typed transactions.
+    pub fn rlp(&self) -> Bytes {
+        match self {
+            Self::Legacy(tx) => tx.rlp(),
+            Self::Eip2930(tx) => TypedTransaction::Eip2930(tx.clone()).rlp(),
+            Self::Eip1559(tx) => TypedTransaction::Eip1559(tx.clone()).rlp(),
+            Self::Eip4844(tx) => {
+                let mut encoded = alloc::vec![0x03];
+                encoded.extend_from_slice(&tx.rlp());
+                encoded.into()
+            }
+        }
+    }
+
+    /// Hash of the transaction that is signed.
+    pub fn sighash(&self) -> H256 {
+        match self {
+            Self::Legacy(tx) => tx.sighash(),
+            _ => keccak256(self.rlp()).into(),
+        }
+    }
+
+    /// Gas limit of the transaction.
+    pub fn gas(&self) -> Option<U256> {
+        match self {
+            Self::Legacy(tx) => tx.gas,
+            Self::Eip2930(tx) => tx.tx.gas,
+            Self::Eip1559(tx) => tx.gas,
+            Self::Eip4844(tx) => Some(tx.gas),
+        }
+    }
+
+    /// Maximum price per unit of gas the transaction can pay: the gas price for legacy and EIP-2930
+    /// transactions, and the max fee per gas otherwise.
+    pub fn max_fee_per_gas(&self) -> Option<U256> {
+        match self {
+            Self::Legacy(tx) => tx.gas_price,
+            Self::Eip2930(tx) => tx.tx.gas_price,
+            Self::Eip1559(tx) => tx.max_fee_per_gas,
+            Self::Eip4844(tx) => Some(tx.max_fee_per_gas),
+        }
+    }
+
+    /// Maximum priority fee (tip) per unit of gas, for EIP-1559 and EIP-4844 transactions.
+    pub fn max_priority_fee_per_gas(&self) -> Option<U256> {
+        match self {
+            Self::Legacy(_) | Self::Eip2930(_) => None,
+            Self::Eip1559(tx) => tx.max_priority_fee_per_gas,
+            Self::Eip4844(tx) => Some(tx.max_priority_fee_per_gas),
+        }
+    }
+
+    /// Maximum total fee the transaction can pay: gas limit × max fee per gas, plus the maximum blob fee for
+    /// EIP-4844 transactions. `None` if a field is missing or the fee overflows.
+    pub fn max_fee(&self) -> Option<U256> {
+        let execution_fee = self.gas()?.checked_mul(self.max_fee_per_gas()?)?;
+        match self {
+            Self::Eip4844(tx) => {
+                let blob_gas = U256::from(GAS_PER_BLOB)
+                    .checked_mul(U256::from(tx.blob_versioned_hashes.len()))?;
+                execution_fee.checked_add(blob_gas.checked_mul(tx.max_fee_per_blob_gas)?)
+            }
+            _ => Some(execution_fee),
+        }
+    }
+
+    pub fn access_list(&self) -> Option<&AccessList> {
+        match self {
+            Self::Legacy(_) => None,
+            Self::Eip2930(tx) => Some(&tx.access_list),
+            Self::Eip1559(tx) => Some(&tx.access_list),
+            Self::Eip4844(tx) => Some(&tx.access_list),
+        }
+    }
+}
+
+impl Default for EvmTransactionRequest {
+    fn default() -> Self {
+        Self::Legacy(LegacyTransactionRequest::default())
+    }
+}
+
+impl From<LegacyTransactionRequest> for EvmTransactionRequest {
+    fn from(tx: LegacyTransactionRequest) -> Self {
+        Self::Legacy(tx)
+    }
+}
+
+impl From<Eip2930TransactionRequest> for EvmTransactionRequest {
+    fn from(tx: Eip2930TransactionRequest) -> Self {
+        Self::Eip2930(tx)
+    }
+}
+
+impl From<Eip1559TransactionRequest> for EvmTransactionRequest {
+    fn from(tx: Eip1559TransactionRequest) -> Self {
+        Self::Eip1559(tx)
+    }
+}
+
+impl From<Eip4844TransactionRequest> for EvmTransactionRequest {
+    fn from(tx: Eip4844TransactionRequest) -> Self {
+        Self::Eip4844(tx)
+    }
+}
@@ -0,0 +1,836 @@
+//! The ERC-4337 `UserOperation` architecture, for smart accounts.
+//!
+//! Signature requests hold the JSON user operation, in either the v0.6 or the v0.7 packed format, along with
+//! the entry point and chain id it is for. Since what gets signed is the userOpHash, programs using this
+//! architecture should return [`custom_hash`] from their own `custom_hash`.
+//!
+//! The receiver, value and calldata are those of the call the account makes, unwrapping its `execute` and
+//! `executeBatch` functions. Calls to other account functions have no known receivers, unless they are
+//! configured as calls of the account to itself with [`SelfCallConstraint`].
+
+use alloc::{format, vec::Vec};
+
+use entropy_programs_core::{
+    Architecture, Error as CoreError, GetCalldata, GetChainId, GetFee, GetNonce, GetReceiver,
+    GetSender, GetValue, SatisfiableForArchitecture, TryParse,
+};
+use ethers_core::abi::{decode, encode, ParamType, Token};
+use ethers_core::types::{Bytes, H160, H256, U256};
+use ethers_core::utils::keccak256;
+use serde::{Deserialize, Serialize};
+
+use crate::abi::{selector, Call, Selector};
+
+/// `execute(address,uint256,bytes)`
+pub const EXECUTE: Selector = [0xb6, 0x1d, 0x27, 0xf6];
+/// `executeBatch(address[],bytes[])`, from the v0.6 `SimpleAccount`
+pub const EXECUTE_BATCH: Selector = [0x18, 0xdf, 0xb3, 0xc7];
+/// `executeBatch(address[],uint256[],bytes[])`, from the v0.7 `SimpleAccount`
+pub const EXECUTE_BATCH_WITH_VALUE: Selector = [0x47, 0xe1, 0xda, 0x2a];
+
+/// ERC-4337 user operation "architecture". The sender is the smart account.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserOperation;
+
+impl Architecture for UserOperation {
+    type Address = H160;
+    type AddressRaw = [u8; 20];
+    type Value = U256;
+    type ChainId = u64;
+    type Nonce = u64;
+    type Fee = U256;
+    type TransactionRequest = UserOperationRequest;
+}
+
+/// A user operation, with the entry point and chain id needed to hash it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationRequest {
+    pub entry_point: H160,
+    pub chain_id: u64,
+    pub user_operation: UserOperationData,
+}
+
+/// A user operation in the format of its entry point version.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum UserOperationData {
+    V06(UserOperationV06),
+    V07(PackedUserOperation),
+}
+
+/// A user operation for the v0.6 entry point.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationV06 {
+    pub sender: H160,
+    pub nonce: U256,
+    pub init_code: Bytes,
+    pub call_data: Bytes,
+    pub call_gas_limit: U256,
+    pub verification_gas_limit: U256,
+    pub pre_verification_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub paymaster_and_data: Bytes,
+    /// Not part of the hash, so usually empty when signing.
+    #[serde(default)]
+    pub signature: Bytes,
+}
+
+/// A user operation for the v0.7 entry point, with gas limits and fees packed into 32 bytes each.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackedUserOperation {
+    pub sender: H160,
+    pub nonce: U256,
+    pub init_code: Bytes,
+    pub call_data: Bytes,
+    /// `verificationGasLimit` in the high 16 bytes, and `callGasLimit` in the low 16 bytes
+    pub account_gas_limits: H256,
+    pub pre_verification_gas: U256,
+    /// `maxPriorityFeePerGas` in the high 16 bytes, and `maxFeePerGas` in the low 16 bytes
+    pub gas_fees: H256,
+    /// The paymaster address, its verification and post-op gas limits (16 bytes each), then its data
+    pub paymaster_and_data: Bytes,
+    /// Not part of the hash, so usually empty when signing.
+    #[serde(default)]
+    pub signature: Bytes,
+}
+
+/// Splits 32 bytes into the numbers packed in the high and low 16 bytes.
+fn unpack(packed: &H256) -> (U256, U256) {
+    (
+        U256::from_big_endian(&packed[..16]),
+        U256::from_big_endian(&packed[16..]),
+    )
+}
+
+impl UserOperationData {
+    pub fn sender(&self) -> H160 {
+        match self {
+            UserOperationData::V06(op) => op.sender,
+            UserOperationData::V07(op) => op.sender,
+        }
+    }
+
+    pub fn nonce(&self) -> U256 {
+        match self {
+            UserOperationData::V06(op) => op.nonce,
+            UserOperationData::V07(op) => op.nonce,
+        }
+    }
+
+    /// The calldata of the call from the entry point to the account.
+    pub fn call_data(&self) -> &Bytes {
+        match self {
+            UserOperationData::V06(op) => &op.call_data,
+            UserOperationData::V07(op) => &op.call_data,
+        }
+    }
+
+    fn call_data_mut(&mut self) -> &mut Bytes {
+        match self {
+            UserOperationData::V06(op) => &mut op.call_data,
+            UserOperationData::V07(op) => &mut op.call_data,
+        }
+    }
+
+    /// The hash of the user operation's fields, as packed by its entry point version.
+    fn packed_hash(&self) -> [u8; 32] {
+        let tokens = match self {
+            UserOperationData::V06(op) => [
+                Token::Address(op.sender),
+                Token::Uint(op.nonce),
+                Token::FixedBytes(keccak256(&op.init_code).to_vec()),
+                Token::FixedBytes(keccak256(&op.call_data).to_vec()),
+                Token::Uint(op.call_gas_limit),
+                Token::Uint(op.verification_gas_limit),
+                Token::Uint(op.pre_verification_gas),
+                Token::Uint(op.max_fee_per_gas),
+                Token::Uint(op.max_priority_fee_per_gas),
+                Token::FixedBytes(keccak256(&op.paymaster_and_data).to_vec()),
+            ]
+            .to_vec(),
+            UserOperationData::V07(op) => [
+                Token::Address(op.sender),
+                Token::Uint(op.nonce),
+                Token::FixedBytes(keccak256(&op.init_code).to_vec()),
+                Token::FixedBytes(keccak256(&op.call_data).to_vec()),
+                Token::FixedBytes(op.account_gas_limits.as_bytes().to_vec()),
+                Token::Uint(op.pre_verification_gas),
+                Token::FixedBytes(op.gas_fees.as_bytes().to_vec()),
+                Token::FixedBytes(keccak256(&op.paymaster_and_data).to_vec()),
+            ]
+            .to_vec(),
+        };
+        keccak256(encode(&tokens))
+    }
+
+    /// The most the operation can pay for gas, as required to be prefunded by the entry point.
+    pub fn max_fee(&self) -> Option<U256> {
+        match self {
+            UserOperationData::V06(op) => {
+                // the verification gas limit also applies to the paymaster's validation and post-op calls
+                let verification_multiplier = match op.paymaster_and_data.is_empty() {
+                    true => 1,
+                    false => 3,
+                };
+                op.verification_gas_limit
+                    .checked_mul(verification_multiplier.into())?
+                    .checked_add(op.call_gas_limit)?
+                    .checked_add(op.pre_verification_gas)?
+                    .checked_mul(op.max_fee_per_gas)
+            }
+            UserOperationData::V07(op) => {
+                let (verification_gas_limit, call_gas_limit) = unpack(&op.account_gas_limits);
+                let (_, max_fee_per_gas) = unpack(&op.gas_fees);
+                let paymaster_gas_limits = match op.paymaster_and_data.get(20..52) {
+                    Some(limits) => {
+                        let (verification, post_op) = unpack(&H256::from_slice(limits));
+                        verification.checked_add(post_op)?
+                    }
+                    None => U256::zero(),
+                };
+                verification_gas_limit
+                    .checked_add(call_gas_limit)?
+                    .checked_add(paymaster_gas_limits)?
+                    .checked_add(op.pre_verification_gas)?
+                    .checked_mul(max_fee_per_gas)
+            }
+        }
+    }
+}
+
+impl UserOperationRequest {
+    /// The userOpHash: `keccak256(abi.encode(keccak256(packed user operation), entry point, chain id))`.
+    pub fn user_op_hash(&self) -> [u8; 32] {
+        keccak256(encode(&[
+            Token::FixedBytes(self.user_operation.packed_hash().to_vec()),
+            Token::Address(self.entry_point),
+            Token::Uint(self.chain_id.into()),
+        ]))
+    }
+
+    /// The calls the account makes, unwrapping `execute` and `executeBatch`. Errors for calls to any other
+    /// account function, since accounts implement other ways of making calls (eg. ERC-7579's
+    /// `execute(bytes32,bytes)`) whose targets can't be known, see [`UserOperationRequest::calls_with`].
+    pub fn calls(&self) -> Result<Vec<Call>, CoreError> {
+        self.calls_with(&[])
+    }
+
+    /// The calls the account makes, like [`UserOperationRequest::calls`], with calls to the given account
+    /// functions treated as calls from the account to itself (eg. to manage its owners). These should come from
+    /// config, for functions known not to make calls on the account's behalf.
+    pub fn calls_with(&self, self_call_selectors: &[Selector]) -> Result<Vec<Call>, CoreError> {
+        let account = self.user_operation.sender();
+        let call_data = self.user_operation.call_data();
+        let decode_args = |types: &[ParamType]| {
+            decode(types, &call_data[4..])
+                .map_err(|e| CoreError::Evaluation(format!("Unable to decode account call: {}", e)))
+        };
+        let addresses = |token: Token| -> Vec<H160> {
+            token
+                .into_array()
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(Token::into_address)
+                .collect()
+        };
+        let uints = |token: Token| -> Vec<U256> {
+            token
+                .into_array()
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(Token::into_uint)
+                .collect()
+        };
+        let bytes = |token: Token| -> Vec<Bytes> {
+            token
+                .into_array()
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(Token::into_bytes)
+                .map(Bytes::from)
+                .collect()
+        };
+        let batch = |to: Vec<H160>, values: Vec<U256>, data: Vec<Bytes>| {
+            // like the `SimpleAccount`, no values means none of the calls send value
+            let values = match values.is_empty() {
+                true => alloc::vec![U256::zero(); to.len()],
+                false => values,
+            };
+            if to.len() != data.len() || to.len() != values.len() {
+                return Err(CoreError::Evaluation(
+                    "Batch call has mismatched argument lengths.".into(),
+                ));
+            }
+            Ok(to
+                .into_iter()
+                .zip(values)
+                .zip(data)
+                .map(|((to, value), data)| Call { to, value, data })
+                .collect())
+        };
+
+        match selector(call_data) {
+            Some(EXECUTE) => {
+                let mut args =
+                    decode_args(&[ParamType::Address, ParamType::Uint(256), ParamType::Bytes])?
+                        .into_iter();
+                Ok(alloc::vec![Call {
+                    to: args
+                        .next()
+                        .and_then(Token::into_address)
+                        .unwrap_or_default(),
+                    value: args.next().and_then(Token::into_uint).unwrap_or_default(),
+                    data: args
+                        .next()
+                        .and_then(Token::into_bytes)
+                        .unwrap_or_default()
+                        .into(),
+                }])
+            }
+            Some(EXECUTE_BATCH) => {
+                let mut args = decode_args(&[
+                    ParamType::Array(ParamType::Address.into()),
+                    ParamType::Array(ParamType::Bytes.into()),
+                ])?
+                .into_iter();
+                let to = addresses(args.next().unwrap_or(Token::Array(Vec::new())));
+                let data = bytes(args.next().unwrap_or(Token::Array(Vec::new())));
+                batch(to, Vec::new(), data)
+            }
+            Some(EXECUTE_BATCH_WITH_VALUE) => {
+                let mut args = decode_args(&[
+                    ParamType::Array(ParamType::Address.into()),
+                    ParamType::Array(ParamType::Uint(256).into()),
+                    ParamType::Array(ParamType::Bytes.into()),
+                ])?
+                .into_iter();
+                let to = addresses(args.next().unwrap_or(Token::Array(Vec::new())));
+                let values = uints(args.next().unwrap_or(Token::Array(Vec::new())));
+                let data = bytes(args.next().unwrap_or(Token::Array(Vec::new())));
+                batch(to, values, data)
+            }
+            Some(selector) if self_call_selectors.contains(&selector) => Ok(alloc::vec![Call {
+                to: account,
+                value: U256::zero(),
+                data: call_data.clone(),
+            }]),
+            _ => Err(CoreError::Evaluation(
+                "Account calldata isn't a known way of making calls.".into(),
+            )),
+        }
+    }
+}
+
+/// Evaluates a constraint against a user operation, treating calls to the given account functions as calls from
+/// the account to itself (see [`UserOperationRequest::calls_with`]), so that eg. an ACL allowing the account
+/// itself as a receiver allows them. Other constraints see such user operations as calling nothing else.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfCallConstraint<C> {
+    pub constraint: C,
+    /// Account functions that don't make calls on the account's behalf, written in hex.
+    #[serde(default, with = "entropy_programs_core::config::selector_hex::vec")]
+    pub self_call_selectors: Vec<Selector>,
+}
+
+impl<C> SatisfiableForArchitecture<UserOperation> for SelfCallConstraint<C>
+where
+    C: SatisfiableForArchitecture<UserOperation>,
+{
+    fn is_satisfied_by(self, request: &UserOperationRequest) -> Result<(), CoreError> {
+        let call_data = request.user_operation.call_data();
+        match selector(call_data) {
+            Some(selector) if self.self_call_selectors.contains(&selector) => {
+                // evaluated as the account `execute`ing the call on itself
+                let mut request = request.clone();
+                let execute = [
+                    EXECUTE.as_slice(),
+                    &encode(&[
+                        Token::Address(request.user_operation.sender()),
+                        Token::Uint(U256::zero()),
+                        Token::Bytes(call_data.to_vec()),
+                    ]),
+                ]
+                .concat();
+                *request.user_operation.call_data_mut() = execute.into();
+                self.constraint.is_satisfied_by(&request)
+            }
+            _ => self.constraint.is_satisfied_by(request),
+        }
+    }
+}
+
+impl GetSender<UserOperation> for UserOperationRequest {
+    /// The smart account.
+    fn sender(&self) -> Option<H160> {
+        Some(self.user_operation.sender())
+    }
+}
+
+impl GetReceiver<UserOperation> for UserOperationRequest {
+    /// The target of the account's call, or of all calls of a batch if they have the same target. `None` for
+    /// batches with several targets, which should be checked with [`UserOperationRequest::calls`].
+    fn receiver(&self) -> Option<H160> {
+        match self.receivers()?.as_slice() {
+            [to] => Some(*to),
+            _ => None,
+        }
+    }
+
+    /// The targets of the account's calls. `None` if the calldata can't be decoded, is an empty batch or calls
+    /// an unknown account function.
+    fn receivers(&self) -> Option<Vec<H160>> {
+        let mut targets = Vec::new();
+        for call in self.calls().ok()? {
+            if !targets.contains(&call.to) {
+                targets.push(call.to);
+            }
+        }
+        (!targets.is_empty()).then_some(targets)
+    }
+}
+
+impl GetValue<UserOperation> for UserOperationRequest {
+    /// The total value sent by the account's calls.
+    fn value(&self) -> Option<U256> {
+        self.calls()
+            .ok()?
+            .iter()
+            .try_fold(U256::zero(), |total, call| total.checked_add(call.value))
+    }
+}
+
+impl GetChainId<UserOperation> for UserOperationRequest {
+    fn chain_id(&self) -> Option<u64> {
+        Some(self.chain_id)
+    }
+}
+
+impl GetCalldata<UserOperation> for UserOperationRequest {
+    /// The calldata of the account's call. Not available for batches, which should be checked with
+    /// [`UserOperationRequest::calls`], or for calls to other account functions.
+    fn calldata(&self) -> Option<&[u8]> {
+        let call_data = self.user_operation.call_data();
+        match selector(call_data) {
+            // the inner calldata is borrowed from the account calldata, after its offset and length
+            Some(EXECUTE) => {
+                let offset: usize = U256::from_big_endian(call_data.get(68..100)?)
+                    .try_into()
+                    .ok()?;
+                let start = 4usize.checked_add(offset)?;
+                let end = start.checked_add(32)?;
+                let length: usize = U256::from_big_endian(call_data.get(start..end)?)
+                    .try_into()
+                    .ok()?;
+                call_data.get(end..end.checked_add(length)?)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl GetNonce<UserOperation> for UserOperationRequest {
+    /// The nonce, or `None` if it doesn't fit in a `u64` (ie. uses a non-zero nonce key).
+    fn nonce(&self) -> Option<u64> {
+        self.user_operation.nonce().try_into().ok()
+    }
+}
+
+impl GetFee<UserOperation> for UserOperationRequest {
+    /// The maximum gas fee, see [`UserOperationData::max_fee`].
+    fn fee(&self) -> Option<U256> {
+        self.user_operation.max_fee()
+    }
+
+    /// The max fee per gas.
+    fn gas_price(&self) -> Option<U256> {
+        match &self.user_operation {
+            UserOperationData::V06(op) => Some(op.max_fee_per_gas),
+            UserOperationData::V07(op) => Some(unpack(&op.gas_fees).1),
+        }
+    }
+}
+
+impl TryParse<UserOperation> for UserOperationRequest {
+    fn try_parse(raw_user_operation: &[u8]) -> Result<Self, CoreError> {
+        serde_json::from_slice(raw_user_operation).map_err(|e| {
+            CoreError::InvalidSignatureRequest(format!("Unable to parse user operation: {}", e))
+        })
+    }
+}
+
+/// Computes the userOpHash of the JSON user operation in a signature request's message, for use as a
+/// program's `custom_hash`.
+///
+/// Accounts that check signatures over the EIP-191 prefixed userOpHash (like the `SimpleAccount`) need
+/// `eip191::custom_hash` (with the `eip191` feature) applied to this.
+pub fn custom_hash(message: &[u8]) -> Option<Vec<u8>> {
+    let request = UserOperationRequest::try_parse(message).ok()?;
+    Some(request.user_op_hash().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::utils::id;
+
+    use super::*;
+
+    const ENTRY_POINT_V06: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+    const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+    fn word(value: impl Into<U256>) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        value.into().to_big_endian(&mut word);
+        word
+    }
+
+    fn address_word(address: H160) -> [u8; 32] {
+        H256::from(address).0
+    }
+
+    fn user_operation(entry_point: &str, call_data: Bytes, v07: bool) -> UserOperationRequest {
+        let sender = H160::repeat_byte(0xaa);
+        let user_operation = match v07 {
+            false => UserOperationData::V06(UserOperationV06 {
+                sender,
+                nonce: 3.into(),
+                call_data,
+                call_gas_limit: 100_000.into(),
+                verification_gas_limit: 50_000.into(),
+                pre_verification_gas: 21_000.into(),
+                max_fee_per_gas: 10.into(),
+                max_priority_fee_per_gas: 1.into(),
+                ..Default::default()
+            }),
+            true => UserOperationData::V07(PackedUserOperation {
+                sender,
+                nonce: 3.into(),
+                call_data,
+                account_gas_limits: word(U256::from(50_000) << 128 | U256::from(100_000)).into(),
+                pre_verification_gas: 21_000.into(),
+                gas_fees: word(U256::one() << 128 | U256::from(10)).into(),
+                ..Default::default()
+            }),
+        };
+        UserOperationRequest {
+            entry_point: entry_point.parse().unwrap(),
+            chain_id: 1,
+            user_operation,
+        }
+    }
+
+    fn execute(to: H160, value: u64, data: &[u8]) -> Bytes {
+        [
+            EXECUTE.as_slice(),
+            &encode(&[
+                Token::Address(to),
+                Token::Uint(value.into()),
+                Token::Bytes(data.to_vec()),
+            ]),
+        ]
+        .concat()
+        .into()
+    }
+
+    #[test]
+    fn selectors_match_account_functions() {
+        assert_eq!(EXECUTE, id("execute(address,uint256,bytes)"));
+        assert_eq!(EXECUTE_BATCH, id("executeBatch(address[],bytes[])"));
+        assert_eq!(
+            EXECUTE_BATCH_WITH_VALUE,
+            id("executeBatch(address[],uint256[],bytes[])")
+        );
+    }
+
+    #[test]
+    fn can_hash_user_operations() {
+        let call_data = execute(H160::repeat_byte(1), 5, &[0xde, 0xad]);
+        let empty_hash = keccak256([]);
+        // the packed user operations are the abi encoding of static types, ie. their 32 byte words
+        let packed_v06 = [
+            address_word(H160::repeat_byte(0xaa)),
+            word(3),
+            empty_hash,
+            keccak256(&call_data),
+            word(100_000),
+            word(50_000),
+            word(21_000),
+            word(10),
+            word(1),
+            empty_hash,
+        ]
+        .concat();
+        let packed_v07 = [
+            address_word(H160::repeat_byte(0xaa)),
+            word(3),
+            empty_hash,
+            keccak256(&call_data),
+            word(U256::from(50_000) << 128 | U256::from(100_000)),
+            word(21_000),
+            word(U256::one() << 128 | U256::from(10)),
+            empty_hash,
+        ]
+        .concat();
+
+        for (entry_point, packed, v07) in [
+            (ENTRY_POINT_V06, packed_v06, false),
+            (ENTRY_POINT_V07, packed_v07, true),
+        ] {
+            let request = user_operation(entry_point, call_data.clone(), v07);
+            let expected = keccak256(
+                [
+                    keccak256(packed),
+                    address_word(entry_point.parse().unwrap()),
+                    word(1),
+                ]
+                .concat(),
+            );
+            assert_eq!(request.user_op_hash(), expected);
+
+            // round trips through the JSON in signature requests
+            let json = serde_json::to_vec(&request).unwrap();
+            assert_eq!(
+                UserOperationRequest::try_parse(&json).unwrap(),
+                request.clone()
+            );
+            assert_eq!(custom_hash(&json), Some(expected.to_vec()));
+        }
+        assert_eq!(custom_hash(b"not a user operation"), None);
+    }
+
+    #[test]
+    fn can_parse_user_operation_json() {
+        let json = format!(
+            r#"{{
+                "entryPoint": "{}",
+                "chainId": 10,
+                "userOperation": {{
+                    "sender": "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                    "nonce": "0x1",
+                    "initCode": "0x",
+                    "callData": "0x",
+                    "accountGasLimits": "0x0000000000000000000000000000c350000000000000000000000000000186a0",
+                    "preVerificationGas": "0x5208",
+                    "gasFees": "0x000000000000000000000000000000010000000000000000000000000000000a",
+                    "paymasterAndData": "0x"
+                }}
+            }}"#,
+            ENTRY_POINT_V07
+        );
+        let request = UserOperationRequest::try_parse(json.as_bytes()).unwrap();
+        assert!(matches!(request.user_operation, UserOperationData::V07(_)));
+        assert_eq!(GetChainId::<UserOperation>::chain_id(&request), Some(10));
+        assert_eq!(GetNonce::<UserOperation>::nonce(&request), Some(1));
+        // (50000 + 100000 + 21000) gas at 10 wei
+        assert_eq!(
+            GetFee::<UserOperation>::fee(&request),
+            Some(U256::from(1_710_000))
+        );
+        assert_eq!(
+            GetFee::<UserOperation>::gas_price(&request),
+            Some(U256::from(10))
+        );
+
+        // missing fields
+        assert!(UserOperationRequest::try_parse(
+            format!(
+                r#"{{ "entryPoint": "{}", "chainId": 10 }}"#,
+                ENTRY_POINT_V07
+            )
+            .as_bytes()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn can_unwrap_account_calls() {
+        let target = H160::repeat_byte(1);
+        let other = H160::repeat_byte(2);
+
+        let request = user_operation(ENTRY_POINT_V06, execute(target, 5, &[0xde, 0xad]), false);
+        assert_eq!(
+            request.calls().unwrap(),
+            vec![Call {
+                to: target,
+                value: 5.into(),
+                data: vec![0xde, 0xad].into(),
+            }]
+        );
+        assert_eq!(
+            GetSender::<UserOperation>::sender(&request),
+            Some(H160::repeat_byte(0xaa))
+        );
+        assert_eq!(
+            GetReceiver::<UserOperation>::receiver(&request),
+            Some(target)
+        );
+        assert_eq!(GetValue::<UserOperation>::value(&request), Some(5.into()));
+        assert_eq!(
+            GetCalldata::<UserOperation>::calldata(&request),
+            Some([0xde, 0xad].as_slice())
+        );
+        // (50000 + 100000 + 21000) gas at 10 wei
+        assert_eq!(
+            GetFee::<UserOperation>::fee(&request),
+            Some(U256::from(1_710_000))
+        );
+
+        let batch: Bytes = [
+            EXECUTE_BATCH.as_slice(),
+            &encode(&[
+                Token::Array(vec![Token::Address(target), Token::Address(other)]),
+                Token::Array(vec![Token::Bytes(vec![1]), Token::Bytes(vec![2])]),
+            ]),
+        ]
+        .concat()
+        .into();
+        let request = user_operation(ENTRY_POINT_V06, batch, false);
+        assert_eq!(request.calls().unwrap().len(), 2);
+        assert_eq!(GetReceiver::<UserOperation>::receiver(&request), None);
+        assert_eq!(
+            GetReceiver::<UserOperation>::receivers(&request),
+            Some(vec![target, other])
+        );
+        assert_eq!(GetValue::<UserOperation>::value(&request), Some(0.into()));
+        assert_eq!(GetCalldata::<UserOperation>::calldata(&request), None);
+
+        let batch_with_value: Bytes = [
+            EXECUTE_BATCH_WITH_VALUE.as_slice(),
+            &encode(&[
+                Token::Array(vec![Token::Address(target), Token::Address(target)]),
+                Token::Array(vec![Token::Uint(1.into()), Token::Uint(2.into())]),
+                Token::Array(vec![Token::Bytes(vec![1]), Token::Bytes(vec![2])]),
+            ]),
+        ]
+        .concat()
+        .into();
+        let request = user_operation(ENTRY_POINT_V07, batch_with_value, true);
+        assert_eq!(
+            request.calls().unwrap()[1],
+            Call {
+                to: target,
+                value: 2.into(),
+                data: vec![2].into(),
+            }
+        );
+        assert_eq!(
+            GetReceiver::<UserOperation>::receiver(&request),
+            Some(target)
+        );
+        assert_eq!(GetValue::<UserOperation>::value(&request), Some(3.into()));
+
+        // mismatched batch lengths
+        let mismatched: Bytes = [
+            EXECUTE_BATCH.as_slice(),
+            &encode(&[
+                Token::Array(vec![Token::Address(target)]),
+                Token::Array(vec![]),
+            ]),
+        ]
+        .concat()
+        .into();
+        let request = user_operation(ENTRY_POINT_V06, mismatched, false);
+        assert!(request.calls().is_err());
+        assert_eq!(GetReceiver::<UserOperation>::receivers(&request), None);
+
+        // empty batches have no known receivers
+        let empty: Bytes = [
+            EXECUTE_BATCH.as_slice(),
+            &encode(&[Token::Array(vec![]), Token::Array(vec![])]),
+        ]
+        .concat()
+        .into();
+        let request = user_operation(ENTRY_POINT_V06, empty, false);
+        assert_eq!(GetReceiver::<UserOperation>::receivers(&request), None);
+
+        // offsets past the end of the calldata
+        let mut overflowing = execute(target, 0, &[]).to_vec();
+        overflowing[92..100].copy_from_slice(&(usize::MAX - 20).to_be_bytes());
+        let request = user_operation(ENTRY_POINT_V06, overflowing.into(), false);
+        assert_eq!(GetCalldata::<UserOperation>::calldata(&request), None);
+
+        // other account functions make calls that can't be known, eg. ERC-7579 `execute(bytes32,bytes)`
+        let erc7579_execute: Bytes = [
+            id("execute(bytes32,bytes)").as_slice(),
+            &encode(&[
+                Token::FixedBytes(vec![0; 32]),
+                Token::Bytes(
+                    [target.as_bytes(), &word(5), &[0xde, 0xad]]
+                        .concat()
+                        .to_vec(),
+                ),
+            ]),
+        ]
+        .concat()
+        .into();
+        for call_data in [
+            erc7579_execute,
+            vec![0x12, 0x34, 0x56, 0x78].into(),
+            Bytes::new(),
+        ] {
+            let request = user_operation(ENTRY_POINT_V06, call_data, false);
+            assert!(request.calls().is_err());
+            assert_eq!(GetReceiver::<UserOperation>::receivers(&request), None);
+            assert_eq!(GetValue::<UserOperation>::value(&request), None);
+            assert_eq!(GetCalldata::<UserOperation>::calldata(&request), None);
+        }
+
+        // unless they are configured as calls to the account itself
+        let request = user_operation(ENTRY_POINT_V06, vec![0x12, 0x34, 0x56, 0x78].into(), false);
+        assert_eq!(
+            request.calls_with(&[[0x12, 0x34, 0x56, 0x78]]).unwrap(),
+            vec![Call {
+                to: H160::repeat_byte(0xaa),
+                value: 0.into(),
+                data: vec![0x12, 0x34, 0x56, 0x78].into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn self_call_constraint_functions_properly() {
+        let account = H160::repeat_byte(0xaa);
+        let add_owner: Bytes = [
+            id("addOwnerAddress(address)").as_slice(),
+            &encode(&[Token::Address(H160::repeat_byte(3))]),
+        ]
+        .concat()
+        .into();
+        let receiver_is_account =
+            |request: &UserOperationRequest| match GetReceiver::<UserOperation>::receiver(request)
+                == Some(account)
+            {
+                true => Ok(()),
+                false => Err(CoreError::Evaluation("Not a self call.".into())),
+            };
+        #[derive(Clone)]
+        struct ReceiverIsAccount<F>(F);
+        impl<F: Fn(&UserOperationRequest) -> Result<(), CoreError>>
+            SatisfiableForArchitecture<UserOperation> for ReceiverIsAccount<F>
+        {
+            fn is_satisfied_by(self, request: &UserOperationRequest) -> Result<(), CoreError> {
+                (self.0)(request)
+            }
+        }
+
+        let config: SelfCallConstraint<()> = serde_json::from_str(&format!(
+            r#"{{ "constraint": null, "self_call_selectors": ["0x{}"] }}"#,
+            hex::encode(id("addOwnerAddress(address)"))
+        ))
+        .unwrap();
+        let constraint = SelfCallConstraint {
+            constraint: ReceiverIsAccount(receiver_is_account),
+            self_call_selectors: config.self_call_selectors,
+        };
+        let request = user_operation(ENTRY_POINT_V06, add_owner, false);
+        assert!(constraint.clone().is_satisfied_by(&request).is_ok());
+        // the user operation is evaluated as is otherwise
+        let request = user_operation(ENTRY_POINT_V06, vec![0x12, 0x34, 0x56, 0x78].into(), false);
+        assert!(constraint.clone().is_satisfied_by(&request).is_err());
+        let request = user_operation(ENTRY_POINT_V06, execute(account, 0, &[1]), false);
+        assert!(constraint.is_satisfied_by(&request).is_ok());
+    }
+}
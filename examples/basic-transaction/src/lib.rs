@@ -3,6 +3,7 @@
 extern crate alloc;
 
 use entropy_programs::{
+    arch::evm::address::parse_address,
     core::{bindgen::*, export_program, prelude::*, SatisfiableForArchitecture, TryParse},
     programs::acl::*,
 };
@@ -14,7 +15,6 @@ use alloc::{
 };
 
 use serde::{Deserialize, Serialize};
-use serde_json;
 
 pub struct BasicTransaction;
 
@@ -58,8 +58,8 @@ impl Program for BasicTransaction {
         let addresses: Vec<<Evm as Architecture>::AddressRaw> = typed_config
             .allowlisted_addresses
             .iter()
-            .map(|a| hex::decode(a).unwrap().try_into().unwrap())
-            .collect();
+            .map(|a| parse_address(a).map(Into::into))
+            .collect::<Result<_, _>>()?;
 
         let allowlisted_acl = Acl::<<Evm as Architecture>::AddressRaw> {
             addresses,
@@ -67,7 +67,7 @@ impl Program for BasicTransaction {
         };
 
         // check that the parsed tx is allowed by the ACL
-        allowlisted_acl.is_satisfied_by(&parsed_tx)?;
+        SatisfiableForArchitecture::<Evm>::is_satisfied_by(allowlisted_acl, &parsed_tx)?;
 
         Ok(())
     }
@@ -119,4 +119,19 @@ mod tests {
             BasicTransaction::evaluate(signature_request, Some(CONFIG.to_vec()), None).is_err()
         );
     }
+
+    #[test]
+    fn test_invalid_config_address() {
+        let signature_request = SignatureRequest {
+            message: EVM_TX_WITH_ALLOWLISTED_RECIPIENT.to_vec(),
+            auxilary_data: None,
+        };
+        // the allowlisted address with an invalid EIP-55 checksum
+        let config =
+            br#"{ "allowlisted_addresses": ["0x772B9A9E8aa1c9db861c6611a82d251db4fac990"] }"#;
+
+        assert!(
+            BasicTransaction::evaluate(signature_request, Some(config.to_vec()), None).is_err()
+        );
+    }
 }
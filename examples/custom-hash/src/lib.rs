@@ -9,8 +9,6 @@ use alloc::{string::ToString, vec::Vec};
 use entropy_programs_core::{bindgen::Error, bindgen::*, export_program, prelude::*};
 use serde::{Deserialize, Serialize};
 
-use blake3;
-
 // TODO confirm this isn't an issue for audit
 register_custom_getrandom!(always_fail);
 
@@ -32,7 +30,7 @@ impl Program for CustomHashExample {
         _config: Option<Vec<u8>>,
         _oracle_data: Option<Vec<Vec<u8>>>,
     ) -> Result<(), Error> {
-        if signature_request.message.len() < 1 {
+        if signature_request.message.is_empty() {
             return Err(Error::Evaluation(
                 "You need to give me SOME data to sign!".to_string(),
             ));
@@ -67,6 +67,7 @@ trait DeviceKey {
     fn pub_key_from_base64(public_key: &[u8]) -> Result<Self::PublicKey, Error>
     where
         Self: Sized;
+    #[allow(dead_code)]
     fn to_base64(&self) -> (String, String);
     // Checks that the public key is included in the config
     fn confirm_in_config(&self, config: &Config) -> Result<(), Error>;
@@ -363,26 +364,17 @@ impl From<Config> for UserConfig {
         let ecdsa_public_keys = config
             .ecdsa_public_keys
             .iter()
-            .map(|key| {
-                let encoded_key = BASE64_STANDARD.encode(key.to_encoded_point(true).as_bytes());
-                encoded_key
-            })
+            .map(|key| BASE64_STANDARD.encode(key.to_encoded_point(true).as_bytes()))
             .collect();
         let sr25519_public_keys = config
             .sr25519_public_keys
             .iter()
-            .map(|key| {
-                let encoded_key = BASE64_STANDARD.encode(key);
-                encoded_key
-            })
+            .map(|key| BASE64_STANDARD.encode(key))
             .collect();
         let ed25519_public_keys = config
             .ed25519_public_keys
             .iter()
-            .map(|key| {
-                let encoded_key = BASE64_STANDARD.encode(key.as_bytes());
-                encoded_key
-            })
+            .map(|key| BASE64_STANDARD.encode(key.as_bytes()))
             .collect();
 
         UserConfig {
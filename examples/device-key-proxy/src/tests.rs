@@ -19,7 +19,7 @@ fn test_ok_for_only_device_key_signatures() {
         ecdsa_public_keys: device_keys
             .ecdsa_keys
             .iter()
-            .map(|key| EcdsaPublicKey::from(key))
+            .map(EcdsaPublicKey::from)
             .collect(),
         sr25519_public_keys: device_keys
             .sr25519_keys
@@ -117,7 +117,7 @@ fn test_fail_bad_signatures() {
         ecdsa_public_keys: device_keys
             .ecdsa_keys
             .iter()
-            .map(|key| EcdsaPublicKey::from(key))
+            .map(EcdsaPublicKey::from)
             .collect(),
         sr25519_public_keys: device_keys
             .sr25519_keys
@@ -231,7 +231,7 @@ fn test_fails_pub_key_not_found() {
         ecdsa_public_keys: device_keys
             .ecdsa_keys
             .iter()
-            .map(|key| EcdsaPublicKey::from(key))
+            .map(EcdsaPublicKey::from)
             .collect(),
         sr25519_public_keys: device_keys
             .sr25519_keys
@@ -29,6 +29,7 @@ impl Program for InfiniteLoop {
         _config: Option<Vec<u8>>,
         _oracle_data: Option<Vec<Vec<u8>>>,
     ) -> Result<(), Error> {
+        #[allow(clippy::empty_loop)]
         loop {}
         #[allow(unreachable_code)]
         Ok(())
@@ -4,12 +4,8 @@
 
 extern crate alloc;
 
-use alloc::{
-    string::{String, ToString},
-    vec,
-    vec::Vec,
-};
-use codec::{Decode, Encode};
+use alloc::{string::ToString, vec::Vec};
+use codec::Decode;
 use entropy_programs_core::{bindgen::Error, bindgen::*, export_program, prelude::*};
 use serde::{Deserialize, Serialize};
 /// JSON-deserializable struct that will be used to derive the program-JSON interface.
@@ -0,0 +1,119 @@
+//! This example evaluates EVM transactions against a declarative policy given entirely in the program config,
+//! so new policies can be created without writing or recompiling any Rust.
+//!
+//! See `entropy-programs-policy` for the available rules.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use entropy_programs::{
+    core::{bindgen::*, export_program, prelude::*, Satisfiable},
+    programs::policy::{Evm, Policy},
+};
+
+use alloc::{format, string::ToString, vec::Vec};
+
+use serde::{Deserialize, Serialize};
+
+pub struct PolicyProgram;
+
+/// JSON representation of the user config, eg.
+/// `{ "rules": [{ "receiver": { "in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac990"] } }] }`.
+pub type UserConfig = Policy<Evm>;
+
+/// JSON representation of the auxiliary data
+#[cfg_attr(feature = "std", derive(schemars::JsonSchema))]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct AuxData {}
+
+// TODO confirm this isn't an issue for audit
+register_custom_getrandom!(always_fail);
+
+impl Program for PolicyProgram {
+    /// Parses the policy from the config and checks the RLP-serialized EVM transaction in the signature request
+    /// against it.
+    fn evaluate(
+        signature_request: SignatureRequest,
+        config: Option<Vec<u8>>,
+        _oracle_data: Option<Vec<Vec<u8>>>,
+    ) -> Result<(), Error> {
+        let policy = serde_json::from_slice::<UserConfig>(
+            config
+                .ok_or(Error::Evaluation("No config provided.".to_string()))?
+                .as_slice(),
+        )
+        .map_err(|e| Error::Evaluation(format!("Failed to parse policy: {}", e)))?;
+
+        policy.is_satisfied_by(signature_request.message.as_slice())
+    }
+
+    /// Since we don't use a custom hash function, we can just return `None` here.
+    fn custom_hash(_data: Vec<u8>) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+export_program!(PolicyProgram);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sends 1 wei on chain 1 to `0x772b9a9e8aa1c9db861c6611a82d251db4fac990`
+    const EVM_TX: &[u8] = b"0xef01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079018080";
+    const CONFIG: &[u8] = r#"
+        {
+            "rules": [
+                { "receiver": { "in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac990"] } },
+                { "value": { "max": "0xde0b6b3a7640000" } },
+                { "chain_id": { "in": [1] } }
+            ]
+        }
+    "#
+    .as_bytes();
+    const MAINNET_ONLY_TO_OTHER_RECIPIENT: &[u8] = r#"
+        {
+            "rules": [
+                { "receiver": { "in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac991"] } },
+                { "chain_id": { "in": [1] } }
+            ]
+        }
+    "#
+    .as_bytes();
+
+    #[test]
+    fn test_evaluate() {
+        let signature_request = SignatureRequest {
+            message: EVM_TX.to_vec(),
+            auxilary_data: None,
+        };
+
+        assert!(PolicyProgram::evaluate(signature_request, Some(CONFIG.to_vec()), None).is_ok());
+    }
+
+    #[test]
+    fn test_fails_when_rule_unsatisfied() {
+        let signature_request = SignatureRequest {
+            message: EVM_TX.to_vec(),
+            auxilary_data: None,
+        };
+
+        assert!(PolicyProgram::evaluate(
+            signature_request,
+            Some(MAINNET_ONLY_TO_OTHER_RECIPIENT.to_vec()),
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_fails_without_config() {
+        let signature_request = SignatureRequest {
+            message: EVM_TX.to_vec(),
+            auxilary_data: None,
+        };
+
+        assert!(PolicyProgram::evaluate(signature_request, None, None).is_err());
+    }
+}
@@ -1,24 +1,22 @@
-//! This is an allow list where the allowed addresses are hashed to improve privacy
+//! This is an allow list where the allowed addresses are salted and hashed to improve privacy.
 //! It is still possible for anyone to check whether a given address is in the list, using the
-//! on-chain bytecode. But you cannot just read the allowed addresses from it.
+//! on-chain config. But you cannot just read the allowed addresses from it.
+//!
+//! The config can be generated from a file of addresses (eg. `addresses.txt`) with:
+//! `cargo run -p entropy-programs-acl-cli -- private-config addresses.txt`
 #![no_std]
 
 extern crate alloc;
 
-use alloc::string::ToString;
-use alloc::vec::Vec;
-use blake2::{Blake2s256, Digest};
+use alloc::{format, string::ToString, vec::Vec};
 use entropy_programs::{
-    arch::evm::NameOrAddress,
-    core::{bindgen::*, export_program, prelude::*, TryParse},
+    core::{bindgen::*, export_program, prelude::*, SatisfiableForArchitecture, TryParse},
     programs::acl::*,
 };
 use serde::{Deserialize, Serialize};
 
-/// JSON-deserializable struct that will be used to derive the program-JSON interface.
-#[cfg_attr(feature = "std", derive(schemars::JsonSchema))]
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct UserConfig {}
+/// JSON-deserializable config, containing the salt and the hashed addresses.
+pub type UserConfig = PrivateAcl;
 
 /// JSON representation of the auxiliary data
 #[cfg_attr(feature = "std", derive(schemars::JsonSchema))]
@@ -27,17 +25,15 @@ pub struct AuxData {}
 
 pub struct PrivateTransactionAcl;
 
-include!(concat!(env!("OUT_DIR"), "/addresses.rs"));
-
 // TODO confirm this isn't an issue for audit
 register_custom_getrandom!(always_fail);
 
 impl Program for PrivateTransactionAcl {
-    /// Allow any address given in the pre-defined list (addresses.txt)
+    /// Allow any address whose salted hash is in the config
     // #[no_mangle]
     fn evaluate(
         signature_request: SignatureRequest,
-        _config: Option<Vec<u8>>,
+        config: Option<Vec<u8>>,
         _oracle_data: Option<Vec<Vec<u8>>>,
     ) -> Result<(), CoreError> {
         // parse the raw tx into some type
@@ -45,25 +41,14 @@ impl Program for PrivateTransactionAcl {
             signature_request.message.as_slice(),
         )?;
 
-        let name_or_address: NameOrAddress = parsed_tx.to.ok_or(Error::Evaluation(
-            "No recipient given in transaction".to_string(),
-        ))?;
-
-        match name_or_address {
-            NameOrAddress::Name(_) => Err(Error::Evaluation("ENS names not supported".to_string())),
-            NameOrAddress::Address(address) => {
-                let hashed_address = {
-                    let mut hasher = Blake2s256::new();
-                    hasher.update(&address.0);
-                    hasher.finalize().into()
-                };
-                if ADDRESSES.contains(&hashed_address) {
-                    Ok(())
-                } else {
-                    Err(Error::Evaluation("Address not in allow list".to_string()))
-                }
-            }
-        }
+        let acl = serde_json::from_slice::<UserConfig>(
+            config
+                .ok_or(Error::Evaluation("No config provided.".to_string()))?
+                .as_slice(),
+        )
+        .map_err(|e| Error::Evaluation(format!("Failed to parse config: {}", e)))?;
+
+        SatisfiableForArchitecture::<Evm>::is_satisfied_by(acl, &parsed_tx)
     }
 
     fn custom_hash(_data: Vec<u8>) -> Option<Vec<u8>> {
@@ -79,6 +64,20 @@ mod tests {
     use super::*;
     use alloc::string::ToString;
 
+    fn config() -> Vec<u8> {
+        let addresses: [[u8; 20]; 1] = [[
+            0x77, 0x2b, 0x9a, 0x9e, 0x8a, 0xa1, 0xc9, 0xdb, 0x86, 0x1c, 0x66, 0x11, 0xa8, 0x2d,
+            0x25, 0x1d, 0xb4, 0xfa, 0xc9, 0x90,
+        ]];
+        serde_json::to_vec(&PrivateAcl::new(
+            [42u8; 32],
+            addresses,
+            AclKind::Allow,
+            false,
+        ))
+        .unwrap()
+    }
+
     #[test]
     fn test_evaluate() {
         let signature_request = SignatureRequest {
@@ -87,7 +86,7 @@ mod tests {
             auxilary_data: None,
         };
 
-        assert!(PrivateTransactionAcl::evaluate(signature_request, None, None).is_ok());
+        assert!(PrivateTransactionAcl::evaluate(signature_request, Some(config()), None).is_ok());
     }
 
     #[test]
@@ -98,6 +97,6 @@ mod tests {
             auxilary_data: None,
         };
 
-        assert!(PrivateTransactionAcl::evaluate(signature_request, None, None).is_err());
+        assert!(PrivateTransactionAcl::evaluate(signature_request, Some(config()), None).is_err());
     }
 }
@@ -0,0 +1,228 @@
+//! Declarative policies that can be written entirely as configuration.
+//!
+//! A [`Policy`] is a list of [`Rule`]s that must all hold for a signature request to be signed. Rules are
+//! written over fields exposed by an architecture (sender, receiver, value, chain id, nonce, fee, calldata selector) and
+//! over the raw message itself, so new policies can be created by only writing JSON, eg.:
+//!
+//! ```json
+//! {
+//!     "rules": [
+//!         { "receiver": { "in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac990"] } },
+//!         { "value": { "max": "0xde0b6b3a7640000" } },
+//!         { "chain_id": { "in": [1] } }
+//!     ]
+//! }
+//! ```
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+pub use entropy_programs_core::{
+    Architecture, Error as CoreError, GetCalldata, GetChainId, GetFee, GetNonce, GetReceiver,
+    GetSender, GetValue, Satisfiable, TryParse,
+};
+
+#[cfg(feature = "evm")]
+pub use entropy_programs_evm::{Evm, H160, U256};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[cfg(all(test, feature = "evm"))]
+mod tests;
+
+/// A set of rules that must all be satisfied by a signature request. Policies without rules are rejected rather
+/// than signing anything.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Policy<A: PolicyArchitecture> {
+    #[serde(deserialize_with = "deserialize_rules")]
+    pub rules: Vec<Rule<A>>,
+}
+
+/// A single condition on a signature request.
+///
+/// If the field a rule is written over is missing from the transaction (eg. no `to` on a contract creation),
+/// the rule is not satisfied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "", rename_all = "snake_case")]
+pub enum Rule<A: PolicyArchitecture> {
+    /// Constrains the sender of the transaction.
+    #[serde(deserialize_with = "deserialize_addresses::<A, _>")]
+    Sender(Membership<A::Address>),
+    /// Constrains the receiver of the transaction, or every receiver of transactions with several.
+    #[serde(deserialize_with = "deserialize_addresses::<A, _>")]
+    Receiver(Membership<A::Address>),
+    /// Constrains the value transferred by the transaction. Bounds are inclusive.
+    Value(Bounds<A::Value>),
+    /// Constrains the chain id of the transaction.
+    ChainId(Membership<A::ChainId>),
+    /// Constrains the nonce of the transaction. Bounds are inclusive.
+    Nonce(Bounds<A::Nonce>),
+    /// Constrains the maximum fee the transaction can pay. Bounds are inclusive.
+    Fee(Bounds<A::Fee>),
+    /// Constrains the 4-byte function selector at the start of the calldata.
+    Selector(Membership<Selector>),
+    /// Constrains the length of the raw message in bytes. Bounds are inclusive.
+    MessageLength(Bounds<usize>),
+    /// Satisfied if any of the inner rules are satisfied. Must not be empty.
+    #[serde(deserialize_with = "deserialize_rules")]
+    AnyOf(Vec<Rule<A>>),
+    /// Satisfied if all of the inner rules are satisfied. Must not be empty.
+    #[serde(deserialize_with = "deserialize_rules")]
+    AllOf(Vec<Rule<A>>),
+}
+
+/// An architecture that policies can be written for.
+pub trait PolicyArchitecture: Architecture {
+    /// Parses an address in a policy config. Architectures should validate addresses as strictly as their
+    /// wallets do (eg. EIP-55 checksums), so that mistyped addresses are rejected rather than silently allowed.
+    fn parse_address(address: &str) -> Result<Self::Address, CoreError>;
+}
+
+#[cfg(feature = "evm")]
+impl PolicyArchitecture for Evm {
+    fn parse_address(address: &str) -> Result<H160, CoreError> {
+        entropy_programs_evm::address::parse_address(address)
+    }
+}
+
+/// Checks that a field is (or is not) one of the listed items.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Membership<T> {
+    In(Vec<T>),
+    NotIn(Vec<T>),
+}
+
+/// Checks that a field lies within inclusive bounds. Missing bounds are unbounded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bounds<T> {
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub min: Option<T>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub max: Option<T>,
+}
+
+/// A 4-byte function selector. (De)serialized as a hex string, eg. `"0xa9059cbb"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selector(#[serde(with = "entropy_programs_core::config::selector_hex")] pub [u8; 4]);
+
+impl<T: PartialEq> Membership<T> {
+    fn contains(&self, item: Option<&T>) -> bool {
+        match (self, item) {
+            (Membership::In(items), Some(item)) => items.contains(item),
+            (Membership::NotIn(items), Some(item)) => !items.contains(item),
+            (_, None) => false,
+        }
+    }
+}
+
+/// Deserializes a membership rule over addresses written as strings, parsing them with
+/// [`PolicyArchitecture::parse_address`].
+fn deserialize_addresses<'de, A: PolicyArchitecture, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Membership<A::Address>, D::Error> {
+    let parse = |addresses: Vec<String>| {
+        addresses
+            .iter()
+            .map(|address| A::parse_address(address))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(serde::de::Error::custom)
+    };
+    match Membership::<String>::deserialize(deserializer)? {
+        Membership::In(addresses) => parse(addresses).map(Membership::In),
+        Membership::NotIn(addresses) => parse(addresses).map(Membership::NotIn),
+    }
+}
+
+/// Deserializes a list of rules, rejecting empty lists, which would otherwise be trivially satisfied.
+fn deserialize_rules<'de, A: PolicyArchitecture, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Rule<A>>, D::Error> {
+    let rules = Vec::<Rule<A>>::deserialize(deserializer)?;
+    match rules.is_empty() {
+        true => Err(serde::de::Error::custom("rules must not be empty")),
+        false => Ok(rules),
+    }
+}
+
+impl<T: PartialOrd> Bounds<T> {
+    fn contains(&self, item: Option<&T>) -> bool {
+        let Some(item) = item else {
+            return false;
+        };
+        self.min.as_ref().is_none_or(|min| item >= min)
+            && self.max.as_ref().is_none_or(|max| item <= max)
+    }
+}
+
+impl<A: PolicyArchitecture> Rule<A> {
+    /// Checks the rule against the raw message and its parsed transaction request.
+    pub fn check(&self, message: &[u8], tx: &A::TransactionRequest) -> Result<(), CoreError> {
+        let (satisfied, field) = match self {
+            Rule::Sender(membership) => (membership.contains(tx.sender().as_ref()), "sender"),
+            Rule::Receiver(membership) => {
+                let satisfied = tx.receivers().is_some_and(|receivers| {
+                    !receivers.is_empty()
+                        && receivers
+                            .iter()
+                            .all(|receiver| membership.contains(Some(receiver)))
+                });
+                (satisfied, "receiver")
+            }
+            Rule::Value(bounds) => (bounds.contains(tx.value().as_ref()), "value"),
+            Rule::ChainId(membership) => (membership.contains(tx.chain_id().as_ref()), "chain_id"),
+            Rule::Nonce(bounds) => (bounds.contains(tx.nonce().as_ref()), "nonce"),
+            Rule::Fee(bounds) => (bounds.contains(tx.fee().as_ref()), "fee"),
+            Rule::Selector(membership) => {
+                let selector = tx
+                    .calldata()
+                    .and_then(|data| data.get(..4))
+                    .map(|s| Selector(s.try_into().expect("slice has length 4")));
+                (membership.contains(selector.as_ref()), "selector")
+            }
+            Rule::MessageLength(bounds) => {
+                (bounds.contains(Some(&message.len())), "message_length")
+            }
+            Rule::AnyOf(rules) => (
+                rules.iter().any(|rule| rule.check(message, tx).is_ok()),
+                "any_of",
+            ),
+            Rule::AllOf(rules) => {
+                return check_all(rules, message, tx);
+            }
+        };
+
+        match satisfied {
+            true => Ok(()),
+            false => Err(CoreError::Evaluation(format!(
+                "Policy rule on `{}` not satisfied.",
+                field
+            ))),
+        }
+    }
+}
+
+impl<A: PolicyArchitecture> Satisfiable for Policy<A> {
+    /// Parses the message as a transaction request of architecture `A` and checks every rule against it.
+    fn is_satisfied_by(self, message: &[u8]) -> Result<(), CoreError> {
+        let tx = <A::TransactionRequest as TryParse<A>>::try_parse(message)?;
+        check_all(&self.rules, message, &tx)
+    }
+}
+
+/// Checks that all of the rules are satisfied, failing closed if there are none.
+fn check_all<A: PolicyArchitecture>(
+    rules: &[Rule<A>],
+    message: &[u8],
+    tx: &A::TransactionRequest,
+) -> Result<(), CoreError> {
+    if rules.is_empty() {
+        return Err(CoreError::Evaluation(
+            "Policies without rules are not allowed.".into(),
+        ));
+    }
+    rules.iter().try_for_each(|rule| rule.check(message, tx))
+}
@@ -0,0 +1,149 @@
+use alloc::vec;
+
+use super::*;
+
+/// RLP-serialized legacy transaction to `0x772b9a9e8aa1c9db861c6611a82d251db4fac990` with a value of 1 wei on
+/// chain 1, nonce 1, a gas price of 0 and "Created On Entropy" as calldata.
+const EVM_TX: &[u8] = b"0xef01808094772b9a9e8aa1c9db861c6611a82d251db4fac990019243726561746564204f6e20456e74726f7079018080";
+
+fn policy(json: &str) -> Policy<Evm> {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn test_policy_parses_from_json() {
+    let parsed = policy(
+        r#"{
+            "rules": [
+                { "receiver": { "in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac990"] } },
+                { "value": { "max": "0xde0b6b3a7640000" } },
+                { "chain_id": { "in": [1, 5] } },
+                { "selector": { "not_in": ["0xa9059cbb"] } },
+                { "message_length": { "min": 1, "max": 1024 } }
+            ]
+        }"#,
+    );
+
+    assert_eq!(
+        parsed.rules,
+        vec![
+            Rule::Receiver(Membership::In(vec![H160::from_slice(
+                &hex::decode("772b9a9e8aa1c9db861c6611a82d251db4fac990").unwrap()
+            )])),
+            Rule::Value(Bounds {
+                min: None,
+                max: Some(U256::exp10(18)),
+            }),
+            Rule::ChainId(Membership::In(vec![1, 5])),
+            Rule::Selector(Membership::NotIn(vec![Selector([0xa9, 0x05, 0x9c, 0xbb])])),
+            Rule::MessageLength(Bounds {
+                min: Some(1),
+                max: Some(1024),
+            }),
+        ]
+    );
+}
+
+#[test]
+fn test_policy_rules_over_tx_fields() {
+    let satisfied = [
+        r#"{ "rules": [{ "receiver": { "in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac990"] } }] }"#,
+        r#"{ "rules": [{ "receiver": { "not_in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac991"] } }] }"#,
+        r#"{ "rules": [{ "value": { "min": "0x1", "max": "0x1" } }] }"#,
+        r#"{ "rules": [{ "chain_id": { "in": [1] } }] }"#,
+        r#"{ "rules": [{ "nonce": { "min": 1, "max": 1 } }] }"#,
+        r#"{ "rules": [{ "fee": { "max": "0x0" } }] }"#,
+        r#"{ "rules": [{ "selector": { "in": ["0x43726561"] } }] }"#,
+        r#"{ "rules": [{ "message_length": { "max": 100 } }] }"#,
+    ];
+    for json in satisfied {
+        assert!(policy(json).is_satisfied_by(EVM_TX).is_ok(), "{}", json);
+    }
+
+    let unsatisfied = [
+        r#"{ "rules": [{ "receiver": { "in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac991"] } }] }"#,
+        r#"{ "rules": [{ "receiver": { "not_in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac990"] } }] }"#,
+        r#"{ "rules": [{ "value": { "min": "0x2" } }] }"#,
+        r#"{ "rules": [{ "chain_id": { "in": [5] } }] }"#,
+        r#"{ "rules": [{ "nonce": { "min": 2 } }] }"#,
+        r#"{ "rules": [{ "fee": { "min": "0x1" } }] }"#,
+        r#"{ "rules": [{ "selector": { "not_in": ["0x43726561"] } }] }"#,
+        r#"{ "rules": [{ "message_length": { "max": 10 } }] }"#,
+        // the sender is never part of an unsigned RLP transaction, so sender rules fail closed
+        r#"{ "rules": [{ "sender": { "not_in": [] } }] }"#,
+    ];
+    for json in unsatisfied {
+        assert!(policy(json).is_satisfied_by(EVM_TX).is_err(), "{}", json);
+    }
+}
+
+#[test]
+fn test_policy_combinators() {
+    let any_of = policy(
+        r#"{ "rules": [{ "any_of": [
+            { "chain_id": { "in": [5] } },
+            { "receiver": { "in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac990"] } }
+        ] }] }"#,
+    );
+    assert!(any_of.is_satisfied_by(EVM_TX).is_ok());
+
+    let all_of = policy(
+        r#"{ "rules": [{ "any_of": [
+            { "all_of": [{ "chain_id": { "in": [1] } }, { "value": { "min": "0x2" } }] },
+            { "all_of": [{ "chain_id": { "in": [5] } }, { "value": { "max": "0x1" } }] }
+        ] }] }"#,
+    );
+    assert!(all_of.is_satisfied_by(EVM_TX).is_err());
+}
+
+#[test]
+fn test_policy_rejects_empty_rules() {
+    let empty = [
+        r#"{ "rules": [] }"#,
+        r#"{ "rules": [{ "all_of": [] }] }"#,
+        r#"{ "rules": [{ "any_of": [] }] }"#,
+        r#"{ "rules": [{ "any_of": [{ "all_of": [] }, { "chain_id": { "in": [1] } }] }] }"#,
+    ];
+    for json in empty {
+        assert!(
+            serde_json::from_str::<Policy<Evm>>(json).is_err(),
+            "{}",
+            json
+        );
+    }
+
+    // policies built in code fail closed instead
+    let policy = Policy::<Evm> {
+        rules: vec![Rule::AllOf(vec![])],
+    };
+    assert!(policy.is_satisfied_by(EVM_TX).is_err());
+    assert!(Policy::<Evm> { rules: vec![] }
+        .is_satisfied_by(EVM_TX)
+        .is_err());
+}
+
+#[test]
+fn test_policy_rejects_unparseable_messages() {
+    let policy = policy(r#"{ "rules": [{ "message_length": { "min": 1 } }] }"#);
+    assert!(policy.is_satisfied_by(b"not a transaction").is_err());
+}
+
+#[test]
+fn test_policy_addresses_are_checksummed() {
+    let checksummed = r#"{ "rules": [{ "receiver": { "in": ["0x772b9a9E8Aa1c9dB861c6611A82d251dB4FaC990"] } }] }"#;
+    assert!(policy(checksummed).is_satisfied_by(EVM_TX).is_ok());
+
+    let invalid = [
+        // a single mistyped case
+        r#"{ "rules": [{ "receiver": { "in": ["0x772b9a9e8Aa1c9dB861c6611A82d251dB4FaC990"] } }] }"#,
+        r#"{ "rules": [{ "sender": { "not_in": ["0x772b9a9e8Aa1c9dB861c6611A82d251dB4FaC990"] } }] }"#,
+        r#"{ "rules": [{ "receiver": { "in": ["0x772b9a9e8aa1c9db861c6611a82d251db4fac9"] } }] }"#,
+    ];
+    for json in invalid {
+        assert!(
+            serde_json::from_str::<Policy<Evm>>(json).is_err(),
+            "{}",
+            json
+        );
+    }
+}
@@ -8,10 +8,17 @@ pub use entropy_programs_core as core;
 /// All architecture-agnostic programs should be re-exported from this module
 pub mod programs {
     pub use entropy_programs_acl as acl;
+    pub use entropy_programs_policy as policy;
 }
 /// All architectures that implement the `ec_core::Architecture` trait should be re-exported from here.
 pub mod arch {
+    #[cfg(feature = "btc")]
+    pub use entropy_programs_btc as btc;
     pub use entropy_programs_evm as evm;
+    #[cfg(feature = "solana")]
+    pub use entropy_programs_solana as solana;
+    #[cfg(feature = "substrate")]
+    pub use entropy_programs_substrate as substrate;
 }
 
 /// Dynamic parsing allows for easily hooking transactions into
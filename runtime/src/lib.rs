@@ -87,7 +87,7 @@ impl Runtime {
         config: Option<&[u8]>,
         oracle_data: Option<&[Vec<u8>]>,
     ) -> Result<(), RuntimeError> {
-        if program.len() == 0 {
+        if program.is_empty() {
             return Err(RuntimeError::EmptyBytecode);
         }
 
@@ -108,7 +108,7 @@ impl Runtime {
         program: &[u8],
         message: &[u8],
     ) -> Result<[u8; 32], RuntimeError> {
-        if program.len() == 0 {
+        if program.is_empty() {
             return Err(RuntimeError::EmptyBytecode);
         }
 
@@ -7,7 +7,6 @@ const CUSTOM_HASH_COMPONENT_WASM: &[u8] =
 const INFINITE_LOOP_WASM: &[u8] =
     include_bytes!("../../target/wasm32-unknown-unknown/release/infinite_loop.wasm");
 
-use blake3;
 use entropy_programs_runtime::{Runtime, SignatureRequest};
 
 #[test]
@@ -0,0 +1,357 @@
+//! Offline simulation of EVM transactions, so that constraints can be written over a transaction's effects
+//! rather than its fields, eg. "the balance of X must not decrease by more than Y".
+//!
+//! Transactions are executed on an embedded EVM against a [`StateSnapshot`] of the accounts they touch, which is
+//! passed to the program as auxiliary or oracle data. [`simulate`] reports the native balance changes and ERC-20
+//! `Transfer`s of the transaction, which [`EffectConstraint`] can check.
+//!
+//! A simulation is only as trustworthy as its snapshot. Auxiliary data is supplied by whoever makes the
+//! signature request, who can pass a snapshot (and sender) under which any transaction looks harmless, eg. one
+//! where the drained account already holds nothing. Snapshots must therefore come from a trusted oracle, or be
+//! verified by the program before simulating, eg. with Merkle proofs of the accounts and storage against a
+//! trusted state root.
+//!
+//! This crate requires `std`, so is not included in `entropy-programs`.
+
+use std::collections::BTreeMap;
+
+use entropy_programs_core::{
+    Error as CoreError, GetCalldata, GetChainId, GetReceiver, GetSender, GetValue,
+};
+use entropy_programs_evm::address;
+use entropy_programs_evm::{Evm, EvmTransactionRequest};
+use ethers_core::types::{Bytes, H160, H256, I256, U256};
+use revm::db::InMemoryDB;
+use revm::primitives::{
+    AccountInfo, Address as RevmAddress, Bytecode, ExecutionResult, TxKind, B256, U256 as RevmU256,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod tests;
+
+/// `keccak256("Transfer(address,address,uint256)")`, the topic of ERC-20 (and ERC-721) transfer events.
+pub const TRANSFER_TOPIC: H256 = H256([
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+]);
+
+/// The state a transaction is simulated against. Accounts that aren't in the snapshot are empty.
+///
+/// Nothing in the snapshot is verified: it must come from a trusted oracle, or be checked against a trusted
+/// state root, rather than be taken from the requester's auxiliary data as is (see the [crate] docs).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// The block the transaction is simulated in.
+    #[serde(default)]
+    pub block: BlockSnapshot,
+    /// The account the transaction is sent from, if it isn't set in the transaction. Unsigned transactions
+    /// usually don't say who signs them. Like the rest of the snapshot this is trusted, so a requester who can
+    /// choose it can simulate the transaction from an account other than the one that will sign it.
+    #[serde(default, with = "address::checksummed_option")]
+    pub sender: Option<H160>,
+    #[serde(default)]
+    pub accounts: BTreeMap<H160, AccountSnapshot>,
+}
+
+/// The block a transaction is simulated in.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSnapshot {
+    #[serde(default)]
+    pub number: U256,
+    #[serde(default)]
+    pub timestamp: U256,
+    #[serde(default)]
+    pub base_fee: U256,
+    /// `None` uses 30 million gas.
+    #[serde(default)]
+    pub gas_limit: Option<U256>,
+}
+
+/// The state of an account.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    #[serde(default)]
+    pub balance: U256,
+    #[serde(default)]
+    pub nonce: u64,
+    #[serde(default)]
+    pub code: Bytes,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+impl StateSnapshot {
+    /// Parses a JSON state snapshot, eg. from oracle data. Parsing doesn't verify the snapshot.
+    pub fn try_parse(raw_state: &[u8]) -> Result<Self, CoreError> {
+        serde_json::from_slice(raw_state)
+            .map_err(|e| CoreError::Evaluation(format!("Unable to parse state snapshot: {}", e)))
+    }
+
+    /// The balance of an account before the transaction.
+    pub fn balance(&self, account: &H160) -> U256 {
+        self.accounts
+            .get(account)
+            .map(|account| account.balance)
+            .unwrap_or_default()
+    }
+}
+
+/// An ERC-20 `Transfer` event emitted by a simulated transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenTransfer {
+    pub token: H160,
+    pub from: H160,
+    pub to: H160,
+    pub amount: U256,
+}
+
+/// The effects of a simulated transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Simulation {
+    /// Whether the transaction succeeded, rather than reverting or halting. Reverted transactions still pay
+    /// for their gas.
+    pub success: bool,
+    pub gas_used: u64,
+    /// The change of the native balance of every account whose balance changed, including the gas paid by
+    /// the sender.
+    pub balance_deltas: BTreeMap<H160, I256>,
+    /// ERC-20 transfers, in the order they were emitted.
+    pub token_transfers: Vec<TokenTransfer>,
+}
+
+impl Simulation {
+    /// The change of an account's native balance.
+    pub fn balance_delta(&self, account: &H160) -> I256 {
+        self.balance_deltas
+            .get(account)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The net change of an account's balance of a token, from the token's transfers.
+    pub fn token_delta(&self, token: &H160, account: &H160) -> Result<I256, CoreError> {
+        self.token_transfers
+            .iter()
+            .filter(|transfer| transfer.token == *token && transfer.from != transfer.to)
+            .try_fold(I256::zero(), |delta, transfer| {
+                let amount = signed(transfer.amount)?;
+                let delta = match (transfer.from == *account, transfer.to == *account) {
+                    (true, _) => delta.checked_sub(amount),
+                    (_, true) => delta.checked_add(amount),
+                    _ => Some(delta),
+                };
+                delta.ok_or(CoreError::Evaluation(
+                    "Token balance change overflows.".to_string(),
+                ))
+            })
+    }
+}
+
+fn signed(amount: U256) -> Result<I256, CoreError> {
+    I256::try_from(amount)
+        .map_err(|_| CoreError::Evaluation(format!("Amount {} is too large to simulate.", amount)))
+}
+
+fn revm_address(address: H160) -> RevmAddress {
+    RevmAddress::from(address.0)
+}
+
+fn revm_u256(value: U256) -> RevmU256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    RevmU256::from_be_bytes(bytes)
+}
+
+fn u256(value: RevmU256) -> U256 {
+    U256::from_big_endian(&value.to_be_bytes::<32>())
+}
+
+/// Executes a transaction against a state snapshot, and reports its effects. Errors if the transaction can't
+/// be executed at all, eg. if the sender can't pay for its gas.
+pub fn simulate(
+    tx: &EvmTransactionRequest,
+    state: &StateSnapshot,
+) -> Result<Simulation, CoreError> {
+    let sender = GetSender::<Evm>::sender(tx)
+        .or(state.sender)
+        .ok_or(CoreError::Evaluation(
+            "Transaction has no sender to simulate it from.".to_string(),
+        ))?;
+
+    let mut db = InMemoryDB::default();
+    for (address, account) in &state.accounts {
+        db.insert_account_info(
+            revm_address(*address),
+            AccountInfo {
+                balance: revm_u256(account.balance),
+                nonce: account.nonce,
+                code: Some(Bytecode::new_raw(account.code.0.clone().into())),
+                ..Default::default()
+            },
+        );
+        for (slot, value) in &account.storage {
+            db.insert_account_storage(revm_address(*address), revm_u256(*slot), revm_u256(*value))
+                .expect("the in-memory database doesn't error");
+        }
+    }
+
+    let block_gas_limit = state.block.gas_limit.unwrap_or(30_000_000.into());
+    let gas_limit = tx.gas().unwrap_or(block_gas_limit);
+    let mut evm = revm::Evm::builder()
+        .with_db(db)
+        .modify_cfg_env(|cfg| {
+            if let Some(chain_id) = GetChainId::<Evm>::chain_id(tx) {
+                cfg.chain_id = chain_id;
+            }
+        })
+        .modify_block_env(|block| {
+            block.number = revm_u256(state.block.number);
+            block.timestamp = revm_u256(state.block.timestamp);
+            block.basefee = revm_u256(state.block.base_fee);
+            block.gas_limit = revm_u256(block_gas_limit);
+        })
+        .modify_tx_env(|env| {
+            env.caller = revm_address(sender);
+            env.transact_to = match GetReceiver::<Evm>::receiver(tx) {
+                Some(to) => TxKind::Call(revm_address(to)),
+                None => TxKind::Create,
+            };
+            env.value = revm_u256(GetValue::<Evm>::value(tx).unwrap_or_default());
+            env.data = GetCalldata::<Evm>::calldata(tx)
+                .unwrap_or_default()
+                .to_vec()
+                .into();
+            env.gas_limit = gas_limit.try_into().unwrap_or(u64::MAX);
+            env.gas_price = revm_u256(tx.max_fee_per_gas().unwrap_or_default());
+            env.gas_priority_fee = tx.max_priority_fee_per_gas().map(revm_u256);
+            // the snapshot may not have the sender's current nonce, which doesn't change the effects
+            env.nonce = None;
+            env.chain_id = GetChainId::<Evm>::chain_id(tx);
+            env.access_list = tx
+                .access_list()
+                .map(|access_list| {
+                    access_list
+                        .0
+                        .iter()
+                        .map(|item| {
+                            let keys = item
+                                .storage_keys
+                                .iter()
+                                .map(|key| RevmU256::from_be_bytes(key.0))
+                                .collect();
+                            (revm_address(item.address), keys)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let EvmTransactionRequest::Eip4844(blob_tx) = tx {
+                env.blob_hashes = blob_tx
+                    .blob_versioned_hashes
+                    .iter()
+                    .map(|hash| B256::from(hash.0))
+                    .collect();
+                env.max_fee_per_blob_gas = Some(revm_u256(blob_tx.max_fee_per_blob_gas));
+            }
+        })
+        .build();
+    let result = evm
+        .transact()
+        .map_err(|e| CoreError::Evaluation(format!("Unable to simulate transaction: {:?}", e)))?;
+
+    let mut balance_deltas = BTreeMap::new();
+    for (address, account) in &result.state {
+        let address = H160(address.0 .0);
+        let before = signed(state.balance(&address))?;
+        let after = signed(u256(account.info.balance))?;
+        if before != after {
+            balance_deltas.insert(address, after - before);
+        }
+    }
+
+    let token_transfers = result
+        .result
+        .logs()
+        .iter()
+        .filter_map(|log| {
+            let topics = log.data.topics();
+            // ERC-721 transfers index the token id, so have 4 topics and no data
+            if topics.len() != 3 || topics[0].0 != TRANSFER_TOPIC.0 || log.data.data.len() != 32 {
+                return None;
+            }
+            Some(TokenTransfer {
+                token: H160(log.address.0 .0),
+                from: H160::from(H256(topics[1].0)),
+                to: H160::from(H256(topics[2].0)),
+                amount: U256::from_big_endian(&log.data.data),
+            })
+        })
+        .collect();
+
+    Ok(Simulation {
+        success: matches!(result.result, ExecutionResult::Success { .. }),
+        gas_used: result.result.gas_used(),
+        balance_deltas,
+        token_transfers,
+    })
+}
+
+/// Constrains the effects of a transaction, by simulating it against the state snapshot from the auxiliary or
+/// oracle data. The constraint is only meaningful if the snapshot is trusted, see [`StateSnapshot`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectConstraint {
+    /// Maximum decreases of native or token balances.
+    #[serde(default)]
+    pub max_decreases: Vec<MaxDecrease>,
+    /// Whether transactions that revert are allowed.
+    #[serde(default)]
+    pub allow_revert: bool,
+}
+
+/// The most an account's balance can decrease by.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaxDecrease {
+    #[serde(with = "address::checksummed")]
+    pub account: H160,
+    /// The token, or `None` for the native balance (which includes the gas paid by the sender).
+    #[serde(default, with = "address::checksummed_option")]
+    pub token: Option<H160>,
+    pub amount: U256,
+}
+
+impl EffectConstraint {
+    /// Simulates `tx` against `state` and checks its effects, see [`simulate`].
+    pub fn is_satisfied_by(
+        self,
+        tx: &EvmTransactionRequest,
+        state: &StateSnapshot,
+    ) -> Result<(), CoreError> {
+        self.check(&simulate(tx, state)?)
+    }
+
+    /// Checks the effects of an already simulated transaction.
+    pub fn check(&self, simulation: &Simulation) -> Result<(), CoreError> {
+        if !simulation.success && !self.allow_revert {
+            return Err(CoreError::Evaluation(
+                "Transactions that revert are not allowed.".to_string(),
+            ));
+        }
+
+        for max_decrease in &self.max_decreases {
+            let delta = match &max_decrease.token {
+                Some(token) => simulation.token_delta(token, &max_decrease.account)?,
+                None => simulation.balance_delta(&max_decrease.account),
+            };
+            if delta < -signed(max_decrease.amount)? {
+                return Err(CoreError::Evaluation(format!(
+                    "Balance of {:?} decreases by {}, more than the maximum of {}.",
+                    max_decrease.account,
+                    delta.unsigned_abs(),
+                    max_decrease.amount
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use ethers_core::abi::{encode, Token};
+use ethers_core::types::{H160, I256, U256};
+
+use super::*;
+use entropy_programs_evm::token::TRANSFER;
+use entropy_programs_evm::{Eip1559TransactionRequest, LegacyTransactionRequest};
+
+const STATE: &str = include_str!("../fixtures/state.json");
+
+const SENDER: H160 = H160::repeat_byte(0xa1);
+const RECIPIENT: H160 = H160::repeat_byte(0xb0);
+/// Emits a `Transfer` from the caller for any `transfer(address,uint256)` call
+const TOKEN: H160 = H160::repeat_byte(0x70);
+/// Always reverts
+const REVERTER: H160 = H160::repeat_byte(0x0e);
+
+/// 1 gwei, the base fee of the fixture
+const GAS_PRICE: u64 = 1_000_000_000;
+const ETHER: u64 = 1_000_000_000_000_000_000;
+
+fn state() -> StateSnapshot {
+    StateSnapshot::try_parse(STATE.as_bytes()).unwrap()
+}
+
+fn transfer(to: H160, amount: u64) -> EvmTransactionRequest {
+    Eip1559TransactionRequest::new()
+        .to(TOKEN)
+        .chain_id(1)
+        .gas(100_000)
+        .max_fee_per_gas(GAS_PRICE)
+        .max_priority_fee_per_gas(0)
+        .data(
+            [
+                TRANSFER.as_slice(),
+                &encode(&[Token::Address(to), Token::Uint(amount.into())]),
+            ]
+            .concat(),
+        )
+        .into()
+}
+
+fn send(value: u64) -> EvmTransactionRequest {
+    LegacyTransactionRequest::new()
+        .to(RECIPIENT)
+        .value(value)
+        .gas(21_000)
+        .gas_price(GAS_PRICE)
+        .into()
+}
+
+#[test]
+fn test_can_parse_state_fixture() {
+    let state = state();
+    assert_eq!(state.sender, Some(SENDER));
+    assert_eq!(state.block.base_fee, U256::from(GAS_PRICE));
+    assert_eq!(state.block.gas_limit, None);
+    assert_eq!(state.balance(&SENDER), U256::from(ETHER));
+    assert_eq!(state.balance(&RECIPIENT), U256::zero());
+    assert_eq!(state.accounts[&SENDER].nonce, 5);
+    assert!(!state.accounts[&TOKEN].code.is_empty());
+
+    assert!(StateSnapshot::try_parse(b"{ \"accounts\": [] }").is_err());
+}
+
+#[test]
+fn test_can_simulate_native_transfers() {
+    let simulation = simulate(&send(ETHER / 2), &state()).unwrap();
+    assert!(simulation.success);
+    assert_eq!(simulation.gas_used, 21_000);
+    // the sender also pays for gas
+    let fee = 21_000 * GAS_PRICE;
+    assert_eq!(
+        simulation.balance_delta(&SENDER),
+        -I256::from(ETHER / 2 + fee)
+    );
+    assert_eq!(simulation.balance_delta(&RECIPIENT), I256::from(ETHER / 2));
+    assert_eq!(simulation.balance_delta(&TOKEN), I256::zero());
+    assert!(simulation.token_transfers.is_empty());
+
+    // more than the sender has
+    assert!(simulate(&send(ETHER), &state()).is_err());
+}
+
+#[test]
+fn test_can_simulate_token_transfers() {
+    let simulation = simulate(&transfer(RECIPIENT, 1000), &state()).unwrap();
+    assert!(simulation.success);
+    assert_eq!(
+        simulation.token_transfers,
+        vec![TokenTransfer {
+            token: TOKEN,
+            from: SENDER,
+            to: RECIPIENT,
+            amount: 1000.into(),
+        }]
+    );
+    assert_eq!(
+        simulation.token_delta(&TOKEN, &SENDER).unwrap(),
+        I256::from(-1000)
+    );
+    assert_eq!(
+        simulation.token_delta(&TOKEN, &RECIPIENT).unwrap(),
+        I256::from(1000)
+    );
+    assert_eq!(
+        simulation.token_delta(&RECIPIENT, &SENDER).unwrap(),
+        I256::zero()
+    );
+    assert!(simulation.balance_delta(&SENDER) < I256::zero());
+}
+
+#[test]
+fn test_can_simulate_reverts_and_requires_a_sender() {
+    let revert: EvmTransactionRequest = LegacyTransactionRequest::new()
+        .to(REVERTER)
+        .gas(50_000)
+        .gas_price(GAS_PRICE)
+        .into();
+    let simulation = simulate(&revert, &state()).unwrap();
+    assert!(!simulation.success);
+    assert!(simulation.balance_delta(&SENDER) < I256::zero());
+
+    let without_sender = StateSnapshot {
+        sender: None,
+        ..state()
+    };
+    assert!(simulate(&send(1), &without_sender).is_err());
+    // unless the transaction has one
+    let from_sender: EvmTransactionRequest = LegacyTransactionRequest::new()
+        .from(SENDER)
+        .to(RECIPIENT)
+        .value(1)
+        .gas(21_000)
+        .gas_price(GAS_PRICE)
+        .into();
+    assert!(simulate(&from_sender, &without_sender).is_ok());
+}
+
+#[test]
+fn test_effect_constraint_functions_properly() {
+    let constraint: EffectConstraint = serde_json::from_str(&format!(
+        r#"{{
+            "max_decreases": [
+                {{ "account": "{:?}", "amount": "0x16345785d8a0000" }},
+                {{ "account": "{:?}", "token": "{:?}", "amount": "0x3e8" }}
+            ]
+        }}"#,
+        SENDER, SENDER, TOKEN
+    ))
+    .unwrap();
+    let evaluate =
+        |tx: EvmTransactionRequest| constraint.clone().is_satisfied_by(&tx, &state()).is_ok();
+
+    // up to 0.1 ether including fees, and 1000 tokens
+    assert!(evaluate(send(ETHER / 20)));
+    assert!(!evaluate(send(ETHER / 10)));
+    assert!(evaluate(transfer(RECIPIENT, 1000)));
+    assert!(!evaluate(transfer(RECIPIENT, 1001)));
+    // transfers to itself don't change its balance
+    assert!(evaluate(transfer(SENDER, 1_000_000)));
+
+    let revert: EvmTransactionRequest = LegacyTransactionRequest::new()
+        .to(REVERTER)
+        .gas(50_000)
+        .gas_price(GAS_PRICE)
+        .into();
+    assert!(!evaluate(revert.clone()));
+    let allowing_revert = EffectConstraint {
+        allow_revert: true,
+        ..constraint.clone()
+    };
+    assert!(allowing_revert.is_satisfied_by(&revert, &state()).is_ok());
+}
@@ -0,0 +1,172 @@
+//! Decoding of the instructions of well-known programs, and constraints on every instruction of a message.
+
+use alloc::vec;
+
+use entropy_programs_core::{Error as CoreError, SatisfiableForArchitecture};
+use serde::{Deserialize, Serialize};
+
+use crate::message::{CompiledInstruction, SolanaMessage};
+use crate::{Pubkey, Solana};
+
+pub const SYSTEM_PROGRAM: Pubkey = Pubkey::from_str_const("11111111111111111111111111111111");
+pub const COMPUTE_BUDGET_PROGRAM: Pubkey =
+    Pubkey::from_str_const("ComputeBudget111111111111111111111111111111");
+pub const TOKEN_PROGRAM: Pubkey =
+    Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM: Pubkey =
+    Pubkey::from_str_const("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// The System program's `Transfer` and `TransferWithSeed` instructions.
+const SYSTEM_TRANSFER: u32 = 2;
+const SYSTEM_TRANSFER_WITH_SEED: u32 = 11;
+/// The SPL Token programs' `Transfer` and `TransferChecked` instructions.
+const TOKEN_TRANSFER: u8 = 3;
+const TOKEN_TRANSFER_CHECKED: u8 = 12;
+/// The Compute Budget program's `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions.
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// An instruction, decoded as far as policies need it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// A transfer of lamports with the System program.
+    SystemTransfer { destination: Pubkey, lamports: u64 },
+    /// A transfer with the SPL Token or Token-2022 program, to a token account.
+    TokenTransfer {
+        program_id: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+    },
+    /// Sets the compute unit limit of the transaction.
+    SetComputeUnitLimit(u32),
+    /// Sets the price of compute units, in micro-lamports.
+    SetComputeUnitPrice(u64),
+    /// Any other instruction.
+    Other { program_id: Pubkey },
+}
+
+impl Instruction {
+    /// Decodes an instruction of a message. Returns `None` if its program or decoded accounts aren't known.
+    pub fn decode(message: &SolanaMessage, instruction: &CompiledInstruction) -> Option<Self> {
+        let program_id = message.account_key(instruction.program_id_index)?;
+        let account = |index: usize| message.account_key(*instruction.accounts.get(index)?);
+        let data = instruction.data.as_slice();
+
+        let decoded = if program_id == SYSTEM_PROGRAM {
+            let discriminator = data
+                .get(..4)
+                .map(|d| u32::from_le_bytes(d.try_into().unwrap()));
+            let lamports = data
+                .get(4..12)
+                .map(|l| u64::from_le_bytes(l.try_into().unwrap()));
+            match (discriminator, lamports) {
+                (Some(SYSTEM_TRANSFER), Some(lamports)) if data.len() == 12 => {
+                    Some(Instruction::SystemTransfer {
+                        destination: account(1)?,
+                        lamports,
+                    })
+                }
+                (Some(SYSTEM_TRANSFER_WITH_SEED), Some(lamports)) => {
+                    Some(Instruction::SystemTransfer {
+                        destination: account(2)?,
+                        lamports,
+                    })
+                }
+                _ => None,
+            }
+        } else if program_id == TOKEN_PROGRAM || program_id == TOKEN_2022_PROGRAM {
+            let amount = data
+                .get(1..9)
+                .map(|a| u64::from_le_bytes(a.try_into().unwrap()));
+            match (data.first(), amount) {
+                (Some(&TOKEN_TRANSFER), Some(amount)) if data.len() == 9 => {
+                    Some(Instruction::TokenTransfer {
+                        program_id,
+                        destination: account(1)?,
+                        amount,
+                    })
+                }
+                (Some(&TOKEN_TRANSFER_CHECKED), Some(amount)) if data.len() == 10 => {
+                    Some(Instruction::TokenTransfer {
+                        program_id,
+                        destination: account(2)?,
+                        amount,
+                    })
+                }
+                _ => None,
+            }
+        } else if program_id == COMPUTE_BUDGET_PROGRAM {
+            match (data.first(), data.len()) {
+                (Some(&SET_COMPUTE_UNIT_LIMIT), 5) => Some(Instruction::SetComputeUnitLimit(
+                    u32::from_le_bytes(data[1..5].try_into().unwrap()),
+                )),
+                (Some(&SET_COMPUTE_UNIT_PRICE), 9) => Some(Instruction::SetComputeUnitPrice(
+                    u64::from_le_bytes(data[1..9].try_into().unwrap()),
+                )),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        Some(decoded.unwrap_or(Instruction::Other { program_id }))
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        match self {
+            Instruction::SystemTransfer { .. } => SYSTEM_PROGRAM,
+            Instruction::TokenTransfer { program_id, .. } => *program_id,
+            Instruction::SetComputeUnitLimit(_) | Instruction::SetComputeUnitPrice(_) => {
+                COMPUTE_BUDGET_PROGRAM
+            }
+            Instruction::Other { program_id } => *program_id,
+        }
+    }
+
+    /// The destination of transfers, or the program of other instructions. Compute Budget instructions have no
+    /// receiver.
+    pub fn receiver(&self) -> Option<Pubkey> {
+        match self {
+            Instruction::SystemTransfer { destination, .. }
+            | Instruction::TokenTransfer { destination, .. } => Some(*destination),
+            _ if self.program_id() == COMPUTE_BUDGET_PROGRAM => None,
+            _ => Some(self.program_id()),
+        }
+    }
+}
+
+/// Evaluates a constraint against every instruction of a message, so that eg. an
+/// [`Acl`](https://docs.rs/entropy-programs-acl) on the receiver applies to each program called and each transfer
+/// destination. Each instruction is evaluated as the message with only that instruction, so its receiver, value
+/// and calldata are the instruction's.
+///
+/// Compute Budget instructions are skipped, and messages with only those are evaluated as they are.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EveryInstructionConstraint<C> {
+    pub constraint: C,
+}
+
+impl<C> SatisfiableForArchitecture<Solana> for EveryInstructionConstraint<C>
+where
+    C: SatisfiableForArchitecture<Solana> + Clone,
+{
+    fn is_satisfied_by(self, message: &SolanaMessage) -> Result<(), CoreError> {
+        let mut evaluated = false;
+        for instruction in &message.instructions {
+            let is_compute_budget =
+                message.account_key(instruction.program_id_index) == Some(COMPUTE_BUDGET_PROGRAM);
+            if is_compute_budget {
+                continue;
+            }
+            let mut single_instruction = message.clone();
+            single_instruction.instructions = vec![instruction.clone()];
+            self.constraint
+                .clone()
+                .is_satisfied_by(&single_instruction)?;
+            evaluated = true;
+        }
+        if !evaluated {
+            return self.constraint.is_satisfied_by(message);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+//! This includes the Solana `Architecture`, for legacy and v0 transaction messages.
+//!
+//! **Lookup tables must come from a trusted source.** A v0 message only commits to the address of each lookup
+//! table and the indexes it uses, not to the addresses at those indexes. [`LookupTables`] given by the requester
+//! (eg. as auxiliary data) can map an index to any address, so receivers loaded from them can't be relied on by
+//! receiver allowlists.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+use core::str::FromStr;
+
+use entropy_programs_core::{
+    Architecture, Error as CoreError, GetCalldata, GetChainId, GetFee, GetNonce, GetReceiver,
+    GetSender, GetValue, TryParse,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub mod instructions;
+pub mod message;
+
+pub use message::{LookupTables, SolanaMessage};
+
+use instructions::Instruction;
+
+/// Solana "architecture". Values and fees are in lamports.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Solana;
+
+impl Architecture for Solana {
+    type Address = Pubkey;
+    type AddressRaw = [u8; 32];
+    type Value = u64;
+    /// The genesis hash of a cluster, which messages don't include.
+    type ChainId = [u8; 32];
+    type Nonce = u64;
+    type Fee = u64;
+    type TransactionRequest = SolanaMessage;
+}
+
+/// An account address, (de)serialized and displayed in base58.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pubkey(pub [u8; 32]);
+
+impl Pubkey {
+    /// Decodes a base58 address at compile time, panicking if it is invalid.
+    pub const fn from_str_const(address: &str) -> Self {
+        Self(bs58::decode(address.as_bytes()).into_array_const_unwrap())
+    }
+}
+
+impl From<[u8; 32]> for Pubkey {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Pubkey> for [u8; 32] {
+    fn from(pubkey: Pubkey) -> Self {
+        pubkey.0
+    }
+}
+
+impl FromStr for Pubkey {
+    type Err = CoreError;
+
+    fn from_str(address: &str) -> Result<Self, CoreError> {
+        let mut bytes = [0u8; 32];
+        match bs58::decode(address).onto(&mut bytes) {
+            Ok(32) => Ok(Self(bytes)),
+            Ok(len) => Err(CoreError::Evaluation(format!(
+                "Address `{}` should be 32 bytes, but is {}.",
+                address, len
+            ))),
+            Err(e) => Err(CoreError::Evaluation(format!(
+                "Invalid address `{}`: {}",
+                address, e
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Pubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&bs58::encode(self.0).into_string())
+    }
+}
+
+impl fmt::Debug for Pubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Serialize for Pubkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pubkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        Pubkey::from_str(&address)
+            .map_err(|_| serde::de::Error::custom(format!("invalid address `{}`", address)))
+    }
+}
+
+impl GetSender<Solana> for SolanaMessage {
+    /// The fee payer.
+    fn sender(&self) -> Option<Pubkey> {
+        self.account_keys.first().copied()
+    }
+}
+
+impl GetReceiver<Solana> for SolanaMessage {
+    /// The receiver of every instruction if they all have the same one, see [`SolanaMessage::receivers`].
+    fn receiver(&self) -> Option<Pubkey> {
+        match GetReceiver::<Solana>::receivers(self)?.as_slice() {
+            [receiver] => Some(*receiver),
+            _ => None,
+        }
+    }
+
+    /// The receivers of the instructions. `None` if an instruction uses an account that isn't known, or if only
+    /// Compute Budget instructions have been given.
+    fn receivers(&self) -> Option<Vec<Pubkey>> {
+        SolanaMessage::receivers(self).filter(|receivers| !receivers.is_empty())
+    }
+}
+
+impl GetValue<Solana> for SolanaMessage {
+    /// The total amount of the System program transfers.
+    fn value(&self) -> Option<u64> {
+        self.decoded_instructions()?
+            .iter()
+            .try_fold(0u64, |total, instruction| match instruction {
+                Instruction::SystemTransfer { lamports, .. } => total.checked_add(*lamports),
+                _ => Some(total),
+            })
+    }
+}
+
+impl GetChainId<Solana> for SolanaMessage {
+    fn chain_id(&self) -> Option<[u8; 32]> {
+        None
+    }
+}
+
+impl GetCalldata<Solana> for SolanaMessage {
+    /// The instruction data of messages with a single instruction.
+    fn calldata(&self) -> Option<&[u8]> {
+        match self.instructions.as_slice() {
+            [instruction] => Some(&instruction.data),
+            _ => None,
+        }
+    }
+}
+
+impl GetNonce<Solana> for SolanaMessage {
+    /// Messages are made unique by their recent blockhash rather than a nonce.
+    fn nonce(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl GetFee<Solana> for SolanaMessage {
+    /// The maximum fee, ie. the signature fees plus the priority fee set with the Compute Budget program.
+    fn fee(&self) -> Option<u64> {
+        self.max_fee()
+    }
+}
+
+impl TryParse<Solana> for SolanaMessage {
+    /// Parses a serialized message, ie. the bytes that are signed.
+    fn try_parse(raw_message: &[u8]) -> Result<Self, CoreError> {
+        SolanaMessage::from_bytes(raw_message).map_err(|e| {
+            CoreError::InvalidSignatureRequest(format!("Unable to parse Solana message: {}", e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,300 @@
+//! The wire format of legacy and v0 messages, and loading v0 messages' addresses from lookup tables.
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+
+use entropy_programs_core::Error as CoreError;
+use serde::{Deserialize, Serialize};
+
+use crate::instructions::{Instruction, COMPUTE_BUDGET_PROGRAM};
+use crate::Pubkey;
+
+/// The prefix of the first byte of versioned messages.
+const VERSION_PREFIX: u8 = 0x80;
+/// The fee of each signature, in lamports.
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// The compute unit limit of each instruction, if the Compute Budget program doesn't set one.
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+/// The maximum compute unit limit of a transaction.
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+/// The number of micro-lamports in a lamport, the unit of compute unit prices.
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageHeader {
+    pub num_required_signatures: u8,
+    pub num_readonly_signed_accounts: u8,
+    pub num_readonly_unsigned_accounts: u8,
+}
+
+/// An instruction, with its program and accounts as indexes into the message's account keys.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompiledInstruction {
+    pub program_id_index: u8,
+    pub accounts: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+/// The accounts a v0 message loads from an address lookup table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressTableLookup {
+    pub account_key: Pubkey,
+    pub writable_indexes: Vec<u8>,
+    pub readonly_indexes: Vec<u8>,
+}
+
+/// The addresses a v0 message loads from lookup tables, in the order they are indexed after the static account
+/// keys.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadedAddresses {
+    pub writable: Vec<Pubkey>,
+    pub readonly: Vec<Pubkey>,
+}
+
+/// The contents of address lookup tables by their address, as JSON `{ "<table>": ["<address>", ...] }`.
+///
+/// Messages don't commit to the contents of the tables they use, so these must come from a trusted source (eg.
+/// an oracle reading them from the chain). Tables given by the requester can map the message's indexes to any
+/// addresses, eg. to allowlisted ones in place of the real receivers.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LookupTables(pub BTreeMap<Pubkey, Vec<Pubkey>>);
+
+impl LookupTables {
+    /// Parses lookup tables from JSON.
+    pub fn try_parse(aux_data: &[u8]) -> Result<Self, CoreError> {
+        serde_json::from_slice(aux_data).map_err(|e| {
+            CoreError::InvalidSignatureRequest(format!("Unable to parse lookup tables: {}", e))
+        })
+    }
+}
+
+/// A legacy or v0 message, ie. a transaction without its signatures.
+///
+/// Accounts loaded from lookup tables aren't known until [`SolanaMessage::load_addresses`] is called, and until
+/// then the instructions using them have no receiver.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolanaMessage {
+    pub header: MessageHeader,
+    pub account_keys: Vec<Pubkey>,
+    pub recent_blockhash: [u8; 32],
+    pub instructions: Vec<CompiledInstruction>,
+    /// `None` for legacy messages.
+    pub address_table_lookups: Option<Vec<AddressTableLookup>>,
+    #[serde(default)]
+    pub loaded_addresses: Option<LoadedAddresses>,
+}
+
+impl SolanaMessage {
+    /// Parses a serialized message.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader(bytes);
+        let is_versioned = reader.peek()? & VERSION_PREFIX != 0;
+        if is_versioned {
+            let version = reader.byte()? & !VERSION_PREFIX;
+            if version != 0 {
+                return Err(format!("unsupported message version {}", version));
+            }
+        }
+
+        let header = MessageHeader {
+            num_required_signatures: reader.byte()?,
+            num_readonly_signed_accounts: reader.byte()?,
+            num_readonly_unsigned_accounts: reader.byte()?,
+        };
+        let account_keys = reader.vec(|reader| reader.pubkey())?;
+        let recent_blockhash = reader.array()?;
+        let instructions = reader.vec(|reader| {
+            Ok(CompiledInstruction {
+                program_id_index: reader.byte()?,
+                accounts: reader.bytes()?,
+                data: reader.bytes()?,
+            })
+        })?;
+        let address_table_lookups = match is_versioned {
+            true => Some(reader.vec(|reader| {
+                Ok(AddressTableLookup {
+                    account_key: reader.pubkey()?,
+                    writable_indexes: reader.bytes()?,
+                    readonly_indexes: reader.bytes()?,
+                })
+            })?),
+            false => None,
+        };
+        if !reader.0.is_empty() {
+            return Err(format!("{} trailing bytes", reader.0.len()));
+        }
+
+        // there must be a writable signer to pay the fee
+        if header.num_readonly_signed_accounts >= header.num_required_signatures
+            || usize::from(header.num_required_signatures)
+                + usize::from(header.num_readonly_unsigned_accounts)
+                > account_keys.len()
+        {
+            return Err("invalid message header".into());
+        }
+        Ok(Self {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups,
+            loaded_addresses: None,
+        })
+    }
+
+    /// Loads the addresses a v0 message looks up from the given lookup tables, which must contain all the tables
+    /// the message uses. The tables must come from a trusted source, see [`LookupTables`].
+    pub fn load_addresses(&mut self, lookup_tables: &LookupTables) -> Result<(), CoreError> {
+        let mut loaded_addresses = LoadedAddresses::default();
+        for lookup in self.address_table_lookups.iter().flatten() {
+            let table = lookup_tables.0.get(&lookup.account_key).ok_or_else(|| {
+                CoreError::Evaluation(format!("Lookup table {} is missing.", lookup.account_key))
+            })?;
+            let load = |indexes: &[u8], addresses: &mut Vec<Pubkey>| {
+                for index in indexes {
+                    let address = table.get(usize::from(*index)).ok_or_else(|| {
+                        CoreError::Evaluation(format!(
+                            "Lookup table {} has no address at index {}.",
+                            lookup.account_key, index
+                        ))
+                    })?;
+                    addresses.push(*address);
+                }
+                Ok::<_, CoreError>(())
+            };
+            load(&lookup.writable_indexes, &mut loaded_addresses.writable)?;
+            load(&lookup.readonly_indexes, &mut loaded_addresses.readonly)?;
+        }
+        self.loaded_addresses = Some(loaded_addresses);
+        Ok(())
+    }
+
+    /// The account at the given index, which is either a static account key or an address loaded from a lookup
+    /// table. Returns `None` if the index is out of bounds, or the addresses haven't been loaded.
+    pub fn account_key(&self, index: u8) -> Option<Pubkey> {
+        let index = usize::from(index);
+        if let Some(account_key) = self.account_keys.get(index) {
+            return Some(*account_key);
+        }
+        let loaded_addresses = self.loaded_addresses.as_ref()?;
+        loaded_addresses
+            .writable
+            .iter()
+            .chain(&loaded_addresses.readonly)
+            .nth(index - self.account_keys.len())
+            .copied()
+    }
+
+    /// The decoded instructions, or `None` if an instruction uses an account that isn't known.
+    pub fn decoded_instructions(&self) -> Option<Vec<Instruction>> {
+        self.instructions
+            .iter()
+            .map(|instruction| Instruction::decode(self, instruction))
+            .collect()
+    }
+
+    /// The receiver of each instruction (see [`Instruction::receiver`]) without duplicates, or `None` if an
+    /// instruction uses an account that isn't known.
+    pub fn receivers(&self) -> Option<Vec<Pubkey>> {
+        let mut receivers = Vec::new();
+        for receiver in self
+            .decoded_instructions()?
+            .iter()
+            .filter_map(Instruction::receiver)
+        {
+            if !receivers.contains(&receiver) {
+                receivers.push(receiver);
+            }
+        }
+        Some(receivers)
+    }
+
+    /// The maximum fee, ie. the signature fees plus the compute unit limit times the compute unit price. Returns
+    /// `None` if an instruction uses an account that isn't known.
+    pub fn max_fee(&self) -> Option<u64> {
+        let instructions = self.decoded_instructions()?;
+        let mut compute_unit_limit = None;
+        let mut compute_unit_price = 0u64;
+        for instruction in &instructions {
+            match instruction {
+                Instruction::SetComputeUnitLimit(limit) => compute_unit_limit = Some(*limit),
+                Instruction::SetComputeUnitPrice(price) => compute_unit_price = *price,
+                _ => {}
+            }
+        }
+        let compute_unit_limit = compute_unit_limit
+            .map(u64::from)
+            .unwrap_or_else(|| {
+                let num_instructions = instructions
+                    .iter()
+                    .filter(|instruction| instruction.program_id() != COMPUTE_BUDGET_PROGRAM)
+                    .count() as u64;
+                num_instructions * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+            })
+            .min(MAX_COMPUTE_UNIT_LIMIT);
+        let priority_fee = (u128::from(compute_unit_limit) * u128::from(compute_unit_price))
+            .div_ceil(MICRO_LAMPORTS_PER_LAMPORT);
+        u64::from(self.header.num_required_signatures)
+            .checked_mul(LAMPORTS_PER_SIGNATURE)?
+            .checked_add(u64::try_from(priority_fee).ok()?)
+    }
+}
+
+/// Reads the wire format, where vectors are prefixed with their length as a compact-u16.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn peek(&self) -> Result<u8, String> {
+        self.0
+            .first()
+            .copied()
+            .ok_or_else(|| "unexpected end".into())
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = self.peek()?;
+        self.0 = &self.0[1..];
+        Ok(byte)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        if self.0.len() < N {
+            return Err("unexpected end".into());
+        }
+        let (array, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(array.try_into().expect("split at N"))
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey, String> {
+        self.array().map(Pubkey)
+    }
+
+    /// Reads a compact-u16, ie. 7 bits per byte with the high bit set on all but the last byte.
+    fn len(&mut self) -> Result<usize, String> {
+        let mut len = 0usize;
+        for i in 0..3 {
+            let byte = self.byte()?;
+            len |= usize::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                if (i > 0 && byte == 0) || len > usize::from(u16::MAX) {
+                    return Err("invalid compact-u16".into());
+                }
+                return Ok(len);
+            }
+        }
+        Err("invalid compact-u16".into())
+    }
+
+    fn vec<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let len = self.len()?;
+        (0..len).map(|_| item(self)).collect()
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, String> {
+        self.vec(|reader| reader.byte())
+    }
+}
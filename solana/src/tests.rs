@@ -0,0 +1,303 @@
+use alloc::{format, string::ToString, vec, vec::Vec};
+
+use entropy_programs_acl::{Acl, AclKind};
+use entropy_programs_core::SatisfiableForArchitecture;
+
+use super::{
+    instructions::*,
+    message::{AddressTableLookup, CompiledInstruction, LoadedAddresses, MessageHeader},
+    *,
+};
+
+fn pubkey(byte: u8) -> Pubkey {
+    Pubkey([byte; 32])
+}
+
+fn compact_u16(mut len: usize, bytes: &mut Vec<u8>) {
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Serializes a message in the wire format.
+fn serialize(message: &SolanaMessage) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    if message.address_table_lookups.is_some() {
+        bytes.push(0x80);
+    }
+    let header = &message.header;
+    bytes.extend([
+        header.num_required_signatures,
+        header.num_readonly_signed_accounts,
+        header.num_readonly_unsigned_accounts,
+    ]);
+    compact_u16(message.account_keys.len(), &mut bytes);
+    for account_key in &message.account_keys {
+        bytes.extend(account_key.0);
+    }
+    bytes.extend(message.recent_blockhash);
+    compact_u16(message.instructions.len(), &mut bytes);
+    for instruction in &message.instructions {
+        bytes.push(instruction.program_id_index);
+        for vec in [&instruction.accounts, &instruction.data] {
+            compact_u16(vec.len(), &mut bytes);
+            bytes.extend(vec);
+        }
+    }
+    if let Some(lookups) = &message.address_table_lookups {
+        compact_u16(lookups.len(), &mut bytes);
+        for lookup in lookups {
+            bytes.extend(lookup.account_key.0);
+            for vec in [&lookup.writable_indexes, &lookup.readonly_indexes] {
+                compact_u16(vec.len(), &mut bytes);
+                bytes.extend(vec);
+            }
+        }
+    }
+    bytes
+}
+
+fn instruction(program_id_index: u8, accounts: Vec<u8>, data: Vec<u8>) -> CompiledInstruction {
+    CompiledInstruction {
+        program_id_index,
+        accounts,
+        data,
+    }
+}
+
+fn system_transfer(lamports: u64) -> Vec<u8> {
+    let mut data = 2u32.to_le_bytes().to_vec();
+    data.extend(lamports.to_le_bytes());
+    data
+}
+
+fn token_transfer_checked(amount: u64) -> Vec<u8> {
+    let mut data = vec![12];
+    data.extend(amount.to_le_bytes());
+    data.push(6);
+    data
+}
+
+fn set_compute_unit_price(micro_lamports: u64) -> Vec<u8> {
+    let mut data = vec![3];
+    data.extend(micro_lamports.to_le_bytes());
+    data
+}
+
+/// A legacy message transferring lamports from the fee payer (1) to 2, with a priority fee.
+fn legacy_transfer() -> SolanaMessage {
+    SolanaMessage {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 2,
+        },
+        account_keys: vec![pubkey(1), pubkey(2), SYSTEM_PROGRAM, COMPUTE_BUDGET_PROGRAM],
+        recent_blockhash: [9; 32],
+        instructions: vec![
+            instruction(3, vec![], set_compute_unit_price(10_000)),
+            instruction(2, vec![0, 1], system_transfer(1_000_000)),
+        ],
+        address_table_lookups: None,
+        loaded_addresses: None,
+    }
+}
+
+/// A v0 message with an SPL token transfer (from 2 to 4, with mint 3) whose destination is in lookup table 5.
+fn v0_token_transfer() -> SolanaMessage {
+    SolanaMessage {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 1,
+        },
+        account_keys: vec![pubkey(1), pubkey(2), pubkey(3), TOKEN_PROGRAM],
+        recent_blockhash: [9; 32],
+        instructions: vec![instruction(
+            3,
+            vec![1, 2, 4, 0],
+            token_transfer_checked(500),
+        )],
+        address_table_lookups: Some(vec![AddressTableLookup {
+            account_key: pubkey(5),
+            writable_indexes: vec![1],
+            readonly_indexes: vec![],
+        }]),
+        loaded_addresses: None,
+    }
+}
+
+fn acl(kind: AclKind, addresses: &[Pubkey]) -> Acl<[u8; 32]> {
+    Acl {
+        addresses: addresses.iter().map(|address| address.0).collect(),
+        kind,
+        allow_null_recipient: false,
+    }
+}
+
+#[test]
+fn test_can_parse_legacy_messages() {
+    let message = legacy_transfer();
+    let parsed = SolanaMessage::try_parse(&serialize(&message)).unwrap();
+    assert_eq!(parsed, message);
+
+    assert_eq!(GetSender::<Solana>::sender(&parsed), Some(pubkey(1)));
+    // compute budget instructions have no receiver
+    assert_eq!(GetReceiver::<Solana>::receiver(&parsed), Some(pubkey(2)));
+    assert_eq!(parsed.receivers(), Some(vec![pubkey(2)]));
+    assert_eq!(GetValue::<Solana>::value(&parsed), Some(1_000_000));
+    // 5000 lamports for the signature, and 200000 compute units at 0.01 lamports
+    assert_eq!(GetFee::<Solana>::fee(&parsed), Some(7_000));
+    assert_eq!(GetCalldata::<Solana>::calldata(&parsed), None);
+}
+
+#[test]
+fn test_can_parse_v0_messages_with_lookup_tables() {
+    let message = v0_token_transfer();
+    let mut parsed = SolanaMessage::try_parse(&serialize(&message)).unwrap();
+    assert_eq!(parsed, message);
+
+    // the destination is unknown until the lookup table is loaded
+    assert_eq!(GetReceiver::<Solana>::receiver(&parsed), None);
+    assert_eq!(GetReceiver::<Solana>::receivers(&parsed), None);
+    let json = format!(
+        r#"{{ "{}": ["{}", "{}"] }}"#,
+        pubkey(5),
+        pubkey(6),
+        pubkey(4)
+    );
+    let lookup_tables = LookupTables::try_parse(json.as_bytes()).unwrap();
+    parsed.load_addresses(&lookup_tables).unwrap();
+    assert_eq!(
+        parsed.loaded_addresses,
+        Some(LoadedAddresses {
+            writable: vec![pubkey(4)],
+            readonly: vec![],
+        })
+    );
+    assert_eq!(GetReceiver::<Solana>::receiver(&parsed), Some(pubkey(4)));
+    assert_eq!(
+        parsed.decoded_instructions(),
+        Some(vec![Instruction::TokenTransfer {
+            program_id: TOKEN_PROGRAM,
+            destination: pubkey(4),
+            amount: 500,
+        }])
+    );
+    assert_eq!(GetValue::<Solana>::value(&parsed), Some(0));
+    assert_eq!(GetFee::<Solana>::fee(&parsed), Some(5_000));
+    assert_eq!(
+        GetCalldata::<Solana>::calldata(&parsed),
+        Some(token_transfer_checked(500).as_slice())
+    );
+
+    // missing tables and addresses
+    let mut parsed = message.clone();
+    assert!(parsed.load_addresses(&LookupTables::default()).is_err());
+    let short_table = LookupTables([(pubkey(5), vec![pubkey(6)])].into());
+    assert!(parsed.load_addresses(&short_table).is_err());
+}
+
+#[test]
+fn test_rejects_invalid_messages() {
+    let bytes = serialize(&legacy_transfer());
+
+    for invalid in [
+        [bytes.as_slice(), &[0]].concat(),
+        bytes[..bytes.len() - 1].to_vec(),
+        // unsupported version
+        [&[0x81], bytes.as_slice()].concat(),
+        // no writable signer
+        [&[1, 1], &bytes[2..]].concat(),
+        // more signers than accounts
+        [&[5], &bytes[1..]].concat(),
+        // non-canonical account keys length
+        [&bytes[..3], &[0x84, 0x00], &bytes[4..]].concat(),
+        vec![],
+    ] {
+        assert!(SolanaMessage::try_parse(&invalid).is_err());
+    }
+}
+
+#[test]
+fn test_can_parse_addresses() {
+    assert_eq!(SYSTEM_PROGRAM, Pubkey([0; 32]));
+    let address = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    assert_eq!(address.parse::<Pubkey>().unwrap(), TOKEN_PROGRAM);
+    assert_eq!(TOKEN_PROGRAM.to_string(), address);
+    assert_eq!(
+        serde_json::from_str::<Pubkey>(&serde_json::to_string(&TOKEN_PROGRAM).unwrap()).unwrap(),
+        TOKEN_PROGRAM
+    );
+    // too short, and not base58
+    assert!("11111111".parse::<Pubkey>().is_err());
+    assert!("0OIl".parse::<Pubkey>().is_err());
+}
+
+#[test]
+fn test_acls_apply_to_the_receiver_or_every_instruction() {
+    let mut message = legacy_transfer();
+    let allowlist = acl(AclKind::Allow, &[pubkey(2)]);
+    assert!(
+        SatisfiableForArchitecture::<Solana>::is_satisfied_by(allowlist.clone(), &message).is_ok()
+    );
+    assert!(SatisfiableForArchitecture::<Solana>::is_satisfied_by(
+        acl(AclKind::Deny, &[pubkey(2)]),
+        &message
+    )
+    .is_err());
+
+    // an instruction calling another program
+    message.account_keys.push(pubkey(7));
+    message.header.num_readonly_unsigned_accounts += 1;
+    message
+        .instructions
+        .push(instruction(4, vec![0], vec![1, 2, 3]));
+    assert_eq!(message.receivers(), Some(vec![pubkey(2), pubkey(7)]));
+    // every receiver must be allowed
+    assert!(
+        SatisfiableForArchitecture::<Solana>::is_satisfied_by(allowlist.clone(), &message).is_err()
+    );
+    assert!(SatisfiableForArchitecture::<Solana>::is_satisfied_by(
+        acl(AclKind::Allow, &[pubkey(2), pubkey(7)]),
+        &message
+    )
+    .is_ok());
+
+    // messages without known receivers never satisfy receiver ACLs
+    let mut compute_budget_only = legacy_transfer();
+    compute_budget_only.instructions.pop();
+    let unloaded = v0_token_transfer();
+    for message in [compute_budget_only, unloaded] {
+        assert_eq!(GetReceiver::<Solana>::receivers(&message), None);
+        for kind in [AclKind::Allow, AclKind::Deny] {
+            let acl = Acl {
+                allow_null_recipient: true,
+                ..acl(kind, &[pubkey(2)])
+            };
+            assert!(SatisfiableForArchitecture::<Solana>::is_satisfied_by(acl, &message).is_err());
+        }
+    }
+
+    let every_instruction = |constraint: Acl<[u8; 32]>| EveryInstructionConstraint { constraint };
+    assert!(every_instruction(allowlist.clone())
+        .is_satisfied_by(&message)
+        .is_err());
+    assert!(
+        every_instruction(acl(AclKind::Allow, &[pubkey(2), pubkey(7)]))
+            .is_satisfied_by(&message)
+            .is_ok()
+    );
+    assert!(every_instruction(acl(AclKind::Deny, &[pubkey(7)]))
+        .is_satisfied_by(&message)
+        .is_err());
+    assert!(every_instruction(allowlist)
+        .is_satisfied_by(&legacy_transfer())
+        .is_ok());
+}
@@ -0,0 +1,62 @@
+//! Constraints on the call of a signing payload.
+
+use alloc::{format, string::ToString, vec::Vec};
+
+use entropy_programs_core::{Error as CoreError, SatisfiableForArchitecture};
+use serde::{Deserialize, Serialize};
+
+use crate::{SigningPayload, Substrate};
+
+/// A call, or all calls of a pallet, by their indexes in the chain's runtime.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllowedCall {
+    pub pallet_index: u8,
+    /// `None` allows every call of the pallet.
+    #[serde(default)]
+    pub call_index: Option<u8>,
+}
+
+impl AllowedCall {
+    fn allows(&self, pallet_index: u8, call_index: u8) -> bool {
+        self.pallet_index == pallet_index && self.call_index.is_none_or(|c| c == call_index)
+    }
+}
+
+/// Only allows payloads whose call is in the allowlist. Indexes are specific to a chain's runtime, so the
+/// constraint can be limited to chains by their genesis hash.
+///
+/// Only the outer call is checked: allowing calls that dispatch other calls (eg. `Utility.batch` or
+/// `Proxy.proxy`) allows any call through them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallConstraint {
+    pub allowed_calls: Vec<AllowedCall>,
+    /// `None` allows any chain.
+    #[serde(default)]
+    pub allowed_genesis_hashes: Option<Vec<[u8; 32]>>,
+}
+
+impl SatisfiableForArchitecture<Substrate> for CallConstraint {
+    fn is_satisfied_by(self, payload: &SigningPayload) -> Result<(), CoreError> {
+        if let Some(allowed_genesis_hashes) = &self.allowed_genesis_hashes {
+            if !allowed_genesis_hashes.contains(&payload.genesis_hash) {
+                return Err(CoreError::Evaluation(
+                    "Chain is not in the allowlist.".to_string(),
+                ));
+            }
+        }
+        let (pallet_index, call_index) = payload
+            .call_index()
+            .ok_or_else(|| CoreError::Evaluation("Call is too short.".to_string()))?;
+        if !self
+            .allowed_calls
+            .iter()
+            .any(|allowed| allowed.allows(pallet_index, call_index))
+        {
+            return Err(CoreError::Evaluation(format!(
+                "Call {} of pallet {} is not in the allowlist.",
+                call_index, pallet_index
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+//! This includes the Substrate `Architecture`, for extrinsic signing payloads.
+//!
+//! A signing payload is the call followed by the signed extensions' extra and additional data. Its call can't be
+//! told apart from the rest without the chain's metadata, so signature requests carry a [`SigningPayload`] with a
+//! length-prefixed call instead, and [`custom_hash`] hashes the payload it encodes.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{format, vec::Vec};
+
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use codec::{Compact, Decode, DecodeAll, Encode, Error as CodecError, Input, Output};
+use entropy_programs_core::{
+    Architecture, Error as CoreError, GetCalldata, GetChainId, GetFee, GetNonce, GetReceiver,
+    GetSender, GetValue, TryParse,
+};
+use serde::{Deserialize, Serialize};
+
+pub mod calls;
+
+/// Payloads longer than this are hashed before being signed.
+const MAX_UNHASHED_PAYLOAD_LEN: usize = 256;
+
+/// Substrate "architecture". Addresses are 32 byte account ids, and values are in the chain's smallest unit.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Substrate;
+
+impl Architecture for Substrate {
+    type Address = [u8; 32];
+    type AddressRaw = [u8; 32];
+    type Value = u128;
+    /// The genesis hash of the chain.
+    type ChainId = [u8; 32];
+    type Nonce = u32;
+    type Fee = u128;
+    type TransactionRequest = SigningPayload;
+}
+
+/// How long a transaction is valid for, as encoded in its payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Era {
+    Immortal,
+    /// Valid for `period` blocks (a power of two from 4 to 65536) from the block whose number modulo `period` is
+    /// `phase`.
+    Mortal {
+        period: u64,
+        phase: u64,
+    },
+}
+
+impl Encode for Era {
+    fn encode_to<T: Output + ?Sized>(&self, output: &mut T) {
+        match self {
+            Era::Immortal => output.push_byte(0),
+            Era::Mortal { period, phase } => {
+                let quantize_factor = (period >> 12).max(1);
+                let encoded = (period.trailing_zeros().saturating_sub(1)).clamp(1, 15) as u16
+                    | ((phase / quantize_factor) << 4) as u16;
+                encoded.encode_to(output);
+            }
+        }
+    }
+}
+
+impl Decode for Era {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, CodecError> {
+        let first = input.read_byte()?;
+        if first == 0 {
+            return Ok(Era::Immortal);
+        }
+        let encoded = u64::from(first) + (u64::from(input.read_byte()?) << 8);
+        let period = 2 << (encoded % (1 << 4));
+        let quantize_factor = (period >> 12).max(1);
+        let phase = (encoded >> 4) * quantize_factor;
+        if period >= 4 && phase < period {
+            Ok(Era::Mortal { period, phase })
+        } else {
+            Err("Invalid period and phase".into())
+        }
+    }
+}
+
+/// An extrinsic signing payload, for chains with the usual signed extensions: the era, nonce and tip as extra
+/// data, and the spec and transaction versions, genesis hash and era's block hash as additional data.
+///
+/// It is SCALE encoded like the payload, except that the call is prefixed with its length.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct SigningPayload {
+    /// The SCALE encoded call, starting with its pallet and call indexes.
+    pub call: Vec<u8>,
+    pub era: Era,
+    #[codec(compact)]
+    pub nonce: u32,
+    #[codec(compact)]
+    pub tip: u128,
+    pub spec_version: u32,
+    pub transaction_version: u32,
+    pub genesis_hash: [u8; 32],
+    /// The hash of the block the era starts at, or the genesis hash for immortal transactions.
+    pub block_hash: [u8; 32],
+}
+
+impl SigningPayload {
+    /// The payload as the chain encodes it, ie. the call without a length prefix followed by the extra and
+    /// additional data.
+    pub fn encode_payload(&self) -> Vec<u8> {
+        let mut payload = self.call.clone();
+        self.era.encode_to(&mut payload);
+        Compact(self.nonce).encode_to(&mut payload);
+        Compact(self.tip).encode_to(&mut payload);
+        self.spec_version.encode_to(&mut payload);
+        self.transaction_version.encode_to(&mut payload);
+        self.genesis_hash.encode_to(&mut payload);
+        self.block_hash.encode_to(&mut payload);
+        payload
+    }
+
+    /// The bytes that are signed: the payload, or its Blake2b-256 hash if it is longer than 256 bytes.
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let payload = self.encode_payload();
+        match payload.len() > MAX_UNHASHED_PAYLOAD_LEN {
+            true => blake2_256(&payload).to_vec(),
+            false => payload,
+        }
+    }
+
+    /// The pallet and call indexes of the call, or `None` if it is too short.
+    pub fn call_index(&self) -> Option<(u8, u8)> {
+        match self.call.as_slice() {
+            [pallet_index, call_index, ..] => Some((*pallet_index, *call_index)),
+            _ => None,
+        }
+    }
+}
+
+fn blake2_256(data: &[u8]) -> [u8; 32] {
+    Blake2b::<U32>::digest(data).into()
+}
+
+impl GetSender<Substrate> for SigningPayload {
+    /// The signer's account isn't part of the payload.
+    fn sender(&self) -> Option<[u8; 32]> {
+        None
+    }
+}
+
+impl GetReceiver<Substrate> for SigningPayload {
+    /// Calls can't be decoded without the chain's metadata, see [`calls::CallConstraint`] instead.
+    fn receiver(&self) -> Option<[u8; 32]> {
+        None
+    }
+
+    /// The receivers aren't known, so constraints on them are never satisfied.
+    fn receivers(&self) -> Option<Vec<[u8; 32]>> {
+        None
+    }
+}
+
+impl GetValue<Substrate> for SigningPayload {
+    fn value(&self) -> Option<u128> {
+        None
+    }
+}
+
+impl GetChainId<Substrate> for SigningPayload {
+    /// The genesis hash.
+    fn chain_id(&self) -> Option<[u8; 32]> {
+        Some(self.genesis_hash)
+    }
+}
+
+impl GetCalldata<Substrate> for SigningPayload {
+    /// The SCALE encoded call.
+    fn calldata(&self) -> Option<&[u8]> {
+        Some(&self.call)
+    }
+}
+
+impl GetNonce<Substrate> for SigningPayload {
+    fn nonce(&self) -> Option<u32> {
+        Some(self.nonce)
+    }
+}
+
+impl GetFee<Substrate> for SigningPayload {
+    /// The fee depends on the call's weight, which isn't known without the chain. The tip is in
+    /// [`SigningPayload::tip`].
+    fn fee(&self) -> Option<u128> {
+        None
+    }
+}
+
+impl TryParse<Substrate> for SigningPayload {
+    /// Parses a SCALE encoded [`SigningPayload`].
+    fn try_parse(raw_payload: &[u8]) -> Result<Self, CoreError> {
+        SigningPayload::decode_all(&mut &raw_payload[..]).map_err(|e| {
+            CoreError::InvalidSignatureRequest(format!("Unable to parse signing payload: {}", e))
+        })
+    }
+}
+
+/// Computes the hash that ECDSA signers sign for the signing payload in a signature request's message, ie. the
+/// Blake2b-256 hash of [`SigningPayload::signed_bytes`], for use as a program's `custom_hash`.
+pub fn custom_hash(message: &[u8]) -> Option<Vec<u8>> {
+    let payload = SigningPayload::try_parse(message).ok()?;
+    Some(blake2_256(&payload.signed_bytes()).to_vec())
+}
+
+#[cfg(test)]
+mod tests;
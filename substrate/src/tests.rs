@@ -0,0 +1,148 @@
+use alloc::vec;
+
+use entropy_programs_core::SatisfiableForArchitecture;
+
+use super::{calls::CallConstraint, *};
+
+/// A `Balances.transfer_keep_alive` to account 0x01..01 of 12345.
+fn transfer() -> SigningPayload {
+    let mut call = vec![5, 3, 0];
+    call.extend([1; 32]);
+    Compact(12345u128).encode_to(&mut call);
+    SigningPayload {
+        call,
+        era: Era::Mortal {
+            period: 64,
+            phase: 42,
+        },
+        nonce: 7,
+        tip: 10,
+        spec_version: 100,
+        transaction_version: 1,
+        genesis_hash: [2; 32],
+        block_hash: [3; 32],
+    }
+}
+
+#[test]
+fn test_can_encode_eras() {
+    // from sp-runtime
+    for (era, encoded) in [
+        (Era::Immortal, vec![0]),
+        (
+            Era::Mortal {
+                period: 64,
+                phase: 42,
+            },
+            vec![5 + 42 % 16 * 16, 42 / 16],
+        ),
+        (
+            Era::Mortal {
+                period: 32768,
+                phase: 20000,
+            },
+            vec![(14 + 2500 % 16 * 16) as u8, (2500 / 16) as u8],
+        ),
+    ] {
+        assert_eq!(era.encode(), encoded);
+        assert_eq!(Era::decode(&mut &encoded[..]).unwrap(), era);
+    }
+    // a phase outside the period
+    assert!(Era::decode(&mut &[0x05 + 0xf0, 0xff][..]).is_err());
+}
+
+#[test]
+fn test_can_parse_payloads() {
+    let payload = transfer();
+    let parsed = SigningPayload::try_parse(&payload.encode()).unwrap();
+    assert_eq!(parsed, payload);
+
+    assert_eq!(GetChainId::<Substrate>::chain_id(&parsed), Some([2; 32]));
+    assert_eq!(GetNonce::<Substrate>::nonce(&parsed), Some(7));
+    assert_eq!(GetReceiver::<Substrate>::receivers(&parsed), None);
+    assert_eq!(
+        GetCalldata::<Substrate>::calldata(&parsed),
+        Some(payload.call.as_slice())
+    );
+    assert_eq!(parsed.call_index(), Some((5, 3)));
+
+    // trailing bytes
+    assert!(SigningPayload::try_parse(&[payload.encode(), vec![0]].concat()).is_err());
+    assert!(SigningPayload::try_parse(&[]).is_err());
+}
+
+#[test]
+fn test_encodes_and_hashes_payloads() {
+    let payload = transfer();
+    let encoded = payload.encode_payload();
+    // the call without its length prefix, then the extra and additional data
+    let expected = [
+        payload.call.clone(),
+        vec![0xa5, 0x02, 7 << 2, 10 << 2],
+        100u32.to_le_bytes().to_vec(),
+        1u32.to_le_bytes().to_vec(),
+        vec![2; 32],
+        vec![3; 32],
+    ]
+    .concat();
+    assert_eq!(encoded, expected);
+    assert_eq!(payload.signed_bytes(), encoded);
+    assert_eq!(
+        custom_hash(&payload.encode()),
+        Some(blake2_256(&encoded).to_vec())
+    );
+
+    // payloads are hashed once longer than 256 bytes
+    let mut long = payload.clone();
+    let extra_len = encoded.len() - payload.call.len();
+    long.call = vec![0; 256 - extra_len];
+    assert_eq!(long.signed_bytes(), long.encode_payload());
+    long.call.push(0);
+    assert_eq!(
+        long.signed_bytes(),
+        blake2_256(&long.encode_payload()).to_vec()
+    );
+
+    // the well-known hash of no data
+    assert_eq!(
+        blake2_256(b"").to_vec(),
+        hex::decode("0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8").unwrap()
+    );
+}
+
+#[test]
+fn test_allows_configured_calls() {
+    let payload = transfer();
+    let (genesis_hash, other_genesis_hash) = ([2u8; 32], [4u8; 32]);
+    let config = |json: serde_json::Value| serde_json::from_value::<CallConstraint>(json).unwrap();
+
+    let balances = config(serde_json::json!({
+        "allowed_calls": [{ "pallet_index": 5 }],
+    }));
+    assert!(balances.is_satisfied_by(&payload).is_ok());
+    let transfer_keep_alive = config(serde_json::json!({
+        "allowed_calls": [{ "pallet_index": 0, "call_index": 0 }, { "pallet_index": 5, "call_index": 3 }],
+        "allowed_genesis_hashes": [genesis_hash],
+    }));
+    assert!(transfer_keep_alive.is_satisfied_by(&payload).is_ok());
+
+    for constraint in [
+        CallConstraint::default(),
+        config(serde_json::json!({ "allowed_calls": [{ "pallet_index": 5, "call_index": 0 }] })),
+        config(serde_json::json!({ "allowed_calls": [{ "pallet_index": 6 }] })),
+        config(serde_json::json!({
+            "allowed_calls": [{ "pallet_index": 5 }],
+            "allowed_genesis_hashes": [other_genesis_hash],
+        })),
+    ] {
+        assert!(constraint.is_satisfied_by(&payload).is_err());
+    }
+
+    let mut too_short = payload;
+    too_short.call = vec![5];
+    assert!(
+        config(serde_json::json!({ "allowed_calls": [{ "pallet_index": 5 }] }))
+            .is_satisfied_by(&too_short)
+            .is_err()
+    );
+}
@@ -0,0 +1,156 @@
+//! Builds program configs and auxiliary data for the ACLs in `entropy-programs-acl` from a file of hex-encoded
+//! EVM addresses, one per line.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+use clap::{Parser, Subcommand, ValueEnum};
+use codec::Encode;
+use entropy_programs_acl::{AclKind, CoreError, MerkleAcl, MerkleTree, PrivateAcl};
+use entropy_programs_evm::address;
+
+type Address = [u8; 20];
+
+#[derive(Parser)]
+#[command(about, version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the JSON config of a Merkle-root ACL over the addresses in a file.
+    MerkleConfig {
+        /// File of hex-encoded addresses, one per line
+        addresses: PathBuf,
+        #[arg(long, value_enum, default_value_t = Kind::Allow)]
+        kind: Kind,
+        #[arg(long)]
+        allow_null_recipient: bool,
+    },
+    /// Prints the JSON config of a private ACL over salted hashes of the addresses in a file.
+    PrivateConfig {
+        /// File of hex-encoded addresses, one per line
+        addresses: PathBuf,
+        #[arg(long, value_enum, default_value_t = Kind::Allow)]
+        kind: Kind,
+        #[arg(long)]
+        allow_null_recipient: bool,
+        /// Hex-encoded 32-byte salt. A random salt is used if not given.
+        #[arg(long)]
+        salt: Option<String>,
+    },
+    /// Prints the proof for an address, to be passed as auxiliary data to a Merkle-root ACL.
+    MerkleProof {
+        /// File of hex-encoded addresses, one per line
+        addresses: PathBuf,
+        /// Hex-encoded address to build an inclusion or exclusion proof for
+        address: String,
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Kind {
+    Allow,
+    Deny,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// JSON
+    Json,
+    /// Hex-encoded SCALE
+    Scale,
+}
+
+impl From<Kind> for AclKind {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Allow => AclKind::Allow,
+            Kind::Deny => AclKind::Deny,
+        }
+    }
+}
+
+/// Parses an address with [`address::parse_address`], so mistyped (mixed case) addresses are rejected.
+fn parse_address(address: &str) -> anyhow::Result<Address> {
+    address::parse_address(address)
+        .map(|address| address.0)
+        .map_err(|e| match e {
+            CoreError::Evaluation(message) => anyhow!(message),
+            e => anyhow!("{}", e),
+        })
+}
+
+fn parse_salt(salt: Option<String>) -> anyhow::Result<[u8; 32]> {
+    match salt {
+        Some(salt) => hex::decode(salt.trim_start_matches("0x"))
+            .context("Invalid hex salt")?
+            .try_into()
+            .map_err(|_| anyhow!("Salt is not 32 bytes")),
+        None => {
+            let mut salt = [0u8; 32];
+            getrandom::getrandom(&mut salt)
+                .map_err(|e| anyhow!("Unable to generate salt: {}", e))?;
+            Ok(salt)
+        }
+    }
+}
+
+fn read_addresses(path: &PathBuf) -> anyhow::Result<Vec<Address>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Unable to read {}", path.display()))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| parse_address(line.trim()))
+        .collect()
+}
+
+fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::MerkleConfig {
+            addresses,
+            kind,
+            allow_null_recipient,
+        } => {
+            let tree = MerkleTree::new(read_addresses(&addresses)?);
+            let config = MerkleAcl {
+                root: tree.root(),
+                kind: kind.into(),
+                allow_null_recipient,
+            };
+            println!("{}", serde_json::to_string(&config)?);
+        }
+        Command::PrivateConfig {
+            addresses,
+            kind,
+            allow_null_recipient,
+            salt,
+        } => {
+            let config = PrivateAcl::new(
+                parse_salt(salt)?,
+                read_addresses(&addresses)?,
+                kind.into(),
+                allow_null_recipient,
+            );
+            println!("{}", serde_json::to_string(&config)?);
+        }
+        Command::MerkleProof {
+            addresses,
+            address,
+            format,
+        } => {
+            let tree = MerkleTree::new(read_addresses(&addresses)?);
+            let proof = tree.proof(&parse_address(address.trim())?);
+            match format {
+                Format::Json => println!("{}", serde_json::to_string(&proof)?),
+                Format::Scale => println!("0x{}", hex::encode(proof.encode())),
+            }
+        }
+    }
+    Ok(())
+}